@@ -1,14 +1,21 @@
 #!/bin/bash
 
 # Azure Search Service credentials...
+# The API only reads from the index, so it gets a query key rather than an admin key.
+# api_key is kept until the deployment reads query_key instead.
 API_KEY=$(az search admin-key show \
     --resource-group mhra-products-development \
     --service-name mhraproductsdevelopment \
     --output tsv --query 'primaryKey')
+QUERY_KEY=$(az search query-key list \
+    --resource-group mhra-products-development \
+    --service-name mhraproductsdevelopment \
+    --output tsv --query '[0].key')
 kubectl create secret generic search-creds \
     -n medicines-api \
     -o json \
     --dry-run \
-    --from-literal api_key="$API_KEY" |
+    --from-literal api_key="$API_KEY" \
+    --from-literal query_key="$QUERY_KEY" |
     kubeseal \
         --format yaml >SealedSecret-search-creds.yaml
@@ -1,15 +1,29 @@
-use search_client::AzureSearchClient;
+use crate::search_analytics;
+use search_client::{CachedSearch, InstrumentedSearch, ReadOnlySearchClient};
 
+type ApiSearchClient = InstrumentedSearch<CachedSearch<ReadOnlySearchClient>>;
+
+// The API only ever reads from the indexes, so its clients can't write to them. Searches are
+// recorded before the cache, so that repeated searches are counted too.
 pub struct AzureContext {
-    pub products_client: AzureSearchClient,
-    pub bmgf_client: AzureSearchClient,
+    pub products_client: ApiSearchClient,
+    pub bmgf_client: ApiSearchClient,
 }
 
 pub fn create_context(products_index: String, bmgf_index: String) -> AzureContext {
-    let products_client = AzureSearchClient::new_with_index(products_index);
-    let bmgf_client = AzureSearchClient::new_with_index(bmgf_index);
+    let recorder = search_analytics::sink_from_env();
+    let client = |index: String| {
+        let client = InstrumentedSearch::new(
+            CachedSearch::new(ReadOnlySearchClient::new_with_index(index.clone())),
+            &index,
+        );
+        match &recorder {
+            Some(recorder) => client.with_recorder(recorder.clone()),
+            None => client,
+        }
+    };
     AzureContext {
-        products_client,
-        bmgf_client,
+        products_client: client(products_index),
+        bmgf_client: client(bmgf_index),
     }
 }
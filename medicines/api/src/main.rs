@@ -18,6 +18,7 @@ mod azure_context;
 mod pagination;
 mod query_objects;
 mod schema;
+mod search_analytics;
 
 const PORT: u16 = 8000;
 
@@ -1,4 +1,19 @@
 use async_graphql::SimpleObject;
+use search_client::{AzurePagination, Search, SearchOptions};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+const DEFAULT_PAGE_SIZE: i32 = 10;
+// Azure returns at most 1000 results from a search, however many are asked for.
+const MAX_PAGE_SIZE: i32 = 1000;
+
+// Bump this when the cursor format changes, e.g. to search-after keys.
+const CURSOR_VERSION: &str = "v1";
 
 // Based upon: https://relay.dev/graphql/connections.htm#sec-undefined.PageInfo
 #[SimpleObject]
@@ -6,17 +21,23 @@ use async_graphql::SimpleObject;
 pub struct PageInfo {
     pub has_previous_page: bool,
     pub has_next_page: bool,
-    pub start_cursor: String,
-    pub end_cursor: String,
+    pub start_cursor: Option<String>,
+    pub end_cursor: Option<String>,
 }
 
 impl PageInfo {
-    pub fn build(offset: i32, result_count: i32, total_count: i32) -> Self {
-        let has_previous_page = offset != 0;
+    pub fn build(query: &QueryKey, offset: i32, result_count: i32, total_count: i32) -> Self {
+        let has_previous_page = offset > 0 && total_count > 0;
         let has_next_page = offset + result_count < total_count;
-        let start_cursor = base64::encode(offset.to_string());
-        let end_cursor =
-            base64::encode(std::cmp::min(total_count, offset + result_count - 1).to_string());
+        // There are no cursors for a page without any edges.
+        let (start_cursor, end_cursor) = if result_count > 0 {
+            (
+                Some(query.cursor(offset)),
+                Some(query.cursor(offset + result_count - 1)),
+            )
+        } else {
+            (None, None)
+        };
 
         PageInfo {
             has_previous_page,
@@ -27,21 +48,235 @@ impl PageInfo {
     }
 }
 
-pub fn get_offset_or_default(skip: Option<i32>, after: Option<String>, default: i32) -> i32 {
-    match (after, skip) {
-        (Some(encoded), _) => match convert_after_to_offset(encoded) {
-            Ok(a) => a,
-            _ => default,
-        },
-        (None, Some(offset)) => offset,
-        _ => default,
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaginationError {
+    InvalidCursor,
+    UnsupportedCursorVersion(String),
+    CursorForAnotherQuery,
+    NegativePageSize,
+    UnknownTotalCount,
+}
+
+impl Display for PaginationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PaginationError::InvalidCursor => write!(f, "The cursor isn't one we gave out"),
+            PaginationError::UnsupportedCursorVersion(version) => write!(
+                f,
+                "Cursors of version {} are no longer supported, please start from the first page",
+                version
+            ),
+            PaginationError::CursorForAnotherQuery => write!(
+                f,
+                "The cursor is for a different search, filters or order, please start from the first page"
+            ),
+            PaginationError::NegativePageSize => write!(f, "`first` and `last` can't be negative"),
+            PaginationError::UnknownTotalCount => {
+                write!(f, "The number of results is needed to page back from the end")
+            }
+        }
+    }
+}
+
+impl Error for PaginationError {}
+
+/// Identifies everything about a query that decides which results it has and their order,
+/// so that its cursors can't be used to page through another one.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QueryKey(String);
+
+impl QueryKey {
+    /// The key of a search for `search`, narrowed to the given values of each filtered field
+    /// (`None` for a field that isn't filtered), and ranked by `scoring_profile`. Filters and
+    /// their values are sorted, so the same filters given in another order have the same key.
+    pub fn new(
+        search: &str,
+        filters: &[(&str, Option<Vec<String>>)],
+        scoring_profile: Option<&str>,
+    ) -> Self {
+        let filters = filters
+            .iter()
+            .filter_map(|(field, values)| {
+                let values = values.as_ref()?.iter().collect::<BTreeSet<_>>();
+                Some((*field, values))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let query = json!({
+            "search": search.trim(),
+            "filters": filters,
+            "scoring_profile": scoring_profile,
+        });
+        Self(digest(&query.to_string()))
+    }
+
+    /// A cursor is the version, the offset and a digest of both with this key. It is
+    /// tamper-evident rather than signed, as `skip` can reach any offset anyway.
+    pub fn cursor(&self, offset: i32) -> String {
+        base64::encode(format!(
+            "{}:{}:{}",
+            CURSOR_VERSION,
+            offset,
+            self.check(offset)
+        ))
+    }
+
+    pub fn offset_of(&self, cursor: &str) -> Result<i32, PaginationError> {
+        let bytes = base64::decode(cursor).map_err(|_| PaginationError::InvalidCursor)?;
+        let decoded = std::str::from_utf8(&bytes).map_err(|_| PaginationError::InvalidCursor)?;
+
+        match decoded.split(':').collect::<Vec<_>>().as_slice() {
+            [CURSOR_VERSION, offset, check] => {
+                let offset = offset
+                    .parse::<i32>()
+                    .map_err(|_| PaginationError::InvalidCursor)?;
+                if *check == self.check(offset) {
+                    Ok(offset)
+                } else {
+                    Err(PaginationError::CursorForAnotherQuery)
+                }
+            }
+            [version, _, _] => Err(PaginationError::UnsupportedCursorVersion(
+                version.to_string(),
+            )),
+            // Before cursors were versioned they were just the offset.
+            [offset] if offset.parse::<i32>().is_ok() => {
+                Err(PaginationError::UnsupportedCursorVersion("v0".to_string()))
+            }
+            _ => Err(PaginationError::InvalidCursor),
+        }
+    }
+
+    fn check(&self, offset: i32) -> String {
+        digest(&format!("{}:{}:{}", CURSOR_VERSION, offset, self.0))[..16].to_string()
     }
 }
 
-pub fn convert_after_to_offset(encoded: String) -> Result<i32, anyhow::Error> {
-    let bytes = base64::decode(encoded)?;
-    let string = std::str::from_utf8(&bytes)?;
-    Ok(string.parse::<i32>()? + 1)
+/// The values of a filter argument, as `QueryKey::new` takes them.
+pub fn filter_values<T: Display>(values: &Option<Vec<T>>) -> Option<Vec<String>> {
+    values
+        .as_ref()
+        .map(|values| values.iter().map(ToString::to_string).collect())
+}
+
+fn digest(text: &str) -> String {
+    let mut hash = sha1::Sha1::new();
+    hash.update(text.as_bytes());
+    hash.digest().to_string()
+}
+
+/// The arguments of a paginated field: Relay's `first`/`after` and `last`/`before`, and our
+/// own `skip` (or `offset`), which stands in for `after` when no cursor is given, along with
+/// the query the cursors belong to.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PageRequest {
+    pub first: Option<i32>,
+    pub after: Option<String>,
+    pub last: Option<i32>,
+    pub before: Option<String>,
+    pub skip: Option<i32>,
+    pub query: QueryKey,
+}
+
+/// The `count` results starting at `offset` that make up a page.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Window {
+    pub offset: i32,
+    pub count: i32,
+}
+
+impl PageRequest {
+    /// Whether the page is counted back from the end of the results, so the number of
+    /// results has to be known to find it.
+    pub fn needs_total_count(&self) -> bool {
+        self.last.is_some() && self.before.is_none()
+    }
+
+    /// Narrows the results to those between the cursors, then takes the `first` or `last`
+    /// of those, as https://relay.dev/graphql/connections.htm#sec-Pagination-algorithm does.
+    /// Without either, a page of 10 is taken from the start (or from the end, with `before`).
+    /// Pages are at most 1000 results long.
+    pub fn window(&self, total_count: Option<i32>) -> Result<Window, PaginationError> {
+        if self.first.unwrap_or(0) < 0 || self.last.unwrap_or(0) < 0 {
+            return Err(PaginationError::NegativePageSize);
+        }
+
+        let mut start = match (&self.after, self.skip) {
+            (Some(after), _) => self.query.offset_of(after)?.saturating_add(1),
+            (None, Some(skip)) => skip,
+            _ => 0,
+        }
+        .max(0);
+        let mut end = match &self.before {
+            Some(before) => Some(self.query.offset_of(before)?),
+            None => total_count,
+        }
+        .map(|end| end.max(start));
+
+        let (first, last) = match (self.first, self.last, &self.before) {
+            (None, None, None) => (Some(DEFAULT_PAGE_SIZE), None),
+            (None, None, Some(_)) => (None, Some(DEFAULT_PAGE_SIZE)),
+            (first, last, _) => (first, last),
+        };
+        let first = first.map(|first| first.min(MAX_PAGE_SIZE));
+        let last = last.map(|last| last.min(MAX_PAGE_SIZE));
+        if let Some(first) = first {
+            let first_end = start.saturating_add(first);
+            end = Some(end.map_or(first_end, |end| end.min(first_end)));
+        }
+        let end = end.ok_or(PaginationError::UnknownTotalCount)?;
+        if let Some(last) = last {
+            start = start.max(end - last);
+        }
+
+        Ok(Window {
+            offset: start,
+            count: end - start,
+        })
+    }
+}
+
+/// Searches for the page of results asked for, first counting the results if the page is
+/// counted back from the end of them.
+pub async fn search_page<T>(
+    client: &impl Search,
+    options: SearchOptions,
+    page: &PageRequest,
+) -> Result<(T, Window), anyhow::Error>
+where
+    T: DeserializeOwned,
+{
+    let total_count = if page.needs_total_count() {
+        let counted: Value = client
+            .search(options.clone().top(0).include_count(true))
+            .await?;
+        counted["@odata.count"].as_i64().map(|count| count as i32)
+    } else {
+        None
+    };
+    let window = page.window(total_count)?;
+
+    let options = options
+        .pagination(AzurePagination {
+            result_count: window.count,
+            offset: window.offset,
+        })
+        .include_count(true);
+    let results = client.search(options).await?;
+
+    Ok((results, window))
+}
+
+/// Takes the page asked for from results that are already in memory, so that they are paged
+/// just as they would be by a search.
+pub fn page_of<T>(items: Vec<T>, page: &PageRequest) -> Result<(Vec<T>, Window), PaginationError> {
+    let window = page.window(Some(items.len() as i32))?;
+    let items = items
+        .into_iter()
+        .skip(window.offset as usize)
+        .take(window.count as usize)
+        .collect();
+
+    Ok((items, window))
 }
 
 #[macro_export]
@@ -90,14 +325,245 @@ macro_rules! pagination {
 #[cfg(test)]
 mod test {
     use super::*;
+    use search_client::InMemorySearchClient;
+    use serde_json::json;
+    use test_case::test_case;
+    use tokio_test::block_on;
+
+    fn given_a_query() -> QueryKey {
+        QueryKey::new("ibuprofen", &[("doc_type", Some(vec!["Spc".into()]))], None)
+    }
+
+    fn page(
+        first: Option<i32>,
+        after: Option<i32>,
+        last: Option<i32>,
+        before: Option<i32>,
+    ) -> PageRequest {
+        let query = given_a_query();
+        PageRequest {
+            first,
+            after: after.map(|offset| query.cursor(offset)),
+            last,
+            before: before.map(|offset| query.cursor(offset)),
+            skip: None,
+            query,
+        }
+    }
 
     #[test]
     fn test_page_info() {
-        let page_info = PageInfo::build(0, 10, 15);
+        let query = given_a_query();
+        let page_info = PageInfo::build(&query, 0, 10, 15);
+
+        assert!(page_info.has_next_page);
+        assert!(!page_info.has_previous_page);
+        assert_eq!(Some(query.cursor(0)), page_info.start_cursor);
+        assert_eq!(Some(query.cursor(9)), page_info.end_cursor);
+    }
+
+    #[test]
+    fn test_page_info_of_the_last_page() {
+        let query = given_a_query();
+        let page_info = PageInfo::build(&query, 10, 5, 15);
+
+        assert!(!page_info.has_next_page);
+        assert!(page_info.has_previous_page);
+        assert_eq!(Some(query.cursor(10)), page_info.start_cursor);
+        assert_eq!(Some(query.cursor(14)), page_info.end_cursor);
+    }
+
+    #[test_case(0, 0; "when there are no results")]
+    #[test_case(20, 15; "when the page is past the end")]
+    fn test_page_info_without_edges_has_no_cursors(offset: i32, total_count: i32) {
+        let page_info = PageInfo::build(&given_a_query(), offset, 0, total_count);
+
+        assert_eq!(None, page_info.start_cursor);
+        assert_eq!(None, page_info.end_cursor);
+        assert!(!page_info.has_next_page);
+        assert_eq!(total_count > 0, page_info.has_previous_page);
+    }
+
+    #[test_case(0)]
+    #[test_case(9)]
+    #[test_case(1229; "for as late as page 124")]
+    fn test_cursors_round_trip(offset: i32) {
+        let query = given_a_query();
+
+        assert_eq!(query.offset_of(&query.cursor(offset)), Ok(offset));
+    }
+
+    #[test]
+    fn test_cursors_are_versioned() {
+        let cursor = base64::decode(given_a_query().cursor(9)).unwrap();
+
+        assert!(String::from_utf8(cursor).unwrap().starts_with("v1:9:"));
+    }
+
+    #[test]
+    fn test_cursors_for_another_query_are_rejected() {
+        let cursor = given_a_query().cursor(9);
+        let another_query =
+            QueryKey::new("ibuprofen", &[("doc_type", Some(vec!["Pil".into()]))], None);
+
+        assert_eq!(
+            another_query.offset_of(&cursor),
+            Err(PaginationError::CursorForAnotherQuery)
+        );
+    }
+
+    #[test]
+    fn test_query_keys_ignore_the_order_of_filters() {
+        let key =
+            |filters: &[(&str, Option<Vec<String>>)]| QueryKey::new("ibuprofen", filters, None);
+        let doc_types = Some(vec!["Spc".to_string(), "Pil".to_string()]);
+        let territories = Some(vec!["UK".to_string()]);
 
-        assert_eq!(true, page_info.has_next_page);
-        assert_eq!(false, page_info.has_previous_page);
-        assert_eq!("MA==", page_info.start_cursor);
-        assert_eq!("OQ==", page_info.end_cursor);
+        assert_eq!(
+            key(&[
+                ("doc_type", doc_types.clone()),
+                ("territory", territories.clone())
+            ]),
+            key(&[
+                ("territory", territories),
+                ("doc_type", Some(vec!["Pil".to_string(), "Spc".to_string()]))
+            ])
+        );
+        assert_eq!(key(&[("doc_type", None)]), key(&[]));
+        assert!(key(&[("doc_type", Some(vec![]))]) != key(&[]));
+        assert!(key(&[("doc_type", doc_types.clone())]) != key(&[("territory", doc_types)]));
+    }
+
+    #[test]
+    fn test_query_keys_depend_on_the_search_and_scoring_profile() {
+        let key = |search: &str, profile: Option<&str>| QueryKey::new(search, &[], profile);
+
+        assert_eq!(key("ibuprofen", None), key(" ibuprofen ", None));
+        assert!(key("ibuprofen", None) != key("paracetamol", None));
+        assert!(key("ibuprofen", None) != key("ibuprofen", Some("preferRecent")));
+    }
+
+    #[test]
+    fn test_cursors_with_another_offset_are_rejected() {
+        let query = given_a_query();
+        let cursor = String::from_utf8(base64::decode(query.cursor(9)).unwrap()).unwrap();
+        let tampered = base64::encode(cursor.replacen(":9:", ":90:", 1));
+
+        assert_eq!(
+            query.offset_of(&tampered),
+            Err(PaginationError::CursorForAnotherQuery)
+        );
+    }
+
+    #[test_case("OQ==", PaginationError::UnsupportedCursorVersion("v0".to_string()); "an unversioned offset")]
+    #[test_case(&base64::encode("v9:9:abc"), PaginationError::UnsupportedCursorVersion("v9".to_string()); "a later version")]
+    #[test_case("not base64!", PaginationError::InvalidCursor; "garbage")]
+    #[test_case(&base64::encode("v1:nine:abc"), PaginationError::InvalidCursor; "a malformed offset")]
+    fn test_invalid_cursors_are_rejected(cursor: &str, expected: PaginationError) {
+        assert_eq!(given_a_query().offset_of(cursor), Err(expected));
+    }
+
+    #[test_case(page(None, None, None, None), Some(15), 0, 10; "the first 10 by default")]
+    #[test_case(page(Some(5), None, None, None), None, 0, 5; "the first few")]
+    #[test_case(page(Some(5), Some(9), None, None), None, 10, 5; "the first few after a cursor")]
+    #[test_case(page(None, None, Some(5), None), Some(12), 7, 5; "the last few")]
+    #[test_case(page(None, None, Some(20), None), Some(12), 0, 12; "more than there are")]
+    #[test_case(page(None, None, Some(5), Some(10)), None, 5, 5; "the last few before a cursor")]
+    #[test_case(page(None, None, None, Some(4)), None, 0, 4; "up to 10 before a cursor")]
+    #[test_case(page(None, Some(2), Some(5), Some(5)), None, 3, 2; "between two cursors")]
+    #[test_case(page(Some(5), None, Some(2), None), Some(12), 3, 2; "the last of the first")]
+    #[test_case(page(Some(5000), None, None, None), None, 0, 1000; "no more than 1000")]
+    #[test_case(page(None, None, Some(5000), None), Some(3000), 2000, 1000; "no more than the last 1000")]
+    #[test_case(page(Some(i32::MAX), Some(i32::MAX - 1), None, None), None, i32::MAX, 0; "after the last offset there is")]
+    fn test_window(page: PageRequest, total_count: Option<i32>, offset: i32, count: i32) {
+        assert_eq!(page.window(total_count).unwrap(), Window { offset, count });
+    }
+
+    #[test_case(Some(10), Some(-1), 0; "matches after when both are provided")]
+    #[test_case(Some(10), None, 10; "matches skip when only skip is provided")]
+    #[test_case(None, Some(-1), 0; "matches after when only after is provided")]
+    #[test_case(None, None, 0; "matches the start when neither are provided")]
+    #[test_case(Some(i32::MAX), None, i32::MAX; "stays in range after the largest skip")]
+    fn test_window_offset(skip: Option<i32>, after: Option<i32>, expected: i32) {
+        let query = given_a_query();
+        let page = PageRequest {
+            after: after.map(|offset| query.cursor(offset)),
+            skip,
+            query,
+            ..PageRequest::default()
+        };
+
+        assert_eq!(page.window(Some(100)).unwrap().offset, expected);
+    }
+
+    #[test]
+    fn test_window_rejects_negative_sizes() {
+        assert_eq!(
+            page(Some(-1), None, None, None).window(None),
+            Err(PaginationError::NegativePageSize)
+        );
+        assert_eq!(
+            page(None, None, Some(-1), None).window(Some(10)),
+            Err(PaginationError::NegativePageSize)
+        );
+    }
+
+    #[test]
+    fn test_window_rejects_cursors_for_another_query() {
+        let page = PageRequest {
+            after: Some(given_a_query().cursor(9)),
+            query: QueryKey::new(
+                "paracetamol",
+                &[("doc_type", Some(vec!["Spc".into()]))],
+                None,
+            ),
+            ..PageRequest::default()
+        };
+
+        assert_eq!(
+            page.window(Some(100)),
+            Err(PaginationError::CursorForAnotherQuery)
+        );
+    }
+
+    #[test]
+    fn test_page_of_pages_backwards_through_a_vector() {
+        let items = (0..12).collect::<Vec<_>>();
+
+        let (items, window) = page_of(items, &page(None, None, Some(5), Some(10))).unwrap();
+
+        assert_eq!(items, vec![5, 6, 7, 8, 9]);
+        assert_eq!(
+            window,
+            Window {
+                offset: 5,
+                count: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_search_page_counts_the_results_to_page_back_from_the_end() {
+        let documents = (0..12)
+            .map(|i| json!({ "metadata_storage_name": i.to_string(), "title": "Ibuprofen" }))
+            .collect();
+        let client = InMemorySearchClient::from_documents("products-index", documents);
+
+        let (results, window) = block_on(search_page::<Value>(
+            &client,
+            SearchOptions::new("ibuprofen"),
+            &page(None, None, Some(5), None),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            window,
+            Window {
+                offset: 7,
+                count: 5
+            }
+        );
+        assert_eq!(results["value"].as_array().unwrap().len(), 5);
+        assert_eq!(results["@odata.count"], 12);
     }
 }
@@ -1,13 +1,17 @@
 use crate::{
     azure_context::AzureContext,
-    pagination::get_offset_or_default,
+    pagination::{PageRequest, QueryKey},
     query_objects::medicine_levels_in_pregnancy::{
         report::{get_reports, Reports},
         substance::{get_substance, SubstanceReports},
     },
-    query_objects::shared::substances_index::{get_substances_index, SubstanceIndex},
+    query_objects::shared::{
+        relevance::ReportRelevance,
+        search_error::to_field_error,
+        substances_index::{get_substances_index, SubstanceIndex},
+        suggestions::{get_autocompletions, get_suggestions},
+    },
 };
-use anyhow::anyhow;
 use async_graphql::{Context, FieldResult, Object};
 
 pub struct MedicineLevelsInPregnancy {}
@@ -21,10 +25,7 @@ impl MedicineLevelsInPregnancy {
         name: Option<String>,
     ) -> FieldResult<SubstanceReports> {
         match name {
-            Some(name) => get_substance(name).await.map_err(|e| {
-                tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                anyhow!("Error retrieving results").into()
-            }),
+            Some(name) => get_substance(name).await.map_err(to_field_error),
             None => Err(anyhow::anyhow!(
                 "Getting a substance without providing a substance name is not supported."
             )
@@ -42,12 +43,10 @@ impl MedicineLevelsInPregnancy {
         let context = context.data::<AzureContext>()?;
         get_substances_index(&context.bmgf_client, letter.chars().next().unwrap())
             .await
-            .map_err(|e| {
-                tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                anyhow!("Error retrieving results").into()
-            })
+            .map_err(to_field_error)
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[field(desc = "Reports related to medicine levels in pregnancy")]
     async fn reports(
         &self,
@@ -56,22 +55,60 @@ impl MedicineLevelsInPregnancy {
         first: Option<i32>,
         skip: Option<i32>,
         after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+        relevance_profile: Option<ReportRelevance>,
     ) -> FieldResult<Reports> {
         let context = context.data::<AzureContext>()?;
-        let offset = get_offset_or_default(skip, after, 0);
+        let relevance = relevance_profile.unwrap_or_default();
+        let page = PageRequest {
+            first,
+            after,
+            last,
+            before,
+            skip,
+            query: QueryKey::new(
+                search.as_deref().unwrap_or_default(),
+                &[],
+                relevance.scoring_profile(),
+            ),
+        };
 
         get_reports(
             &context.bmgf_client,
             search.as_deref().unwrap_or(" "),
-            first,
-            offset,
+            relevance,
+            &page,
             None,
         )
         .await
         .map(Into::into)
-        .map_err(|e| {
-            tracing::error!("Error fetching results from Azure search service: {:?}", e);
-            anyhow!("Error retrieving results").into()
-        })
+        .map_err(to_field_error)
+    }
+
+    #[field(desc = "Suggested titles, products and active substances matching the search term")]
+    async fn suggestions(
+        &self,
+        context: &Context<'_>,
+        search: String,
+        first: Option<i32>,
+    ) -> FieldResult<Vec<String>> {
+        let context = context.data::<AzureContext>()?;
+        get_suggestions(&context.bmgf_client, &search, first)
+            .await
+            .map_err(to_field_error)
+    }
+
+    #[field(desc = "Completions of the last word of the search term")]
+    async fn autocomplete(
+        &self,
+        context: &Context<'_>,
+        search: String,
+        first: Option<i32>,
+    ) -> FieldResult<Vec<String>> {
+        let context = context.data::<AzureContext>()?;
+        get_autocompletions(&context.bmgf_client, &search, first)
+            .await
+            .map_err(to_field_error)
     }
 }
@@ -1,8 +1,13 @@
-use crate::{pagination, pagination::PageInfo};
+use crate::{
+    pagination,
+    pagination::{search_page, PageInfo, PageRequest, QueryKey},
+    query_objects::shared::relevance::ReportRelevance,
+};
 use async_graphql::SimpleObject;
 use search_client::{
+    filter::{field, Filter},
     models::{ReportResult, ReportResults},
-    Search,
+    Search, SearchOptions,
 };
 
 #[SimpleObject(desc = "A report related to medicine levels in pregnancy")]
@@ -58,24 +63,29 @@ impl From<ReportResult> for Report {
 
 pagination! {Reports, ReportEdge, Report}
 
-fn get_report_edges(reports: Vec<Report>, offset: i32) -> Vec<ReportEdge> {
+fn get_report_edges(reports: Vec<Report>, offset: i32, query: &QueryKey) -> Vec<ReportEdge> {
     reports
         .into_iter()
         .enumerate()
         .map(|(i, report)| ReportEdge {
             node: report,
-            cursor: base64::encode((i as i32 + offset).to_string()),
+            cursor: query.cursor(i as i32 + offset),
         })
         .collect()
 }
 
-fn get_reports_from_edges(edges: Vec<ReportEdge>, offset: i32, total_count: i32) -> Reports {
+fn get_reports_from_edges(
+    edges: Vec<ReportEdge>,
+    offset: i32,
+    total_count: i32,
+    query: &QueryKey,
+) -> Reports {
     let result_count = edges.len() as i32;
 
     Reports {
         edges,
         total_count,
-        page_info: PageInfo::build(offset, result_count, total_count),
+        page_info: PageInfo::build(query, offset, result_count, total_count),
     }
 }
 
@@ -83,48 +93,52 @@ pub fn get_reports_graph_from_reports_vector(
     reports: Vec<Report>,
     offset: i32,
     total_count: i32,
+    query: &QueryKey,
 ) -> Reports {
-    let edges = get_report_edges(reports, offset);
-    get_reports_from_edges(edges, offset, total_count)
+    let edges = get_report_edges(reports, offset, query);
+    get_reports_from_edges(edges, offset, total_count, query)
 }
 
 pub struct AzureReportResult {
     reports: Vec<Report>,
     offset: i32,
     total_count: i32,
+    query: QueryKey,
 }
 
 impl Into<Reports> for AzureReportResult {
     fn into(self) -> Reports {
-        get_reports_graph_from_reports_vector(self.reports, self.offset, self.total_count)
+        get_reports_graph_from_reports_vector(
+            self.reports,
+            self.offset,
+            self.total_count,
+            &self.query,
+        )
     }
 }
 
 pub async fn get_reports(
     client: &impl Search,
     search: &str,
-    first: Option<i32>,
-    offset: i32,
+    relevance: ReportRelevance,
+    page: &PageRequest,
     substance_name: Option<&str>,
 ) -> Result<AzureReportResult, anyhow::Error> {
-    let result_count = first.unwrap_or(10);
+    let mut options = SearchOptions::new(search).scoring_profile(relevance.scoring_profile());
+    if let Some(filter) = build_filter(substance_name) {
+        options = options.filter(filter);
+    }
 
-    let azure_result = client
-        .search_with_pagination_and_filter::<ReportResults>(
-            &search,
-            search_client::AzurePagination {
-                result_count,
-                offset,
-            },
-            true,
-            build_filter(substance_name).as_deref(),
-        )
-        .await?;
+    let (azure_result, window) = search_page::<ReportResults>(client, options, page).await?;
 
-    Ok(map_azure_result(azure_result, offset))
+    Ok(map_azure_result(
+        azure_result,
+        window.offset,
+        page.query.clone(),
+    ))
 }
 
-fn map_azure_result(result: ReportResults, offset: i32) -> AzureReportResult {
+fn map_azure_result(result: ReportResults, offset: i32, query: QueryKey) -> AzureReportResult {
     let reports = result
         .search_results
         .into_iter()
@@ -137,21 +151,19 @@ fn map_azure_result(result: ReportResults, offset: i32) -> AzureReportResult {
         reports,
         total_count,
         offset,
+        query,
     }
 }
 
-fn build_filter(substance_name: Option<&str>) -> Option<String> {
+fn build_filter(substance_name: Option<&str>) -> Option<Filter> {
     match substance_name {
         Some(substance) => Some(build_substance_name_filter(substance)),
         None => None,
     }
 }
 
-fn build_substance_name_filter(substance_name: &str) -> String {
-    format!(
-        "active_substances/any(substance: substance eq '{}')",
-        substance_name
-    )
+fn build_substance_name_filter(substance_name: &str) -> Filter {
+    field("active_substances").any(|substance| substance.eq(substance_name))
 }
 
 #[cfg(test)]
@@ -210,7 +222,7 @@ mod test {
     }
 
     fn when_we_map_the_results(results: ReportResults) -> AzureReportResult {
-        map_azure_result(results, 0)
+        map_azure_result(results, 0, QueryKey::default())
     }
 
     fn then_all_fields_map_correctly(reports_response: AzureReportResult) {
@@ -272,4 +284,13 @@ mod test {
         let response = when_we_map_the_results(search_results);
         then_we_have_the_expected_output(response);
     }
+
+    #[test]
+    fn test_build_filter() {
+        assert_eq!(build_filter(None), None);
+        assert_eq!(
+            build_filter(Some("ST JOHN'S WORT")).map(|f| f.to_string()),
+            Some("active_substances/any(f: f eq 'ST JOHN''S WORT')".to_string())
+        );
+    }
 }
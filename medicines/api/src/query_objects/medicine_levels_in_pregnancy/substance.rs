@@ -1,10 +1,11 @@
 use crate::{
     azure_context::AzureContext,
+    pagination::{page_of, PageRequest, QueryKey},
     query_objects::medicine_levels_in_pregnancy::report::{
         get_reports, get_reports_graph_from_reports_vector, Report, Reports,
     },
+    query_objects::shared::{relevance::ReportRelevance, search_error::to_field_error},
 };
-use anyhow::anyhow;
 use async_graphql::{Context, FieldResult, Object};
 
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -32,35 +33,45 @@ impl SubstanceReports {
         context: &Context<'_>,
         first: Option<i32>,
         offset: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
     ) -> FieldResult<Reports> {
         let context = context.data::<AzureContext>()?;
-
-        let offset = match offset {
-            Some(a) => a,
-            None => 0,
+        let page = PageRequest {
+            first,
+            after,
+            last,
+            before,
+            skip: offset,
+            query: QueryKey::new(
+                "",
+                &[("active_substances", Some(vec![self.name.clone()]))],
+                ReportRelevance::default().scoring_profile(),
+            ),
         };
 
         if let Some(reports) = self.reports.clone() {
             let total_count = reports.len() as i32;
-
-            let reports = match first {
-                Some(t) => reports.into_iter().take(t as usize).collect(),
-                None => reports,
-            };
+            let (reports, window) = page_of(reports, &page).map_err(to_field_error)?;
 
             Ok(get_reports_graph_from_reports_vector(
                 reports,
-                offset,
+                window.offset,
                 total_count,
+                &page.query,
             ))
         } else {
-            get_reports(&context.bmgf_client, "", first, offset, Some(&self.name))
-                .await
-                .map(Into::into)
-                .map_err(|e| {
-                    tracing::error!("Error fetching reeports from Azure search service: {:?}", e);
-                    anyhow!("Error retrieving results").into()
-                })
+            get_reports(
+                &context.bmgf_client,
+                "",
+                ReportRelevance::default(),
+                &page,
+                Some(&self.name),
+            )
+            .await
+            .map(Into::into)
+            .map_err(to_field_error)
         }
     }
 }
@@ -1,11 +1,16 @@
-use crate::{pagination, pagination::PageInfo};
+use crate::{
+    pagination,
+    pagination::{search_page, PageInfo, PageRequest, QueryKey},
+    query_objects::shared::relevance::DocumentRelevance,
+};
 use async_graphql::SimpleObject;
 use search_client::{
+    filter::{field, Filter},
     models::{DocumentType, IndexResult, IndexResults, TerritoryType},
-    Search,
+    Search, SearchOptions,
 };
 
-#[SimpleObject(desc = "An SPC, PIL or PAR document")]
+#[SimpleObject(desc = "An SPC, PIL, PAR or other product document")]
 #[derive(Debug, Clone, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Document {
     #[field(desc = "Product associated with document")]
@@ -62,23 +67,28 @@ impl From<IndexResult> for Document {
 
 pagination! {Documents, DocumentEdge, Document}
 
-fn get_document_edges(docs: Vec<Document>, offset: i32) -> Vec<DocumentEdge> {
+fn get_document_edges(docs: Vec<Document>, offset: i32, query: &QueryKey) -> Vec<DocumentEdge> {
     docs.into_iter()
         .enumerate()
         .map(|(i, document)| DocumentEdge {
             node: document,
-            cursor: base64::encode((i as i32 + offset).to_string()),
+            cursor: query.cursor(i as i32 + offset),
         })
         .collect()
 }
 
-fn get_documents_from_edges(edges: Vec<DocumentEdge>, offset: i32, total_count: i32) -> Documents {
+fn get_documents_from_edges(
+    edges: Vec<DocumentEdge>,
+    offset: i32,
+    total_count: i32,
+    query: &QueryKey,
+) -> Documents {
     let result_count = edges.len() as i32;
 
     Documents {
         edges,
         total_count,
-        page_info: PageInfo::build(offset, result_count, total_count),
+        page_info: PageInfo::build(query, offset, result_count, total_count),
     }
 }
 
@@ -86,50 +96,54 @@ pub fn get_documents_graph_from_documents_vector(
     docs: Vec<Document>,
     offset: i32,
     total_count: i32,
+    query: &QueryKey,
 ) -> Documents {
-    let edges = get_document_edges(docs, offset);
-    get_documents_from_edges(edges, offset, total_count)
+    let edges = get_document_edges(docs, offset, query);
+    get_documents_from_edges(edges, offset, total_count, query)
 }
 
 pub struct AzureDocumentResult {
     docs: Vec<Document>,
     offset: i32,
     total_count: i32,
+    query: QueryKey,
 }
 
 impl Into<Documents> for AzureDocumentResult {
     fn into(self) -> Documents {
-        get_documents_graph_from_documents_vector(self.docs, self.offset, self.total_count)
+        get_documents_graph_from_documents_vector(
+            self.docs,
+            self.offset,
+            self.total_count,
+            &self.query,
+        )
     }
 }
 
 pub async fn get_documents(
     client: &impl Search,
     search: &str,
-    first: Option<i32>,
-    offset: i32,
+    relevance: DocumentRelevance,
+    page: &PageRequest,
     document_types: Option<Vec<DocumentType>>,
     territory_types: Option<Vec<TerritoryType>>,
     product_name: Option<&str>,
 ) -> Result<AzureDocumentResult, anyhow::Error> {
-    let result_count = first.unwrap_or(10);
-
-    let azure_result = client
-        .search_with_pagination_and_filter::<IndexResults>(
-            &search,
-            search_client::AzurePagination {
-                result_count,
-                offset,
-            },
-            true,
-            build_filter(document_types, territory_types, product_name).as_deref(),
-        )
-        .await?;
+    let mut options = SearchOptions::new(search).scoring_profile(relevance.scoring_profile());
+    if let Some(filter) = build_filter(document_types, territory_types, product_name) {
+        options = options.filter(filter);
+    }
 
-    Ok(map_azure_result(azure_result, offset))
+    let (azure_result, window) = search_page::<IndexResults>(client, options, page).await?;
+
+    Ok(map_azure_result(
+        azure_result,
+        window.offset,
+        page.query.clone(),
+    ))
 }
 
-fn map_azure_result(result: IndexResults, offset: i32) -> AzureDocumentResult {
+fn map_azure_result(result: IndexResults, offset: i32, query: QueryKey) -> AzureDocumentResult {
     let docs = result
         .search_results
         .into_iter()
@@ -142,6 +156,7 @@ fn map_azure_result(result: IndexResults, offset: i32) -> AzureDocumentResult {
         docs,
         total_count,
         offset,
+        query,
     }
 }
 
@@ -149,60 +164,62 @@ fn build_filter(
     document_types: Option<Vec<DocumentType>>,
     territory_types: Option<Vec<TerritoryType>>,
     product_name: Option<&str>,
-) -> Option<String> {
+) -> Option<Filter> {
     let docs_filter = document_types.and_then(build_document_types_filter);
     let products_filter = product_name.map(build_product_name_filter);
     let territories_filter = territory_types.and_then(build_territory_types_filter);
 
-    let filters: Vec<String> = products_filter
-        .into_iter()
-        .chain(docs_filter)
-        .chain(territories_filter)
-        .collect();
-
-    match &filters[..] {
-        [] => None,
-        [filter] => Some(filter.clone()),
-        _ => Some(format!("({})", filters.join(" and "))),
-    }
+    Filter::all_of(
+        products_filter
+            .into_iter()
+            .chain(docs_filter)
+            .chain(territories_filter),
+    )
 }
 
-fn build_document_types_filter(document_types: Vec<DocumentType>) -> Option<String> {
-    if document_types.is_empty() {
-        return None;
-    }
-
-    Some(format!(
-        "({})",
+fn build_document_types_filter(document_types: Vec<DocumentType>) -> Option<Filter> {
+    Filter::any_of(
         document_types
             .into_iter()
-            .map(|document_type| format!("doc_type eq '{}'", document_type))
-            .collect::<Vec<_>>()
-            .join(" or ")
-    ))
+            .map(|document_type| match document_type {
+                DocumentType::Unknown => build_unknown_document_type_filter(),
+                _ => field("doc_type").eq(document_type.to_string()),
+            }),
+    )
 }
 
-fn build_territory_types_filter(territory_types: Vec<TerritoryType>) -> Option<String> {
+// Documents of a type this version doesn't know about, whatever it is called in the index.
+fn build_unknown_document_type_filter() -> Filter {
+    !Filter::Or(
+        DocumentType::known()
+            .into_iter()
+            .map(|document_type| field("doc_type").eq(document_type.to_string()))
+            .collect(),
+    )
+}
+
+fn build_territory_types_filter(territory_types: Vec<TerritoryType>) -> Option<Filter> {
     if territory_types.is_empty() {
         return None;
     }
 
+    let territory = field("territory");
     let mut initial_query = territory_types
         .into_iter()
         .flat_map(|territory_type| match territory_type {
-            TerritoryType::GB => Some("territory eq 'GB'"),
-            TerritoryType::NI => Some("territory eq 'NI'"),
+            TerritoryType::GB => Some(territory.eq("GB")),
+            TerritoryType::NI => Some(territory.eq("NI")),
             _ => None,
         })
         .collect::<Vec<_>>();
 
-    initial_query.extend(vec!["territory eq 'UK'", "territory eq null"]);
+    initial_query.extend(vec![territory.eq("UK"), territory.is_null()]);
 
-    Some(format!("({})", initial_query.join(" or ")))
+    Filter::any_of(initial_query)
 }
 
-fn build_product_name_filter(product_name: &str) -> String {
-    format!("(product_name eq '{}')", product_name)
+fn build_product_name_filter(product_name: &str) -> Filter {
+    field("product_name").eq(product_name)
 }
 
 #[cfg(test)]
@@ -265,7 +282,7 @@ mod test {
     }
 
     fn when_we_map_the_results(results: IndexResults) -> AzureDocumentResult {
-        map_azure_result(results, 0)
+        map_azure_result(results, 0, QueryKey::default())
     }
 
     fn then_all_fields_map_correctly(reports_response: AzureDocumentResult) {
@@ -327,7 +344,7 @@ mod test {
         Some(vec![DocumentType::Spc, DocumentType::Pil,DocumentType::Par,]),
         Some(vec![TerritoryType::UK, TerritoryType::GB, TerritoryType::NI,]),
         Some("IBUPROFEN 100MG CAPLETS"),
-        Some("((product_name eq 'IBUPROFEN 100MG CAPLETS') and (doc_type eq 'Spc' or doc_type eq 'Pil' or doc_type eq 'Par') and (territory eq 'GB' or territory eq 'NI' or territory eq 'UK' or territory eq null))")
+        Some("(product_name eq 'IBUPROFEN 100MG CAPLETS' and (doc_type eq 'Spc' or doc_type eq 'Pil' or doc_type eq 'Par') and (territory eq 'GB' or territory eq 'NI' or territory eq 'UK' or territory eq null))")
     )]
     #[test_case(
         Some(vec![DocumentType::Spc,  DocumentType::Pil,DocumentType::Par,]),
@@ -335,35 +352,53 @@ mod test {
         None,
         Some("((doc_type eq 'Spc' or doc_type eq 'Pil' or doc_type eq 'Par') and (territory eq 'GB' or territory eq 'NI' or territory eq 'UK' or territory eq null))")
     )]
+    #[test_case(
+        Some(vec![DocumentType::Rmm, DocumentType::Psl, DocumentType::Label,]),
+        None,
+        None,
+        Some("(doc_type eq 'Rmm' or doc_type eq 'Psl' or doc_type eq 'Label')")
+    )]
+    #[test_case(
+        Some(vec![DocumentType::Spc, DocumentType::Unknown,]),
+        None,
+        None,
+        Some("(doc_type eq 'Spc' or not (doc_type eq 'Spc' or doc_type eq 'Pil' or doc_type eq 'Par' or doc_type eq 'Rmm' or doc_type eq 'Psl' or doc_type eq 'Label'))")
+    )]
     #[test_case(
         None,
         None,
         Some("IBUPROFEN 100MG CAPLETS"),
-        Some("(product_name eq 'IBUPROFEN 100MG CAPLETS')")
+        Some("product_name eq 'IBUPROFEN 100MG CAPLETS'")
     )]
     #[test_case(
         None,
         Some(vec![TerritoryType::UK]),
         Some("IBUPROFEN 100MG CAPLETS"),
-        Some("((product_name eq 'IBUPROFEN 100MG CAPLETS') and (territory eq 'UK' or territory eq null))")
+        Some("(product_name eq 'IBUPROFEN 100MG CAPLETS' and (territory eq 'UK' or territory eq null))")
     )]
     #[test_case(
         None,
         Some(vec![TerritoryType::GB]),
         Some("IBUPROFEN 100MG CAPLETS"),
-        Some("((product_name eq 'IBUPROFEN 100MG CAPLETS') and (territory eq 'GB' or territory eq 'UK' or territory eq null))")
+        Some("(product_name eq 'IBUPROFEN 100MG CAPLETS' and (territory eq 'GB' or territory eq 'UK' or territory eq null))")
     )]
     #[test_case(
         None,
         Some(vec![TerritoryType::NI]),
         Some("IBUPROFEN 100MG CAPLETS"),
-        Some("((product_name eq 'IBUPROFEN 100MG CAPLETS') and (territory eq 'NI' or territory eq 'UK' or territory eq null))")
+        Some("(product_name eq 'IBUPROFEN 100MG CAPLETS' and (territory eq 'NI' or territory eq 'UK' or territory eq null))")
     )]
     #[test_case(
         Some(vec![]),
         Some(vec![]),
         Some("IBUPROFEN 100MG CAPLETS"),
-        Some("(product_name eq 'IBUPROFEN 100MG CAPLETS')")
+        Some("product_name eq 'IBUPROFEN 100MG CAPLETS'")
+    )]
+    #[test_case(
+        None,
+        None,
+        Some("ST JOHN'S WORT TABLETS"),
+        Some("product_name eq 'ST JOHN''S WORT TABLETS'")
     )]
     fn test_build_filter(
         document_types: Option<Vec<DocumentType>>,
@@ -373,7 +408,7 @@ mod test {
     ) {
         assert_eq!(
             expected_filter.map(|s| s.to_string()),
-            build_filter(document_types, territory_types, product_name)
+            build_filter(document_types, territory_types, product_name).map(|f| f.to_string())
         );
     }
 }
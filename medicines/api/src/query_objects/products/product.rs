@@ -1,22 +1,72 @@
 use crate::{
     azure_context::AzureContext,
-    query_objects::products::document::{
-        self, get_documents, get_documents_graph_from_documents_vector, Document,
+    pagination::{filter_values, page_of, PageRequest, QueryKey},
+    query_objects::{
+        products::document::{
+            self, get_documents, get_documents_graph_from_documents_vector, Document,
+        },
+        shared::{
+            relevance::DocumentRelevance,
+            search_error::{to_field_error, NotFound},
+        },
     },
 };
-use anyhow::anyhow;
-use async_graphql::{Context, FieldResult, Object};
-use search_client::models::{DocumentType, TerritoryType};
+use async_graphql::{Context, FieldResult, Object, SimpleObject};
+use futures::{lock::Mutex, TryStreamExt};
+use search_client::{
+    filter::field,
+    models::{DocumentType, FacetQueryResults, TerritoryType},
+    scan, FacetSort, FacetSpec, ScanOptions, Search, SearchClientError, SearchOptions, SortOrder,
+};
+use serde_derive::Deserialize;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+};
+
+// Enough for every licence of even the most widely licensed generics.
+const MAX_FACET_VALUES: u32 = 1000;
 
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Product {
     name: String,
     documents: Option<Vec<Document>>,
+    details: LazyDetails,
+}
+
+/// A product's details, looked up the first time one of its fields asks for them and
+/// shared by the rest.
+#[derive(Debug, Default)]
+struct LazyDetails(Mutex<Option<ProductDetails>>);
+
+// The details follow from the product's name, so they don't change how products compare.
+impl PartialEq for LazyDetails {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for LazyDetails {}
+
+impl PartialOrd for LazyDetails {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LazyDetails {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
 }
 
 impl Product {
     pub fn new(name: String, documents: Option<Vec<Document>>) -> Self {
-        Self { name, documents }
+        Self {
+            name,
+            documents,
+            details: LazyDetails::default(),
+        }
     }
 
     pub fn add(&mut self, document: Document) {
@@ -26,6 +76,46 @@ impl Product {
             self.documents = Some(vec![document])
         }
     }
+
+    // Products listed for a substance are built from its documents, so their details are
+    // only looked up if they are asked for.
+    // The lock is held while they are looked up, so fields resolved alongside the first
+    // wait for its answer rather than asking Azure again.
+    async fn details(&self, context: &Context<'_>) -> FieldResult<ProductDetails> {
+        let mut details = self.details.0.lock().await;
+        if let Some(details) = details.as_ref() {
+            return Ok(details.clone());
+        }
+
+        let context = context.data::<AzureContext>()?;
+        let found = get_product_details(&context.products_client, &self.name)
+            .await
+            .map_err(to_field_error)?
+            .unwrap_or_default();
+        *details = Some(found.clone());
+        Ok(found)
+    }
+}
+
+#[SimpleObject(desc = "The number of documents of a type that a product has")]
+#[derive(Debug, Clone, Eq, Ord, PartialEq, PartialOrd)]
+pub struct DocumentCount {
+    #[field(desc = "Document type")]
+    pub doc_type: DocumentType,
+    #[field(desc = "Number of documents")]
+    pub count: i32,
+}
+
+/// What the index knows about a product, gathered from all of its documents.
+#[derive(Debug, Clone, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct ProductDetails {
+    pl_numbers: Vec<String>,
+    territories: Vec<TerritoryType>,
+    active_substances: Vec<String>,
+    latest_spc_date: Option<String>,
+    latest_pil_date: Option<String>,
+    latest_par_date: Option<String>,
+    document_counts: Vec<DocumentCount>,
 }
 
 #[Object(desc = "A medical product containing active ingredients")]
@@ -35,20 +125,70 @@ impl Product {
         &self.name
     }
 
+    #[field(desc = "Licence numbers of the product")]
+    async fn pl_numbers(&self, context: &Context<'_>) -> FieldResult<Vec<String>> {
+        Ok(self.details(context).await?.pl_numbers)
+    }
+
+    #[field(desc = "Territories the product's documents apply to")]
+    async fn territories(&self, context: &Context<'_>) -> FieldResult<Vec<TerritoryType>> {
+        Ok(self.details(context).await?.territories)
+    }
+
+    #[field(desc = "Active substances of the product")]
+    async fn active_substances(&self, context: &Context<'_>) -> FieldResult<Vec<String>> {
+        Ok(self.details(context).await?.active_substances)
+    }
+
+    #[field(desc = "Created date of the latest SPC")]
+    async fn latest_spc_date(&self, context: &Context<'_>) -> FieldResult<Option<String>> {
+        Ok(self.details(context).await?.latest_spc_date)
+    }
+
+    #[field(desc = "Created date of the latest PIL")]
+    async fn latest_pil_date(&self, context: &Context<'_>) -> FieldResult<Option<String>> {
+        Ok(self.details(context).await?.latest_pil_date)
+    }
+
+    #[field(desc = "Created date of the latest PAR")]
+    async fn latest_par_date(&self, context: &Context<'_>) -> FieldResult<Option<String>> {
+        Ok(self.details(context).await?.latest_par_date)
+    }
+
+    #[field(desc = "Number of documents of each type")]
+    async fn document_counts(&self, context: &Context<'_>) -> FieldResult<Vec<DocumentCount>> {
+        Ok(self.details(context).await?.document_counts)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     #[field(desc = "Documents related to product")]
     async fn documents(
         &self,
         context: &Context<'_>,
         first: Option<i32>,
         offset: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
         document_types: Option<Vec<DocumentType>>,
         territory_types: Option<Vec<TerritoryType>>,
     ) -> FieldResult<document::Documents> {
         let context = context.data::<AzureContext>()?;
-
-        let offset = match offset {
-            Some(a) => a,
-            None => 0,
+        let page = PageRequest {
+            first,
+            after,
+            last,
+            before,
+            skip: offset,
+            query: QueryKey::new(
+                "",
+                &[
+                    ("product_name", Some(vec![self.name.clone()])),
+                    ("doc_type", filter_values(&document_types)),
+                    ("territory", filter_values(&territory_types)),
+                ],
+                DocumentRelevance::default().scoring_profile(),
+            ),
         };
 
         if let Some(docs) = self.documents.clone() {
@@ -69,36 +209,27 @@ impl Product {
             };
 
             let total_count = docs.len() as i32;
-
-            let docs = match first {
-                Some(t) => docs.into_iter().take(t as usize).collect(),
-                None => docs,
-            };
+            let (docs, window) = page_of(docs, &page).map_err(to_field_error)?;
 
             Ok(get_documents_graph_from_documents_vector(
                 docs,
-                offset,
+                window.offset,
                 total_count,
+                &page.query,
             ))
         } else {
             get_documents(
                 &context.products_client,
                 "",
-                first,
-                offset,
+                DocumentRelevance::default(),
+                &page,
                 document_types,
                 territory_types,
                 Some(&self.name),
             )
             .await
             .map(Into::into)
-            .map_err(|e| {
-                tracing::error!(
-                    "Error fetching documents from Azure search service: {:?}",
-                    e
-                );
-                anyhow!("Error retrieving results").into()
-            })
+            .map_err(to_field_error)
         }
     }
 }
@@ -120,14 +251,164 @@ pub fn handle_doc(document: &Document, products: &mut Vec<Product>) {
     }
 }
 
-pub async fn get_product(product_name: String) -> Result<Product, reqwest::Error> {
-    Ok(Product::new(product_name, None))
+/// The product with its details, or `NotFound` if no documents in the index are for it.
+pub async fn get_product(
+    client: &(impl Search + Sync),
+    product_name: &str,
+) -> anyhow::Result<Product> {
+    let details = get_product_details(client, product_name)
+        .await?
+        .ok_or_else(|| NotFound(format!("Product {}", product_name)))?;
+
+    Ok(Product {
+        name: product_name.to_string(),
+        documents: None,
+        details: LazyDetails(Mutex::new(Some(details))),
+    })
+}
+
+// Territories and substances aren't facetable, so they are read from the product's
+// documents; licence numbers aren't retrievable, so they come from a facet.
+async fn get_product_details(
+    client: &(impl Search + Sync),
+    product_name: &str,
+) -> Result<Option<ProductDetails>, SearchClientError> {
+    let facet = |name: &str| {
+        FacetSpec::new(name)
+            .count(MAX_FACET_VALUES)
+            .sort_by(FacetSort::Value, SortOrder::Asc)
+    };
+    let options = SearchOptions::default()
+        .filter(field("product_name").eq(product_name))
+        .facet(facet("pl_number"))
+        .facet(facet("doc_type"));
+    let facets = client.search_facets(options).await?;
+
+    if facets.count.unwrap_or_default() == 0 {
+        return Ok(None);
+    }
+
+    let ((territories, active_substances), latest_spc, latest_pil, latest_par) = futures::try_join!(
+        get_territories_and_substances(client, product_name),
+        get_latest_created(client, product_name, DocumentType::Spc),
+        get_latest_created(client, product_name, DocumentType::Pil),
+        get_latest_created(client, product_name, DocumentType::Par),
+    )?;
+
+    Ok(Some(ProductDetails {
+        pl_numbers: facet_values(&facets, "pl_number").collect(),
+        territories,
+        active_substances,
+        latest_spc_date: latest_spc,
+        latest_pil_date: latest_pil,
+        latest_par_date: latest_par,
+        document_counts: count_document_types(&facets),
+    }))
+}
+
+// Types this version doesn't know about are counted together, as `Unknown`.
+fn count_document_types(facets: &FacetQueryResults) -> Vec<DocumentCount> {
+    let mut counts = BTreeMap::<DocumentType, i32>::new();
+    for facet in facets.field("doc_type") {
+        let doc_type = facet
+            .value
+            .to_string()
+            .parse()
+            .unwrap_or(DocumentType::Unknown);
+        *counts.entry(doc_type).or_default() += facet.count;
+    }
+
+    counts
+        .into_iter()
+        .map(|(doc_type, count)| DocumentCount { doc_type, count })
+        .collect()
+}
+
+fn facet_values<'a>(
+    facets: &'a FacetQueryResults,
+    field: &str,
+) -> impl Iterator<Item = String> + 'a {
+    facets
+        .field(field)
+        .iter()
+        .map(|facet| facet.value.to_string())
+}
+
+#[derive(Deserialize)]
+struct TerritoryAndSubstances {
+    territory: Option<String>,
+    #[serde(default)]
+    substance_name: Vec<String>,
+}
+
+async fn get_territories_and_substances(
+    client: &(impl Search + Sync),
+    product_name: &str,
+) -> Result<(Vec<TerritoryType>, Vec<String>), SearchClientError> {
+    let options = ScanOptions::new("metadata_storage_name")
+        .filter(field("product_name").eq(product_name))
+        .select(&["territory", "substance_name"]);
+    let (territories, substances) = scan::<_, TerritoryAndSubstances>(client, options)
+        .try_fold(
+            (BTreeSet::new(), BTreeSet::new()),
+            |(mut territories, mut substances), document| async move {
+                territories.extend(document.territory);
+                substances.extend(document.substance_name);
+                Ok((territories, substances))
+            },
+        )
+        .await?;
+
+    Ok((
+        territories
+            .iter()
+            .filter_map(|territory| territory.parse().ok())
+            .collect(),
+        substances.into_iter().collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct CreatedResults {
+    value: Vec<Created>,
+}
+
+#[derive(Deserialize)]
+struct Created {
+    created: Option<String>,
+}
+
+async fn get_latest_created(
+    client: &impl Search,
+    product_name: &str,
+    doc_type: DocumentType,
+) -> Result<Option<String>, SearchClientError> {
+    let options = SearchOptions::default()
+        .filter(
+            field("product_name")
+                .eq(product_name)
+                .and(field("doc_type").eq(doc_type.to_string())),
+        )
+        .order_by("created", SortOrder::Desc)
+        .select(&["created"])
+        .highlight(&[])
+        .top(1);
+    let results: CreatedResults = client.search(options).await?;
+
+    Ok(results
+        .value
+        .into_iter()
+        .next()
+        .and_then(|result| result.created))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use search_client::models::IndexResult;
+    use pretty_assertions::assert_eq;
+    use search_client::{models::IndexResult, InMemorySearchClient};
+    use serde_json::json;
+    use tokio_test::block_on;
 
     fn azure_result_factory(product_name: Option<String>) -> Document {
         let result = IndexResult {
@@ -191,4 +472,86 @@ mod test {
         assert_eq!(products[1].name, "B");
         assert_eq!(products[2].name, "C");
     }
+
+    fn given_an_index_of_products() -> InMemorySearchClient {
+        let document =
+            |key: &str, doc_type: &str, territory: &str, pl_number: &str, created: &str| {
+                json!({
+                    "metadata_storage_name": key,
+                    "product_name": "NUROFEN 200MG TABLETS",
+                    "doc_type": doc_type,
+                    "territory": territory,
+                    "pl_number": [pl_number],
+                    "substance_name": ["IBUPROFEN"],
+                    "created": created,
+                })
+            };
+        InMemorySearchClient::from_documents(
+            "products-index",
+            vec![
+                document("a", "Spc", "UK", "PL 00327/0082", "2019-03-01T00:00:00Z"),
+                document("b", "Spc", "GB", "PL 00327/0083", "2020-06-01T00:00:00Z"),
+                document("c", "Pil", "UK", "PL 00327/0082", "2018-01-15T00:00:00Z"),
+                document(
+                    "d",
+                    "Leaflet",
+                    "NI",
+                    "PL 00327/0082",
+                    "2017-01-01T00:00:00Z",
+                ),
+                json!({
+                    "metadata_storage_name": "e",
+                    "product_name": "PARACETAMOL 500MG TABLETS",
+                    "doc_type": "Par",
+                    "territory": "UK",
+                    "pl_number": ["PL 12345/0001"],
+                    "substance_name": ["PARACETAMOL"],
+                    "created": "2021-01-01T00:00:00Z",
+                }),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_get_product_aggregates_its_documents() {
+        let client = given_an_index_of_products();
+
+        let product = block_on(get_product(&client, "NUROFEN 200MG TABLETS")).unwrap();
+
+        assert_eq!(product.name, "NUROFEN 200MG TABLETS");
+        assert_eq!(
+            *block_on(product.details.0.lock()),
+            Some(ProductDetails {
+                pl_numbers: vec!["PL 00327/0082".to_string(), "PL 00327/0083".to_string()],
+                territories: vec![TerritoryType::GB, TerritoryType::NI, TerritoryType::UK],
+                active_substances: vec!["IBUPROFEN".to_string()],
+                latest_spc_date: Some("2020-06-01T00:00:00Z".to_string()),
+                latest_pil_date: Some("2018-01-15T00:00:00Z".to_string()),
+                latest_par_date: None,
+                document_counts: vec![
+                    DocumentCount {
+                        doc_type: DocumentType::Spc,
+                        count: 2
+                    },
+                    DocumentCount {
+                        doc_type: DocumentType::Pil,
+                        count: 1
+                    },
+                    DocumentCount {
+                        doc_type: DocumentType::Unknown,
+                        count: 1
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_product_for_an_unknown_name_is_not_found() {
+        let client = given_an_index_of_products();
+
+        let error = block_on(get_product(&client, "NUROFEN 999MG TABLETS")).unwrap_err();
+
+        assert_eq!(error.to_string(), "Product NUROFEN 999MG TABLETS not found");
+    }
 }
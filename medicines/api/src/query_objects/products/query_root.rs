@@ -1,15 +1,19 @@
 use crate::{
     azure_context::AzureContext,
-    pagination::get_offset_or_default,
+    pagination::{filter_values, PageRequest, QueryKey},
     query_objects::products::{
         document::{get_documents, Documents},
         product::{get_product, Product},
         products_index::{get_products_index, ProductIndex},
         substance::{get_substance_with_products, Substance},
     },
-    query_objects::shared::substances_index::{get_substances_index, SubstanceIndex},
+    query_objects::shared::{
+        relevance::DocumentRelevance,
+        search_error::to_field_error,
+        substances_index::{get_substances_index, SubstanceIndex},
+        suggestions::{get_autocompletions, get_suggestions},
+    },
 };
-use anyhow::anyhow;
 use async_graphql::{Context, FieldResult, Object};
 use search_client::models::{DocumentType, TerritoryType};
 
@@ -27,22 +31,21 @@ impl Products {
         match name {
             Some(name) => get_substance_with_products(&name, &context.products_client)
                 .await
-                .map_err(|e| {
-                    tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                    anyhow!("Error retrieving results").into()
-                }),
+                .map_err(to_field_error),
             None => Err(anyhow::anyhow!(
                 "Getting a substance without providing a substance name is not supported."
             )
             .into()),
         }
     }
-    #[field(desc = "Retrieves all documents associated with the queried product")]
-    async fn product(&self, _context: &Context<'_>, name: String) -> FieldResult<Product> {
-        get_product(name).await.map_err(|e| {
-            tracing::error!("Error fetching results from Azure search service: {:?}", e);
-            anyhow!("Error retrieving results").into()
-        })
+    #[field(
+        desc = "Retrieves the queried product with its licences, territories, active substances and documents"
+    )]
+    async fn product(&self, context: &Context<'_>, name: String) -> FieldResult<Product> {
+        let context = context.data::<AzureContext>()?;
+        get_product(&context.products_client, &name)
+            .await
+            .map_err(to_field_error)
     }
 
     #[field(
@@ -56,10 +59,7 @@ impl Products {
         let context = context.data::<AzureContext>()?;
         get_substances_index(&context.products_client, letter.chars().next().unwrap())
             .await
-            .map_err(|e| {
-                tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                anyhow!("Error retrieving results").into()
-            })
+            .map_err(to_field_error)
     }
 
     #[field(
@@ -73,10 +73,7 @@ impl Products {
         let context = context.data::<AzureContext>()?;
         get_products_index(&context.products_client, &substance)
             .await
-            .map_err(|e| {
-                tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                anyhow!("Error retrieving results").into()
-            })
+            .map_err(to_field_error)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -88,26 +85,67 @@ impl Products {
         first: Option<i32>,
         skip: Option<i32>,
         after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
         document_types: Option<Vec<DocumentType>>,
         territory_types: Option<Vec<TerritoryType>>,
+        relevance_profile: Option<DocumentRelevance>,
     ) -> FieldResult<Documents> {
         let context = context.data::<AzureContext>()?;
-        let offset = get_offset_or_default(skip, after, 0);
+        let relevance = relevance_profile.unwrap_or_default();
+        let page = PageRequest {
+            first,
+            after,
+            last,
+            before,
+            skip,
+            query: QueryKey::new(
+                search.as_deref().unwrap_or_default(),
+                &[
+                    ("doc_type", filter_values(&document_types)),
+                    ("territory", filter_values(&territory_types)),
+                ],
+                relevance.scoring_profile(),
+            ),
+        };
 
         get_documents(
             &context.products_client,
             search.as_deref().unwrap_or(" "),
-            first,
-            offset,
+            relevance,
+            &page,
             document_types,
             territory_types,
             None,
         )
         .await
         .map(Into::into)
-        .map_err(|e| {
-            tracing::error!("Error fetching results from Azure search service: {:?}", e);
-            anyhow!("Error retrieving results").into()
-        })
+        .map_err(to_field_error)
+    }
+
+    #[field(desc = "Suggested titles, products and active substances matching the search term")]
+    async fn suggestions(
+        &self,
+        context: &Context<'_>,
+        search: String,
+        first: Option<i32>,
+    ) -> FieldResult<Vec<String>> {
+        let context = context.data::<AzureContext>()?;
+        get_suggestions(&context.products_client, &search, first)
+            .await
+            .map_err(to_field_error)
+    }
+
+    #[field(desc = "Completions of the last word of the search term")]
+    async fn autocomplete(
+        &self,
+        context: &Context<'_>,
+        search: String,
+        first: Option<i32>,
+    ) -> FieldResult<Vec<String>> {
+        let context = context.data::<AzureContext>()?;
+        get_autocompletions(&context.products_client, &search, first)
+            .await
+            .map_err(to_field_error)
     }
 }
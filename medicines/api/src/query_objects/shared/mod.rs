@@ -1 +1,4 @@
+pub mod relevance;
+pub mod search_error;
 pub mod substances_index;
+pub mod suggestions;
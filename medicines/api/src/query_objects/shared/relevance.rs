@@ -0,0 +1,84 @@
+use async_graphql::Enum;
+use search_client::scoring_profiles;
+
+#[Enum(desc = "How documents matching a search are ranked")]
+#[derive(Debug)]
+pub enum DocumentRelevance {
+    #[item(desc = "Prefer matches in licence numbers, keywords and product names (default)")]
+    Keywords,
+    #[item(desc = "As KEYWORDS, but prefer documents created in the last year")]
+    Recent,
+    #[item(desc = "Rank by how well the text matches alone")]
+    Text,
+}
+
+impl Default for DocumentRelevance {
+    fn default() -> Self {
+        DocumentRelevance::Keywords
+    }
+}
+
+impl DocumentRelevance {
+    pub fn scoring_profile(self) -> Option<&'static str> {
+        match self {
+            DocumentRelevance::Keywords => Some(scoring_profiles::PREFER_KEYWORDS),
+            DocumentRelevance::Recent => Some(scoring_profiles::PREFER_RECENT),
+            DocumentRelevance::Text => None,
+        }
+    }
+}
+
+#[Enum(desc = "How reports matching a search are ranked")]
+#[derive(Debug)]
+pub enum ReportRelevance {
+    #[item(
+        desc = "Prefer matches in licence numbers, report names, substances and products (default)"
+    )]
+    Keywords,
+    #[item(desc = "Strongly prefer matches in the report name")]
+    ReportName,
+    #[item(desc = "Rank by how well the text matches alone")]
+    Text,
+}
+
+impl Default for ReportRelevance {
+    fn default() -> Self {
+        ReportRelevance::Keywords
+    }
+}
+
+impl ReportRelevance {
+    pub fn scoring_profile(self) -> Option<&'static str> {
+        match self {
+            ReportRelevance::Keywords => Some(scoring_profiles::PREFER_KEYWORDS),
+            ReportRelevance::ReportName => Some(scoring_profiles::PREFER_REPORT_NAME),
+            ReportRelevance::Text => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(DocumentRelevance::Keywords, Some("preferKeywords"))]
+    #[test_case(DocumentRelevance::Recent, Some("preferRecent"))]
+    #[test_case(DocumentRelevance::Text, None)]
+    fn document_relevance_selects_a_scoring_profile(
+        relevance: DocumentRelevance,
+        expected: Option<&str>,
+    ) {
+        assert_eq!(relevance.scoring_profile(), expected);
+    }
+
+    #[test_case(ReportRelevance::Keywords, Some("preferKeywords"))]
+    #[test_case(ReportRelevance::ReportName, Some("preferReportName"))]
+    #[test_case(ReportRelevance::Text, None)]
+    fn report_relevance_selects_a_scoring_profile(
+        relevance: ReportRelevance,
+        expected: Option<&str>,
+    ) {
+        assert_eq!(relevance.scoring_profile(), expected);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::pagination::PaginationError;
+use async_graphql::FieldError;
+use search_client::SearchClientError;
+use serde_json::json;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+const GENERIC_ERROR_MESSAGE: &str = "Error retrieving results";
+
+/// A lookup by name that matched nothing, as opposed to one that failed.
+#[derive(Debug)]
+pub struct NotFound(pub String);
+
+impl Display for NotFound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} not found", self.0)
+    }
+}
+
+impl Error for NotFound {}
+
+// Invalid searches and cursors are the user's to fix, a busy service is worth retrying and a
+// missing product or substance is worth saying so, so only those are described to the client;
+// anything else is logged and reported generically.
+pub fn to_field_error(error: impl Into<anyhow::Error>) -> FieldError {
+    let error = error.into();
+
+    if let Some(error) = error.downcast_ref::<NotFound>() {
+        return FieldError(error.to_string(), Some(json!({ "code": "NOT_FOUND" })));
+    }
+
+    if let Some(error) = error.downcast_ref::<PaginationError>() {
+        let code = match error {
+            PaginationError::NegativePageSize | PaginationError::UnknownTotalCount => {
+                "INVALID_PAGINATION"
+            }
+            _ => "INVALID_CURSOR",
+        };
+        return FieldError(error.to_string(), Some(json!({ "code": code })));
+    }
+
+    match error.downcast_ref::<SearchClientError>() {
+        Some(SearchClientError::InvalidRequest(message)) => {
+            tracing::warn!("Invalid search request: {}", message);
+            FieldError(
+                format!("Invalid search: {}", message),
+                Some(json!({ "code": "INVALID_SEARCH" })),
+            )
+        }
+        Some(e @ SearchClientError::Throttled { .. })
+        | Some(e @ SearchClientError::CircuitOpen { .. }) => {
+            tracing::warn!("Azure search service is unavailable: {:?}", e);
+            FieldError(
+                "The search service is busy, please try again shortly".to_string(),
+                Some(json!({
+                    "code": "SEARCH_BUSY",
+                    "retryAfter": e.retry_after().map(|retry_after| retry_after.as_secs()),
+                })),
+            )
+        }
+        _ => {
+            tracing::error!(
+                "Error fetching results from Azure search service: {:?}",
+                error
+            );
+            FieldError(GENERIC_ERROR_MESSAGE.to_string(), None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+    use std::time::Duration;
+
+    #[test]
+    fn invalid_searches_are_described() {
+        let error = to_field_error(SearchClientError::InvalidRequest(
+            "Syntax error at position 7".to_string(),
+        ));
+
+        assert_eq!(error.0, "Invalid search: Syntax error at position 7");
+        assert_eq!(error.1, Some(json!({ "code": "INVALID_SEARCH" })));
+    }
+
+    #[test]
+    fn throttling_says_when_to_retry() {
+        let error = to_field_error(SearchClientError::Throttled {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after: Some(Duration::from_secs(5)),
+        });
+
+        assert_eq!(
+            error.1,
+            Some(json!({ "code": "SEARCH_BUSY", "retryAfter": 5 }))
+        );
+    }
+
+    #[test]
+    fn cursors_for_another_query_are_described() {
+        let error = to_field_error(PaginationError::CursorForAnotherQuery);
+
+        assert_eq!(
+            error.0,
+            "The cursor is for a different search, filters or order, please start from the first page"
+        );
+        assert_eq!(error.1, Some(json!({ "code": "INVALID_CURSOR" })));
+    }
+
+    #[test]
+    fn missing_products_are_not_found() {
+        let error = to_field_error(NotFound("Product NUROFEN 999MG TABLETS".to_string()));
+
+        assert_eq!(error.0, "Product NUROFEN 999MG TABLETS not found");
+        assert_eq!(error.1, Some(json!({ "code": "NOT_FOUND" })));
+    }
+
+    #[test]
+    fn other_errors_are_not_described() {
+        let error = to_field_error(SearchClientError::Unauthorized(StatusCode::FORBIDDEN));
+
+        assert_eq!(error.0, "Error retrieving results");
+        assert_eq!(error.1, None);
+    }
+}
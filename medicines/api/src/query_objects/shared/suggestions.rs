@@ -0,0 +1,101 @@
+use search_client::{
+    models::{AutocompleteResults, SuggestResults},
+    Search,
+};
+
+const DEFAULT_SUGGESTION_COUNT: i32 = 5;
+// Azure only accepts a `$top` of between 1 and 100 for suggest and autocomplete.
+const MAX_SUGGESTION_COUNT: i32 = 100;
+
+fn suggestion_count(first: Option<i32>) -> i32 {
+    first
+        .unwrap_or(DEFAULT_SUGGESTION_COUNT)
+        .max(1)
+        .min(MAX_SUGGESTION_COUNT)
+}
+
+pub async fn get_suggestions(
+    client: &impl Search,
+    search: &str,
+    first: Option<i32>,
+) -> anyhow::Result<Vec<String>> {
+    let azure_result = client.suggest(search, suggestion_count(first)).await?;
+
+    Ok(format_suggestions(azure_result))
+}
+
+pub async fn get_autocompletions(
+    client: &impl Search,
+    search: &str,
+    first: Option<i32>,
+) -> anyhow::Result<Vec<String>> {
+    let azure_result = client.autocomplete(search, suggestion_count(first)).await?;
+
+    Ok(format_autocompletions(azure_result))
+}
+
+// Several documents often share a title or product name, so only return each suggestion once.
+fn format_suggestions(results: SuggestResults) -> Vec<String> {
+    let mut suggestions: Vec<String> = vec![];
+    for result in results.suggestions {
+        if !suggestions.contains(&result.text) {
+            suggestions.push(result.text);
+        }
+    }
+    suggestions
+}
+
+fn format_autocompletions(results: AutocompleteResults) -> Vec<String> {
+    results
+        .completions
+        .into_iter()
+        .map(|result| result.query_plus_text)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use search_client::models::{AutocompleteResult, SuggestResult};
+
+    #[test]
+    fn suggestion_counts_are_kept_within_azure_limits() {
+        assert_eq!(suggestion_count(None), 5);
+        assert_eq!(suggestion_count(Some(20)), 20);
+        assert_eq!(suggestion_count(Some(0)), 1);
+        assert_eq!(suggestion_count(Some(-3)), 1);
+        assert_eq!(suggestion_count(Some(1000)), 100);
+    }
+
+    #[test]
+    fn formats_suggestions_without_duplicates() {
+        let results = SuggestResults {
+            suggestions: vec![
+                "IBUPROFEN 200MG TABLETS",
+                "IBUPROFEN",
+                "IBUPROFEN 200MG TABLETS",
+            ]
+            .into_iter()
+            .map(|text| SuggestResult { text: text.into() })
+            .collect(),
+        };
+
+        assert_eq!(
+            format_suggestions(results),
+            vec!["IBUPROFEN 200MG TABLETS", "IBUPROFEN"]
+        );
+    }
+
+    #[test]
+    fn formats_autocompletions_with_the_query() {
+        let results = AutocompleteResults {
+            completions: vec![AutocompleteResult {
+                text: "ibuprofen".into(),
+                query_plus_text: "tablets ibuprofen".into(),
+            }],
+        };
+
+        assert_eq!(format_autocompletions(results), vec!["tablets ibuprofen"]);
+    }
+}
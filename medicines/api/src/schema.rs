@@ -1,6 +1,6 @@
 use crate::{
     azure_context::AzureContext,
-    pagination::get_offset_or_default,
+    pagination::{filter_values, PageRequest, QueryKey},
     query_objects::medicine_levels_in_pregnancy::query_root::MedicineLevelsInPregnancy,
     query_objects::{
         products::{
@@ -10,10 +10,13 @@ use crate::{
             query_root::Products,
             substance::{get_substance_with_products, Substance},
         },
-        shared::substances_index::{get_substances_index, SubstanceIndex},
+        shared::{
+            relevance::DocumentRelevance,
+            search_error::to_field_error,
+            substances_index::{get_substances_index, SubstanceIndex},
+        },
     },
 };
-use anyhow::anyhow;
 use async_graphql::{Context, EmptyMutation, EmptySubscription, FieldResult, Object, Schema};
 use search_client::models::{DocumentType, TerritoryType};
 
@@ -31,10 +34,7 @@ impl QueryRoot {
         match name {
             Some(name) => get_substance_with_products(&name, &context.products_client)
                 .await
-                .map_err(|e| {
-                    tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                    anyhow!("Error retrieving results").into()
-                }),
+                .map_err(to_field_error),
             None => Err(anyhow::anyhow!(
                 "Getting a substance without providing a substance name is not supported."
             )
@@ -42,11 +42,11 @@ impl QueryRoot {
         }
     }
     #[field(deprecation = "Please use `products::product` instead")]
-    async fn product(&self, _context: &Context<'_>, name: String) -> FieldResult<Product> {
-        get_product(name).await.map_err(|e| {
-            tracing::error!("Error fetching results from Azure search service: {:?}", e);
-            anyhow!("Error retrieving results").into()
-        })
+    async fn product(&self, context: &Context<'_>, name: String) -> FieldResult<Product> {
+        let context = context.data::<AzureContext>()?;
+        get_product(&context.products_client, &name)
+            .await
+            .map_err(to_field_error)
     }
 
     #[field(deprecation = "Please use `products::substances_index` instead")]
@@ -58,10 +58,7 @@ impl QueryRoot {
         let context = context.data::<AzureContext>()?;
         get_substances_index(&context.products_client, letter.chars().next().unwrap())
             .await
-            .map_err(|e| {
-                tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                anyhow!("Error retrieving results").into()
-            })
+            .map_err(to_field_error)
     }
 
     #[field(deprecation = "Please use `products::products_index` instead")]
@@ -73,10 +70,7 @@ impl QueryRoot {
         let context = context.data::<AzureContext>()?;
         get_products_index(&context.products_client, &substance)
             .await
-            .map_err(|e| {
-                tracing::error!("Error fetching results from Azure search service: {:?}", e);
-                anyhow!("Error retrieving results").into()
-            })
+            .map_err(to_field_error)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -92,23 +86,33 @@ impl QueryRoot {
         territory_types: Option<Vec<TerritoryType>>,
     ) -> FieldResult<Documents> {
         let context = context.data::<AzureContext>()?;
-        let offset = get_offset_or_default(skip, after, 0);
+        let page = PageRequest {
+            first,
+            after,
+            skip,
+            query: QueryKey::new(
+                search.as_deref().unwrap_or_default(),
+                &[
+                    ("doc_type", filter_values(&document_types)),
+                    ("territory", filter_values(&territory_types)),
+                ],
+                DocumentRelevance::default().scoring_profile(),
+            ),
+            ..PageRequest::default()
+        };
 
         get_documents(
             &context.products_client,
             search.as_deref().unwrap_or(" "),
-            first,
-            offset,
+            DocumentRelevance::default(),
+            &page,
             document_types,
             territory_types,
             None,
         )
         .await
         .map(Into::into)
-        .map_err(|e| {
-            tracing::error!("Error fetching results from Azure search service: {:?}", e);
-            anyhow!("Error retrieving results").into()
-        })
+        .map_err(to_field_error)
     }
 
     async fn products(&self, _context: &Context<'_>) -> FieldResult<Products> {
@@ -135,33 +139,3 @@ impl ApiSchema {
         )
     }
 }
-
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    use crate::pagination::convert_after_to_offset;
-    use test_case::test_case;
-
-    #[test_case("LTE=".to_string(), 0; "for the first page of results")]
-    #[test_case("MA==".to_string(), 1; "for grabbing the second result onwards")]
-    #[test_case("OQ==".to_string(), 10; "for the second page of results when pagesize is 10")]
-    #[test_case(base64::encode("1229".to_string()), 1230; "for as late as page 124")]
-    fn test_convert_after_to_offset(encoded: String, expected: i32) {
-        assert_eq!(convert_after_to_offset(encoded).unwrap(), expected);
-    }
-
-    #[test_case(Some(10), Some("LTE=".to_string()), 15, 0; "matches after when only after is provided")]
-    #[test_case(Some(10), None, 15, 10; "matches skip when only skip is provided")]
-    #[test_case(None, Some("LTE=".to_string()), 15, 0; "matches after when both are provided")]
-    #[test_case(None, None, 10, 10; "matches default when neither are provided")]
-    fn test_get_offset_or_default(
-        skip: Option<i32>,
-        after: Option<String>,
-        default: i32,
-        expected: i32,
-    ) {
-        let offset = get_offset_or_default(skip, after, default);
-        assert_eq!(offset, expected);
-    }
-}
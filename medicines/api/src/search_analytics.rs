@@ -0,0 +1,162 @@
+use crate::get_env_or_default;
+use search_client::{SearchEvent, SearchRecorder};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+const DEFAULT_FILE: &str = "search-analytics.jsonl";
+
+enum Destination {
+    Log,
+    File(Mutex<File>),
+}
+
+/// Writes search events to the logs (with the target `search_analytics`) or appends them to
+/// a file as JSON lines, for `search query_report` to summarise.
+pub struct AnalyticsSink {
+    destination: Destination,
+    redact: bool,
+}
+
+impl AnalyticsSink {
+    pub fn log() -> Self {
+        Self {
+            destination: Destination::Log,
+            redact: false,
+        }
+    }
+
+    pub fn file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            destination: Destination::File(Mutex::new(file)),
+            redact: false,
+        })
+    }
+
+    /// Drops the free text from each search term before it is written (see
+    /// `SearchEvent::redacted`).
+    pub fn redact(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    fn write_line(file: &Mutex<File>, event: &SearchEvent) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        let mut file = file.lock().expect("search analytics file lock poisoned");
+        file.write_all(&line)
+    }
+}
+
+impl SearchRecorder for AnalyticsSink {
+    fn record(&self, event: SearchEvent) {
+        let event = if self.redact { event.redacted() } else { event };
+        match &self.destination {
+            Destination::Log => tracing::info!(
+                target: "search_analytics",
+                index = %event.index,
+                term = %event.term,
+                filter = ?event.filter,
+                result_count = ?event.result_count,
+                latency_ms = event.latency_ms,
+                top = ?event.top,
+                skip = ?event.skip,
+                "search"
+            ),
+            Destination::File(file) => {
+                if let Err(e) = Self::write_line(file, &event) {
+                    tracing::warn!("Failed to record search: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// The sink set by `SEARCH_ANALYTICS` (`off`, `log` or `file`, default `off`). Events are
+/// appended to `SEARCH_ANALYTICS_FILE` in `file` mode, and free text is dropped from them
+/// unless `SEARCH_ANALYTICS_REDACT` is `false`.
+pub fn sink_from_env() -> Option<Arc<dyn SearchRecorder>> {
+    let redact = get_env_or_default("SEARCH_ANALYTICS_REDACT", true);
+    let sink = match get_env_or_default("SEARCH_ANALYTICS", "off".to_string()).as_str() {
+        "log" => AnalyticsSink::log(),
+        "file" => {
+            let path = get_env_or_default("SEARCH_ANALYTICS_FILE", DEFAULT_FILE.to_string());
+            match AnalyticsSink::file(&path) {
+                Ok(sink) => sink,
+                Err(e) => {
+                    tracing::error!("Not recording searches, can't open {}: {}", path, e);
+                    return None;
+                }
+            }
+        }
+        _ => return None,
+    };
+    Some(Arc::new(sink.redact(redact)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use search_client::{InMemorySearchClient, InstrumentedSearch, Search, SearchOptions};
+    use serde_json::{json, Value};
+    use std::{env, fs};
+    use tokio_test::block_on;
+
+    fn given_a_client_recording_to(
+        sink: AnalyticsSink,
+    ) -> InstrumentedSearch<InMemorySearchClient> {
+        let client = InMemorySearchClient::from_documents(
+            "products-index",
+            vec![
+                json!({ "metadata_storage_name": "a", "product_name": "IBUPROFEN 200MG TABLETS" }),
+            ],
+        );
+        InstrumentedSearch::new(client, "products-index").with_recorder(Arc::new(sink))
+    }
+
+    fn when_searching_for(client: &impl Search, term: &str) {
+        let _: Value = block_on(client.search(SearchOptions::new(term))).unwrap();
+    }
+
+    fn then_the_file_has(path: &Path) -> Vec<SearchEvent> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn file_sink_appends_a_line_per_search() {
+        let path = env::temp_dir().join("search-analytics-appends.jsonl");
+        let _ = fs::remove_file(&path);
+        let client = given_a_client_recording_to(AnalyticsSink::file(&path).unwrap());
+
+        when_searching_for(&client, "Ibuprofen");
+        when_searching_for(&client, "nurofen 200 mg");
+
+        let events = then_the_file_has(&path);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].term, "ibuprofen");
+        assert_eq!(events[0].result_count, Some(1));
+        assert_eq!(events[1].term, "nurofen 200mg");
+        assert_eq!(events[1].result_count, Some(0));
+    }
+
+    #[test]
+    fn redacting_sink_drops_free_text() {
+        let path = env::temp_dir().join("search-analytics-redacts.jsonl");
+        let _ = fs::remove_file(&path);
+        let client = given_a_client_recording_to(AnalyticsSink::file(&path).unwrap().redact(true));
+
+        when_searching_for(&client, "jane smith ibuprofen 200mg");
+
+        let events = then_the_file_has(&path);
+        assert_eq!(events[0].term, "* * * 200mg");
+    }
+}
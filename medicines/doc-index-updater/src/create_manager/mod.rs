@@ -14,6 +14,7 @@ use crate::{
 };
 use anyhow::anyhow;
 use async_trait::async_trait;
+use search_client::SearchClientError;
 use search_index::add_blob_to_search_index;
 use std::{collections::HashMap, time::Duration};
 use tokio::time::delay_for;
@@ -66,21 +67,39 @@ async fn handle_processing_error_for_create_message<T>(
 where
     T: RemovableMessage<CreateMessage>,
 {
-    if let ProcessMessageError::StorageClientError(StorageClientError::SftpError(
-        SftpError::CouldNotRetrieveFile,
-    )) = error
-    {
-        tracing::warn!("Couldn't find file. Updating state to Error and removing message.");
-        let _ = state_manager
-            .set_status(
-                removable_message.get_message().job_id,
-                JobStatus::Error {
-                    message: "Couldn't find file".to_string(),
-                    code: "404".to_string(),
-                },
-            )
-            .await?;
-        let _ = removable_message.remove().await?;
+    match error {
+        ProcessMessageError::StorageClientError(StorageClientError::SftpError(
+            SftpError::CouldNotRetrieveFile,
+        )) => {
+            tracing::warn!("Couldn't find file. Updating state to Error and removing message.");
+            let _ = state_manager
+                .set_status(
+                    removable_message.get_message().job_id,
+                    JobStatus::Error {
+                        message: "Couldn't find file".to_string(),
+                        code: "404".to_string(),
+                    },
+                )
+                .await?;
+            let _ = removable_message.remove().await?;
+        }
+        ProcessMessageError::SearchClient(SearchClientError::InvalidRequest(message)) => {
+            tracing::warn!(
+                "Index entry was rejected ({}). Updating state to Error and removing message.",
+                message
+            );
+            let _ = state_manager
+                .set_status(
+                    removable_message.get_message().job_id,
+                    JobStatus::Error {
+                        message,
+                        code: "400".to_string(),
+                    },
+                )
+                .await?;
+            let _ = removable_message.remove().await?;
+        }
+        _ => {}
     }
     Ok(())
 }
@@ -168,6 +187,10 @@ mod test {
         ))
     }
 
+    fn given_the_index_entry_was_rejected() -> ProcessMessageError {
+        SearchClientError::InvalidRequest("The request is invalid.".to_string()).into()
+    }
+
     fn given_we_have_a_create_message() -> TestRemovableMessage<CreateMessage> {
         TestRemovableMessage::<CreateMessage> {
             message: get_test_create_message(Uuid::new_v4()),
@@ -219,4 +242,22 @@ mod test {
             "Message should be removed"
         );
     }
+
+    #[test]
+    fn test_rejected_index_entry_removes_create_message() {
+        let mut removable_message = given_we_have_a_create_message();
+        let error = given_the_index_entry_was_rejected();
+
+        let result = when_we_handle_the_error(
+            &mut removable_message,
+            error,
+            TestJobStatusClient::accepted(),
+        );
+
+        assert!(result.is_ok());
+        assert!(
+            removable_message.remove_was_called,
+            "Message should be removed"
+        );
+    }
 }
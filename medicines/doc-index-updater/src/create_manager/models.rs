@@ -1,8 +1,7 @@
 use super::sanitiser::{SanitisedString, VecSanitisedString};
 use crate::{create_manager::Blob, models::Document};
 use chrono::{SecondsFormat, Utc};
-use regex::Regex;
-use search_client::models::{DocumentType, IndexEntry, TerritoryType};
+use search_client::models::{DocumentType, IndexEntry, ProductLicence, TerritoryType};
 use std::{collections::HashMap, str};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -18,26 +17,14 @@ pub struct BlobMetadata {
     pub keywords: Option<VecSanitisedString>,
 }
 
+// Licences are found in the metadata rather than parsed, as it may hold more than one, or
+// the JSON array of them that `format_product_licence` produces.
 fn derive_territory(pl_number: &str, territory: Option<TerritoryType>) -> Option<TerritoryType> {
-    match territory {
-        Some(t) => Some(t),
-        None => match pl_number
-            .chars()
-            .filter(|c| c.is_alphabetic())
-            .collect::<String>()
-            .as_ref()
-        {
-            "PL" => Some(TerritoryType::UK),
-            "PLPI" => Some(TerritoryType::UK),
-            "PLNI" => Some(TerritoryType::NI),
-            "PLGB" => Some(TerritoryType::GB),
-            "THRNI" => Some(TerritoryType::NI),
-            "THRGB" => Some(TerritoryType::GB),
-            "NRNI" => Some(TerritoryType::NI),
-            "NRGB" => Some(TerritoryType::GB),
-            _ => None,
-        },
-    }
+    territory.or_else(|| {
+        ProductLicence::find_all(pl_number)
+            .first()
+            .and_then(ProductLicence::territory)
+    })
 }
 
 impl BlobMetadata {
@@ -150,7 +137,10 @@ impl From<Blob> for IndexEntry {
                 .unwrap_or_default()
                 .join(", "),
             title: blob.metadata.title.to_string(),
-            pl_number: vec![blob.metadata.pl_number.to_string()],
+            pl_number: ProductLicence::find_all(&blob.metadata.pl_number)
+                .iter()
+                .map(ProductLicence::canonical)
+                .collect(),
             territory: blob.metadata.territory,
             file_name: blob.metadata.file_name.to_string(),
             doc_type: blob.metadata.doc_type,
@@ -199,21 +189,12 @@ pub fn create_facets_by_active_substance(
 }
 
 pub fn format_product_licence(input: &str) -> String {
-    lazy_static! {
-        static ref RE_WHITESPACE: Regex = Regex::new(r"(\s+|/|_|-)").expect("cannot compile regex");
-        static ref RE_PL: Regex = Regex::new(r"(?i:[A-Z]+)(\s+|/|_|-)*\d{5}(\s+|/|_|-)*\d{4}")
-            .expect("cannot compile regex");
-    }
-    let product_licences: Vec<String> = RE_PL
-        .find_iter(input)
-        .map(|m| {
-            RE_WHITESPACE
-                .replace_all(m.as_str(), "")
-                .to_ascii_uppercase()
-        })
-        .collect();
-
-    to_json(product_licences)
+    to_json(
+        ProductLicence::find_all(input)
+            .iter()
+            .map(ProductLicence::canonical)
+            .collect(),
+    )
 }
 
 #[cfg(test)]
@@ -328,7 +309,11 @@ mod test {
     #[test_case("PLNI 12345/1234", "[\"PLNI123451234\"]")]
     #[test_case("THR 12345/1234", "[\"THR123451234\"]")]
     #[test_case("NR 12345/1234", "[\"NR123451234\"]")]
-    #[test_case("NEW 12345/1234", "[\"NEW123451234\"]")]
+    #[test_case(
+        "PL 12345/1234, PLGB 23456/2345",
+        "[\"PL123451234\",\"PLGB234562345\"]"
+    )]
+    #[test_case("NEW 12345/1234", "[]")]
     #[test_case("12345/1234", "[]")]
     #[test_case("NO PL", "[]")]
     fn format_product_licence_test(input: &str, output: &str) {
@@ -375,18 +360,20 @@ mod test {
         )
     }
 
-    #[test_case(None, "PL123456", Some(TerritoryType::UK))]
-    #[test_case(None, "PLPI123456", Some(TerritoryType::UK))]
-    #[test_case(None, "PLNI123456", Some(TerritoryType::NI))]
-    #[test_case(None, "PLGB123456", Some(TerritoryType::GB))]
-    #[test_case(None, "THRNI123456", Some(TerritoryType::NI))]
-    #[test_case(None, "THRGB123456", Some(TerritoryType::GB))]
-    #[test_case(None, "NRNI123456", Some(TerritoryType::NI))]
-    #[test_case(None, "NRGB123456", Some(TerritoryType::GB))]
-    #[test_case(Some(TerritoryType::UK), "PLNI123456", Some(TerritoryType::UK))]
-    #[test_case(Some(TerritoryType::NI), "PL123456", Some(TerritoryType::NI))]
-    #[test_case(Some(TerritoryType::NI), "PLPI123456", Some(TerritoryType::NI))]
-    #[test_case(Some(TerritoryType::GB), "PLNI123456", Some(TerritoryType::GB))]
+    #[test_case(None, "PL 12345/1234", Some(TerritoryType::UK))]
+    #[test_case(None, "PLPI 12345/1234", Some(TerritoryType::UK))]
+    #[test_case(None, "PLNI 12345/1234", Some(TerritoryType::NI))]
+    #[test_case(None, "PLGB 12345/1234", Some(TerritoryType::GB))]
+    #[test_case(None, "THRNI 12345/1234", Some(TerritoryType::NI))]
+    #[test_case(None, "THRGB 12345/1234", Some(TerritoryType::GB))]
+    #[test_case(None, "NRNI 12345/1234", Some(TerritoryType::NI))]
+    #[test_case(None, "NRGB 12345/1234", Some(TerritoryType::GB))]
+    #[test_case(None, "THR 12345/1234", None)]
+    #[test_case(None, "[\"PLGB123451234\",\"PLNI123451234\"]", Some(TerritoryType::GB))]
+    #[test_case(Some(TerritoryType::UK), "PLNI 12345/1234", Some(TerritoryType::UK))]
+    #[test_case(Some(TerritoryType::NI), "PL 12345/1234", Some(TerritoryType::NI))]
+    #[test_case(Some(TerritoryType::NI), "PLPI 12345/1234", Some(TerritoryType::NI))]
+    #[test_case(Some(TerritoryType::GB), "PLNI 12345/1234", Some(TerritoryType::GB))]
     fn test_derive_territory(
         territory: Option<TerritoryType>,
         pl_number: &str,
@@ -1,10 +1,10 @@
 use crate::create_manager::Blob;
-use search_client::{models::IndexEntry, CreateIndexEntry};
+use search_client::{models::IndexEntry, CreateIndexEntry, SearchClientError};
 
 pub async fn add_blob_to_search_index(
     search_client: impl CreateIndexEntry,
     blob: Blob,
-) -> Result<(), anyhow::Error> {
+) -> Result<(), SearchClientError> {
     let entry: IndexEntry = blob.into();
 
     tracing::debug!("Creating index entry ({:?})", entry);
@@ -12,9 +12,9 @@ use anyhow::anyhow;
 use async_trait::async_trait;
 use search_client::{
     models::{IndexEntry, IndexResult},
-    AzureSearchClient, CreateIndexEntry, DeleteIndexEntry,
+    CreateIndexEntry, DeleteIndexEntry, SearchClient, SearchClientError,
 };
-use std::time::Duration;
+use std::{convert::TryFrom, time::Duration};
 use storage_client::{AzureBlobStorage, DeleteBlob};
 use tokio::time::delay_for;
 use uuid::Uuid;
@@ -99,6 +99,22 @@ where
         ProcessMessageError::FailedDeletingBlob(_, _) => {
             tracing::error!("{}", error_message);
         }
+        ProcessMessageError::SearchClient(SearchClientError::InvalidRequest(_)) => {
+            tracing::error!("{}", error_message);
+            state_manager
+                .set_status(
+                    removable_message.get_message().job_id,
+                    JobStatus::Error {
+                        message: error_message,
+                        code: "400".to_string(),
+                    },
+                )
+                .await?;
+            let _remove = removable_message.remove().await?;
+        }
+        ProcessMessageError::SearchClient(e) if e.is_retryable() => {
+            tracing::warn!("{}, message will be retried", e);
+        }
         _ => {}
     }
 
@@ -108,7 +124,7 @@ where
 pub async fn process_message(message: DeleteMessage) -> Result<Uuid, ProcessMessageError> {
     tracing::info!("Message received: {:?} ", message);
 
-    let search_client = AzureSearchClient::new();
+    let search_client = SearchClient::new();
     let storage_client = AzureBlobStorage::permanent();
 
     process_delete_message(message, storage_client, search_client, AuditLogger {}).await
@@ -143,8 +159,11 @@ async fn process_delete_message(
             &index_record
         );
 
+        let index_entry = IndexEntry::try_from(index_record.clone()).map_err(|err| {
+            ProcessMessageError::FailedRestoringIndex(blob_name.clone(), err.to_string())
+        })?;
         search_client
-            .create_index_entry(IndexEntry::from(index_record.clone()))
+            .create_index_entry(index_entry)
             .await
             .map_err(|err| {
                 ProcessMessageError::FailedRestoringIndex(blob_name.clone(), err.to_string())
@@ -184,30 +203,46 @@ pub async fn get_index_record_from_content_id(
     content_id: &str,
     search_client: &impl SearchIndex,
 ) -> Result<IndexResult, ProcessMessageError> {
-    let search_results = search_client.search_index(content_id).await?;
-    for result in search_results.search_results {
-        if result.file_name == content_id {
-            return Ok(result);
+    let records = match search_client
+        .find_index_records("file_name", content_id)
+        .await
+    {
+        Ok(records) => records,
+        // Indexes created before file_name was made filterable reject the filter, so search
+        // for the content ID instead until they have been recreated.
+        Err(SearchClientError::InvalidRequest(message)) => {
+            tracing::warn!(
+                "Could not filter on file_name ({}), searching for {} instead",
+                message,
+                content_id
+            );
+            search_client
+                .search_index(content_id)
+                .await?
+                .search_results
+                .into_iter()
+                .filter(|result| result.file_name == content_id)
+                .collect()
         }
-    }
-    Err(ProcessMessageError::DocumentNotFoundInIndex(
-        content_id.to_string(),
-    ))
+        Err(e) => return Err(e.into()),
+    };
+
+    records
+        .into_iter()
+        .next()
+        .ok_or_else(|| ProcessMessageError::DocumentNotFoundInIndex(content_id.to_string()))
 }
 
 pub async fn get_index_record_from_metadata_storage_name(
     metadata_storage_name: &str,
     search_client: &impl SearchIndex,
 ) -> Result<IndexResult, ProcessMessageError> {
-    let search_results = search_client.search_index(metadata_storage_name).await?;
-    for result in search_results.search_results {
-        if result.metadata_storage_name == metadata_storage_name {
-            return Ok(result);
-        }
-    }
-    Err(ProcessMessageError::DocumentNotFoundInIndex(
-        metadata_storage_name.to_string(),
-    ))
+    search_client
+        .get_index_record(metadata_storage_name)
+        .await?
+        .ok_or_else(|| {
+            ProcessMessageError::DocumentNotFoundInIndex(metadata_storage_name.to_string())
+        })
 }
 
 #[cfg(test)]
@@ -310,6 +345,57 @@ mod test {
         assert_eq!(result.status, JobStatus::Accepted);
     }
 
+    #[test]
+    fn invalid_search_request_during_delete_removes_message_and_sets_job_status_as_error() {
+        let state_manager = given_a_state_manager();
+        let mut removable_message = given_we_have_a_delete_message();
+        let error = given_an_invalid_search_request();
+
+        block_on(handle_processing_error_for_delete_message(
+            &mut removable_message,
+            error,
+            &state_manager,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            removable_message.remove_was_called, true,
+            "Didn't remove message, but should"
+        );
+        let result =
+            block_on(state_manager.get_status(removable_message.get_message().job_id)).unwrap();
+        assert_eq!(
+            result.status,
+            JobStatus::Error {
+                message: String::from("Invalid search request: Invalid document key"),
+                code: String::from("400"),
+            },
+        );
+    }
+
+    #[test]
+    fn throttling_during_delete_leaves_message_to_be_retried() {
+        let state_manager = given_a_state_manager();
+        let mut removable_message = given_we_have_a_delete_message();
+        given_the_delete_job_is_accepted(removable_message.get_message().job_id, &state_manager);
+        let error = given_the_search_service_is_throttling();
+
+        block_on(handle_processing_error_for_delete_message(
+            &mut removable_message,
+            error,
+            &state_manager,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            removable_message.remove_was_called, false,
+            "Removed message, but shouldn't"
+        );
+        let result =
+            block_on(state_manager.get_status(removable_message.get_message().job_id)).unwrap();
+        assert_eq!(result.status, JobStatus::Accepted);
+    }
+
     #[test]
     fn failure_to_delete_blob_leaves_job_status_as_accepted() {
         let state_manager = given_a_state_manager();
@@ -473,6 +559,28 @@ mod test {
         }
     }
 
+    #[test]
+    fn unrecognised_document_types_are_not_restored() {
+        let removable_message = given_we_have_a_delete_message().message;
+        let search_client = given_a_search_client_with_an_unrecognised_document_type();
+        let storage_client = given_a_storage_client_that_cannot_delete_blob();
+        let logger = given_a_transaction_logger();
+
+        given_the_necessary_env_vars_are_initialised();
+
+        let result = block_on(process_delete_message(
+            removable_message,
+            storage_client,
+            search_client,
+            logger,
+        ));
+
+        assert!(matches!(
+            result,
+            Err(ProcessMessageError::FailedRestoringIndex(blob_name, _)) if blob_name == "storage_name"
+        ));
+    }
+
     #[test]
     fn failure_to_delete_index_returns_expected_error() {
         let removable_message = given_we_have_a_delete_message().message;
@@ -494,7 +602,10 @@ mod test {
             Err(e) => {
                 assert_eq!(
                     e.to_string(),
-                    ProcessMessageError::Generic(anyhow!("Index could not be deleted")).to_string()
+                    ProcessMessageError::from(given_a_search_service_error(
+                        "Index could not be deleted"
+                    ))
+                    .to_string()
                 );
             }
         }
@@ -504,6 +615,25 @@ mod test {
         env::set_var("STORAGE_CONTAINER", "storage_container");
     }
 
+    fn given_a_search_service_error(message: &str) -> SearchClientError {
+        SearchClientError::UnexpectedStatus {
+            status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.to_string(),
+        }
+    }
+
+    fn given_an_invalid_search_request() -> ProcessMessageError {
+        SearchClientError::InvalidRequest("Invalid document key".to_string()).into()
+    }
+
+    fn given_the_search_service_is_throttling() -> ProcessMessageError {
+        SearchClientError::Throttled {
+            status: reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            retry_after: None,
+        }
+        .into()
+    }
+
     fn given_document_not_found_in_index() -> ProcessMessageError {
         ProcessMessageError::DocumentNotFoundInIndex("any id".to_owned())
     }
@@ -594,10 +724,53 @@ mod test {
         then_document_not_found_in_index_error_is_raised(result);
     }
 
+    #[test]
+    fn index_record_is_found_by_exact_content_id_or_storage_name() {
+        let search_client = given_a_search_client_that_returns_results();
+
+        let by_content_id = block_on(get_index_record_from_unique_identifier(
+            &UniqueDocumentIdentifier::ContentId("our_id".to_string()),
+            &search_client,
+        ))
+        .unwrap();
+        let by_storage_name = block_on(get_index_record_from_unique_identifier(
+            &UniqueDocumentIdentifier::MetadataStorageName("storage_name".to_string()),
+            &search_client,
+        ))
+        .unwrap();
+
+        assert_eq!(by_content_id.metadata_storage_name, "storage_name");
+        assert_eq!(by_storage_name.file_name, "our_id");
+    }
+
+    #[test]
+    fn index_record_is_not_found_by_partial_content_id() {
+        let search_client = given_a_search_client_that_returns_results();
+
+        let result = block_on(get_index_record_from_content_id("our", &search_client))
+            .map(|record| record.metadata_storage_name);
+
+        then_document_not_found_in_index_error_is_raised(result);
+    }
+
+    #[test]
+    fn index_record_is_searched_for_when_file_name_cannot_be_filtered() {
+        let search_client = given_a_search_client_that_cannot_filter_by_file_name();
+
+        let found = block_on(get_index_record_from_content_id("our_id", &search_client))
+            .map(|record| record.metadata_storage_name);
+        let partial = block_on(get_index_record_from_content_id("our", &search_client))
+            .map(|record| record.metadata_storage_name);
+
+        assert_eq!(found.unwrap(), "storage_name");
+        then_document_not_found_in_index_error_is_raised(partial);
+    }
+
     fn given_a_search_client_that_returns_no_results() -> impl SearchIndex {
         TestAzureSearchClient {
             can_insert_index: true,
             can_delete_index: true,
+            can_filter_by_file_name: true,
             search_results: vec![],
         }
     }
@@ -607,6 +780,7 @@ mod test {
         TestAzureSearchClient {
             can_insert_index: true,
             can_delete_index: true,
+            can_filter_by_file_name: true,
             search_results: vec![given_an_index_search_result()],
         }
     }
@@ -616,6 +790,7 @@ mod test {
         TestAzureSearchClient {
             can_insert_index: false,
             can_delete_index: true,
+            can_filter_by_file_name: true,
             search_results: vec![given_an_index_search_result()],
         }
     }
@@ -625,6 +800,30 @@ mod test {
         TestAzureSearchClient {
             can_insert_index: true,
             can_delete_index: false,
+            can_filter_by_file_name: true,
+            search_results: vec![given_an_index_search_result()],
+        }
+    }
+
+    fn given_a_search_client_with_an_unrecognised_document_type(
+    ) -> impl SearchIndex + CreateIndexEntry + DeleteIndexEntry {
+        TestAzureSearchClient {
+            can_insert_index: true,
+            can_delete_index: true,
+            can_filter_by_file_name: true,
+            search_results: vec![IndexResult {
+                doc_type: DocumentType::Unknown,
+                ..given_an_index_search_result()
+            }],
+        }
+    }
+
+    // As Azure behaves for an index created before file_name was made filterable.
+    fn given_a_search_client_that_cannot_filter_by_file_name() -> impl SearchIndex {
+        TestAzureSearchClient {
+            can_insert_index: true,
+            can_delete_index: true,
+            can_filter_by_file_name: false,
             search_results: vec![given_an_index_search_result()],
         }
     }
@@ -674,17 +873,51 @@ mod test {
         pub search_results: Vec<IndexResult>,
         pub can_insert_index: bool,
         pub can_delete_index: bool,
+        pub can_filter_by_file_name: bool,
     }
 
     #[async_trait]
     impl SearchIndex for TestAzureSearchClient {
-        async fn search_index(&self, _search_term: &str) -> Result<IndexResults, reqwest::Error> {
+        async fn search_index(
+            &self,
+            _search_term: &str,
+        ) -> Result<IndexResults, SearchClientError> {
             Ok(IndexResults {
                 search_results: self.search_results.clone(),
                 context: String::from(""),
                 count: None,
             })
         }
+
+        async fn get_index_record(
+            &self,
+            metadata_storage_name: &str,
+        ) -> Result<Option<IndexResult>, SearchClientError> {
+            Ok(self
+                .search_results
+                .iter()
+                .find(|result| result.metadata_storage_name == metadata_storage_name)
+                .cloned())
+        }
+
+        async fn find_index_records(
+            &self,
+            field_name: &str,
+            field_value: &str,
+        ) -> Result<Vec<IndexResult>, SearchClientError> {
+            assert_eq!(field_name, "file_name");
+            if !self.can_filter_by_file_name {
+                return Err(SearchClientError::InvalidRequest(
+                    "Invalid expression: 'file_name' is not a filterable field.".to_string(),
+                ));
+            }
+            Ok(self
+                .search_results
+                .iter()
+                .filter(|result| result.file_name == field_value)
+                .cloned()
+                .collect())
+        }
     }
 
     #[async_trait]
@@ -693,9 +926,9 @@ mod test {
             &self,
             key_name: &str,
             _value: &str,
-        ) -> Result<AzureIndexChangedResults, anyhow::Error> {
+        ) -> Result<AzureIndexChangedResults, SearchClientError> {
             if !&self.can_delete_index {
-                return Err(anyhow!("Index could not be deleted"));
+                return Err(given_a_search_service_error("Index could not be deleted"));
             }
 
             let index_changed_result = AzureIndexChangedResult {
@@ -714,9 +947,9 @@ mod test {
         async fn create_index_entry(
             &self,
             _key_values: IndexEntry,
-        ) -> Result<AzureIndexChangedResults, anyhow::Error> {
+        ) -> Result<AzureIndexChangedResults, SearchClientError> {
             if !&self.can_insert_index {
-                return Err(anyhow!("Index could not be created"));
+                return Err(given_a_search_service_error("Index could not be created"));
             }
 
             let index_changed_result = AzureIndexChangedResult {
@@ -7,6 +7,7 @@ use crate::{
     service_bus_client::{create_factory, delete_factory, DocIndexUpdaterQueue},
     state_manager::{with_state, JobStatusClient, MyRedisError, StateManager},
 };
+use search_client::models::DocumentType;
 use time::Duration;
 use tracing_futures::Instrument;
 use uuid::Uuid;
@@ -31,6 +32,11 @@ pub struct FailedToAddToQueue;
 
 impl warp::reject::Reject for FailedToAddToQueue {}
 
+#[derive(Debug)]
+pub struct UnknownDocumentType;
+
+impl warp::reject::Reject for UnknownDocumentType {}
+
 pub async fn accept_job(
     state_manager: &impl JobStatusClient,
 ) -> Result<JobStatusResponse, MyRedisError> {
@@ -126,6 +132,11 @@ pub async fn check_in_document_handler(
     state_manager: &impl JobStatusClient,
     initiator_email: Option<String>,
 ) -> Result<JobStatusResponse, Rejection> {
+    if doc.document_type == DocumentType::Unknown {
+        tracing::warn!("Rejected document {} with an unknown document type", doc.id);
+        return Err(warp::reject::custom(UnknownDocumentType));
+    }
+
     if let Ok(mut queue) = create_factory().await {
         let id = accept_job(state_manager).await?.id;
         let correlation_id = id.to_string();
@@ -136,6 +136,9 @@ async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, Infa
     } else if let Some(AuthenticationFailed) = err.find() {
         code = StatusCode::UNAUTHORIZED;
         message = "AUTHENTICATION_FAILED";
+    } else if let Some(document_manager::UnknownDocumentType) = err.find() {
+        code = StatusCode::BAD_REQUEST;
+        message = "UNKNOWN_DOCUMENT_TYPE";
     } else {
         tracing::error!("Internal server error: {:?}", err);
         code = StatusCode::INTERNAL_SERVER_ERROR;
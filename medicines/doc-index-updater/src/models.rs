@@ -2,8 +2,8 @@ use crate::service_bus_client::ProcessMessageError;
 use async_trait::async_trait;
 use regex::Regex;
 use search_client::{
-    models::{DocumentType, IndexResults, TerritoryType},
-    AzureSearchClient, Search,
+    models::{DocumentType, IndexResult, IndexResults, TerritoryType},
+    Search, SearchClient, SearchClientError, SearchOptions,
 };
 use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::Debug;
@@ -258,13 +258,40 @@ impl Message for DeleteMessage {
 
 #[async_trait]
 pub trait SearchIndex {
-    async fn search_index(&self, search_term: &str) -> Result<IndexResults, reqwest::Error>;
+    async fn search_index(&self, search_term: &str) -> Result<IndexResults, SearchClientError>;
+
+    async fn get_index_record(
+        &self,
+        metadata_storage_name: &str,
+    ) -> Result<Option<IndexResult>, SearchClientError>;
+
+    async fn find_index_records(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<IndexResult>, SearchClientError>;
 }
 
 #[async_trait]
-impl SearchIndex for AzureSearchClient {
-    async fn search_index(&self, search_term: &str) -> Result<IndexResults, reqwest::Error> {
-        self.search::<IndexResults>(search_term).await
+impl SearchIndex for SearchClient {
+    async fn search_index(&self, search_term: &str) -> Result<IndexResults, SearchClientError> {
+        self.search::<IndexResults>(SearchOptions::new(search_term))
+            .await
+    }
+
+    async fn get_index_record(
+        &self,
+        metadata_storage_name: &str,
+    ) -> Result<Option<IndexResult>, SearchClientError> {
+        self.get_document(metadata_storage_name).await
+    }
+
+    async fn find_index_records(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<IndexResult>, SearchClientError> {
+        self.find_by_field(field_name, field_value).await
     }
 }
 
@@ -9,6 +9,7 @@ use async_trait::async_trait;
 use azure_sdk_core::errors::AzureError;
 use azure_sdk_service_bus::{event_hub::PeekLockResponse, prelude::Client};
 use hyper::StatusCode;
+use search_client::SearchClientError;
 use thiserror::Error;
 use time::Duration;
 use tracing_futures::Instrument;
@@ -156,6 +157,8 @@ pub enum ProcessMessageError {
     #[error("Cannot restore index for blob with ID {0}: {1}")]
     FailedRestoringIndex(String, String),
     #[error(transparent)]
+    SearchClient(#[from] SearchClientError),
+    #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
     #[error(transparent)]
     Generic(#[from] anyhow::Error),
@@ -9,13 +9,40 @@ use azure_sdk_core::{
 };
 use azure_sdk_storage_blob::Blob;
 use azure_sdk_storage_core::prelude::*;
-use std::collections::HashMap;
+use search_client::credentials::{self, TokenCredential, TokenSource};
+use std::{collections::HashMap, sync::Arc};
+use url::Url;
+
+lazy_static! {
+    // Shared, so that every client uses the same token until it needs refreshing.
+    static ref STORAGE_TOKEN: Option<Arc<TokenCredential<Box<dyn TokenSource>>>> =
+        credentials::token_source_from_env(credentials::STORAGE_SCOPE)
+            .map(|source| Arc::new(TokenCredential::new(source)));
+}
+
+#[derive(Clone)]
+enum StorageCredential {
+    MasterKey(String),
+    Token(Arc<TokenCredential<Box<dyn TokenSource>>>),
+}
+
+// A bearer token if `AZURE_AUTH_MODE` asks for one, otherwise the account's master key.
+fn storage_credential(master_key_variable: &str) -> StorageCredential {
+    match STORAGE_TOKEN.as_ref() {
+        Some(token) => StorageCredential::Token(token.clone()),
+        None => StorageCredential::MasterKey(
+            std::env::var(master_key_variable)
+                .unwrap_or_else(|_| panic!("Set env variable {} first!", master_key_variable)),
+        ),
+    }
+}
 
 pub struct AzureBlobStorage {
     pub container_name: String,
     prefix: String,
     storage_account: String,
-    master_key: String,
+    credential: StorageCredential,
+    blob_endpoint: Option<Url>,
 }
 
 impl AzureBlobStorage {
@@ -24,14 +51,14 @@ impl AzureBlobStorage {
             .expect("Set env variable STORAGE_CONTAINER_TEMPORARY first!");
         let storage_account =
             std::env::var("STORAGE_ACCOUNT").expect("Set env variable STORAGE_ACCOUNT first!");
-        let master_key = std::env::var("STORAGE_MASTER_KEY")
-            .expect("Set env variable STORAGE_MASTER_KEY first!");
+        let credential = storage_credential("STORAGE_MASTER_KEY");
 
         Self {
             container_name,
             prefix: "temp/".to_owned(),
             storage_account,
-            master_key,
+            credential,
+            blob_endpoint: blob_endpoint("STORAGE_BLOB_ENDPOINT"),
         }
     }
     pub fn permanent() -> Self {
@@ -39,14 +66,14 @@ impl AzureBlobStorage {
             std::env::var("STORAGE_CONTAINER").expect("Set env variable STORAGE_CONTAINER first!");
         let storage_account =
             std::env::var("STORAGE_ACCOUNT").expect("Set env variable STORAGE_ACCOUNT first!");
-        let master_key = std::env::var("STORAGE_MASTER_KEY")
-            .expect("Set env variable STORAGE_MASTER_KEY first!");
+        let credential = storage_credential("STORAGE_MASTER_KEY");
 
         Self {
             container_name,
             prefix: "".to_owned(),
             storage_account,
-            master_key,
+            credential,
+            blob_endpoint: blob_endpoint("STORAGE_BLOB_ENDPOINT"),
         }
     }
 
@@ -55,22 +82,55 @@ impl AzureBlobStorage {
             .expect("Set env variable LOG_STORAGE_CONTAINER first!");
         let storage_account = std::env::var("LOG_STORAGE_ACCOUNT")
             .expect("Set env variable LOG_STORAGE_ACCOUNT first!");
-        let master_key = std::env::var("LOG_STORAGE_MASTER_KEY")
-            .expect("Set env variable LOG_STORAGE_MASTER_KEY first!");
+        let credential = storage_credential("LOG_STORAGE_MASTER_KEY");
 
         Self {
             container_name,
             prefix: "".to_owned(),
             storage_account,
-            master_key,
+            credential,
+            blob_endpoint: blob_endpoint("LOG_STORAGE_BLOB_ENDPOINT"),
         }
     }
 
-    pub fn get_azure_client(&self) -> Result<Box<dyn Client>, StorageClientError> {
-        let client = base64::decode(&self.master_key)
-            .map(|_| client::with_access_key(&self.storage_account, &self.master_key))?;
+    pub async fn get_azure_client(&self) -> Result<Box<dyn Client>, StorageClientError> {
+        match (&self.blob_endpoint, &self.credential) {
+            // The emulator client signs requests with the emulator's well-known account and key.
+            (Some(endpoint), _) => Ok(Box::new(client::with_emulator(endpoint, endpoint))),
+            (None, StorageCredential::MasterKey(master_key)) => {
+                base64::decode(master_key)?;
+                Ok(Box::new(client::with_access_key(
+                    &self.storage_account,
+                    master_key,
+                )))
+            }
+            (None, StorageCredential::Token(token)) => {
+                let token = token.token().await.map_err(|e| {
+                    StorageClientError::ClientError(format!("Couldn't get access token: {}", e))
+                })?;
+                Ok(Box::new(client::with_bearer_token(
+                    self.storage_account.clone(),
+                    token,
+                )))
+            }
+        }
+    }
 
-        Ok(Box::new(client))
+    fn blob_url(&self, name: &str) -> String {
+        match &self.blob_endpoint {
+            // Emulators address blobs by path rather than by subdomain.
+            Some(endpoint) => format!(
+                "{}/{}/{}/{}",
+                endpoint.as_str().trim_end_matches('/'),
+                &self.storage_account,
+                &self.container_name,
+                name
+            ),
+            None => format!(
+                "https://{}.blob.core.windows.net/{}/{}",
+                &self.storage_account, &self.container_name, name
+            ),
+        }
     }
 }
 
@@ -82,7 +142,7 @@ impl StorageClient for AzureBlobStorage {
         licence_number: &str,
         metadata_ref: HashMap<&str, &str>,
     ) -> Result<StorageFile, StorageClientError> {
-        let storage_client = self.get_azure_client()?;
+        let storage_client = self.get_azure_client().await?;
 
         let file_digest = md5::compute(file_data);
         let name = format!("{}{}", &self.prefix, file_name(licence_number, file_data));
@@ -102,10 +162,7 @@ impl StorageClient for AzureBlobStorage {
                 StorageClientError::UploadError(format!("Couldn't create blob: {:?}", e))
             })?;
 
-        let path = format!(
-            "https://{}.blob.core.windows.net/{}/{}",
-            &self.storage_account, &self.container_name, &name
-        );
+        let path = self.blob_url(&name);
 
         Ok(StorageFile { name, path })
     }
@@ -122,7 +179,7 @@ impl StorageClient for AzureBlobStorage {
         Ok(file_data)
     }
     async fn append_to_file(&self, file_name: &str, body: &[u8]) -> Result<(), StorageClientError> {
-        let storage_client = self.get_azure_client()?;
+        let storage_client = self.get_azure_client().await?;
         storage_client
             .put_append_block()
             .with_container_name(&self.container_name)
@@ -141,9 +198,52 @@ impl StorageClient for AzureBlobStorage {
     }
 }
 
+// Set to use a local emulator or mock server, e.g. `http://127.0.0.1:10000/` for Azurite.
+fn blob_endpoint(key: &str) -> Option<Url> {
+    std::env::var(key).ok().map(|endpoint| {
+        Url::parse(&endpoint).unwrap_or_else(|e| panic!("{} is not a valid URL: {}", key, e))
+    })
+}
+
 fn file_name(licence_number: &str, file_data: &[u8]) -> String {
     let mut hash = sha1::Sha1::new();
     hash.update(licence_number.as_bytes());
     hash.update(file_data);
     hash.digest().to_string()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn given_blob_storage(blob_endpoint: Option<&str>) -> AzureBlobStorage {
+        AzureBlobStorage {
+            container_name: "docs".to_owned(),
+            prefix: "".to_owned(),
+            storage_account: "devstoreaccount1".to_owned(),
+            credential: StorageCredential::MasterKey("".to_owned()),
+            blob_endpoint: blob_endpoint.map(|endpoint| Url::parse(endpoint).unwrap()),
+        }
+    }
+
+    #[test]
+    fn blob_url_defaults_to_azure() {
+        let storage = given_blob_storage(None);
+
+        assert_eq!(
+            storage.blob_url("abc"),
+            "https://devstoreaccount1.blob.core.windows.net/docs/abc"
+        );
+    }
+
+    #[test]
+    fn blob_url_uses_the_configured_endpoint() {
+        let storage = given_blob_storage(Some("http://127.0.0.1:10000/"));
+
+        assert_eq!(
+            storage.blob_url("abc"),
+            "http://127.0.0.1:10000/devstoreaccount1/docs/abc"
+        );
+    }
+}
@@ -13,7 +13,8 @@ pub trait DeleteBlob {
 #[async_trait]
 impl DeleteBlob for AzureBlobStorage {
     async fn delete_blob(&mut self, blob_name: &str) -> Result<(), StorageClientError> {
-        self.get_azure_client()?
+        self.get_azure_client()
+            .await?
             .delete_blob()
             .with_container_name(&self.container_name)
             .with_blob_name(&blob_name)
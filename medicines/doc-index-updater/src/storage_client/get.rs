@@ -15,7 +15,8 @@ pub trait GetBlob {
 impl GetBlob for AzureBlobStorage {
     async fn get_blob(&self, blob_name: &str) -> Result<BlobResponse, StorageClientError> {
         let blob = self
-            .get_azure_client()?
+            .get_azure_client()
+            .await?
             .get_blob()
             .with_container_name(&self.container_name)
             .with_blob_name(&blob_name)
@@ -1,4 +1,5 @@
 use regex::Regex;
+use search_client::models::{LicencePrefix, ProductLicence};
 use std::str;
 
 pub fn sanitize(s: &str) -> String {
@@ -55,29 +56,14 @@ pub fn create_facets_by_active_substance(active_substances: Vec<String>) -> Vec<
     facets
 }
 
+// The spreadsheets often leave the prefix off, and all of those are plain PL numbers.
 pub fn extract_product_licences(input: &str) -> String {
-    lazy_static! {
-        static ref RE_WHITESPACE: Regex = Regex::new(r"(\s+|/|_|-)").expect("cannot compile regex");
-        static ref RE_PL: Regex = Regex::new(r"(?i:\b|PL)(\s+|/|_|-)*\d{5}(\s+|/|_|-)*\d{4}")
-            .expect("cannot compile regex");
-    }
-    let product_licences: Vec<String> = RE_PL
-        .find_iter(input)
-        .map(|m| {
-            RE_WHITESPACE
-                .replace_all(m.as_str(), "")
-                .to_ascii_uppercase()
-        })
-        .map(|s| {
-            if s.starts_with("PL") {
-                s
-            } else {
-                String::from("PL") + s.as_str()
-            }
-        })
-        .collect();
-
-    to_json(product_licences)
+    to_json(
+        ProductLicence::find_all_with_default(input, LicencePrefix::Pl)
+            .iter()
+            .map(ProductLicence::canonical)
+            .collect(),
+    )
 }
 
 #[cfg(test)]
@@ -196,6 +182,13 @@ mod test {
         assert_eq!(extract_product_licences(input), output);
     }
     #[test]
+    fn extract_product_licences_keeps_other_prefixes() {
+        let input = "PLGB 12345/1234, THR 23456/2345, 34567/3456";
+        let output = "[\"PLGB123451234\",\"THR234562345\",\"PL345673456\"]";
+
+        assert_eq!(extract_product_licences(input), output);
+    }
+    #[test]
     fn extract_product_license_test_not_found() {
         assert_eq!(extract_product_licences("no pl number here"), "[]");
     }
@@ -0,0 +1,286 @@
+use crate::{
+    error::from_value,
+    models::{AutocompleteResults, FacetQueryResults, FacetResults, SuggestResults},
+    query_parser::SearchQuery,
+    Search, SearchClientError, SearchOptions,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{sync::Arc, time::Instant};
+
+/// What happened when someone searched, for tuning synonyms and scoring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchEvent {
+    pub timestamp: DateTime<Utc>,
+    pub index: String,
+    /// The search term in a canonical form, so that searches for the same thing can be
+    /// counted together (see `SearchEvent::redacted`).
+    pub term: String,
+    pub filter: Option<String>,
+    /// How many documents matched, or `None` if the search failed.
+    pub result_count: Option<u64>,
+    pub latency_ms: u64,
+    pub top: Option<i32>,
+    pub skip: Option<i32>,
+}
+
+impl SearchEvent {
+    /// The event with any free text in the term replaced by `*`, as people sometimes search
+    /// for names or symptoms. Numbers, strengths and licence numbers are kept.
+    pub fn redacted(self) -> Self {
+        Self {
+            term: SearchQuery::parse(&self.term).normalized_text(true),
+            ..self
+        }
+    }
+}
+
+/// Somewhere to send search events, such as a log or a file.
+pub trait SearchRecorder: Send + Sync {
+    fn record(&self, event: SearchEvent);
+}
+
+/// Records an event for every `search` made through the client it wraps. The other methods
+/// are passed straight through.
+#[derive(Clone)]
+pub struct InstrumentedSearch<S> {
+    inner: S,
+    index: String,
+    recorder: Option<Arc<dyn SearchRecorder>>,
+}
+
+impl<S> InstrumentedSearch<S> {
+    /// Wraps `inner`, labelling its events with `index`. Nothing is recorded until a
+    /// recorder is given.
+    pub fn new(inner: S, index: &str) -> Self {
+        Self {
+            inner,
+            index: index.to_string(),
+            recorder: None,
+        }
+    }
+
+    pub fn with_recorder(mut self, recorder: Arc<dyn SearchRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+fn result_count(results: &Value) -> Option<u64> {
+    results["@odata.count"]
+        .as_u64()
+        .or_else(|| results["value"].as_array().map(|value| value.len() as u64))
+}
+
+#[async_trait]
+impl<S> Search for InstrumentedSearch<S>
+where
+    S: Search + Send + Sync,
+{
+    async fn search<T>(&self, options: SearchOptions) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let recorder = match &self.recorder {
+            Some(recorder) => recorder,
+            None => return self.inner.search(options).await,
+        };
+
+        let mut event = SearchEvent {
+            timestamp: Utc::now(),
+            index: self.index.clone(),
+            term: SearchQuery::parse(&options.search_term).normalized_text(false),
+            filter: options.filter.as_ref().map(|filter| filter.to_string()),
+            result_count: None,
+            latency_ms: 0,
+            top: options.top,
+            skip: options.skip,
+        };
+        let started = Instant::now();
+        let results: Result<Value, _> = self.inner.search(options).await;
+        event.latency_ms = started.elapsed().as_millis() as u64;
+        event.result_count = results.as_ref().ok().and_then(result_count);
+        recorder.record(event);
+
+        from_value(results?)
+    }
+
+    async fn search_by_facet_field(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<FacetResults, SearchClientError> {
+        self.inner
+            .search_by_facet_field(field_name, field_value)
+            .await
+    }
+
+    async fn search_facets(
+        &self,
+        options: SearchOptions,
+    ) -> Result<FacetQueryResults, SearchClientError> {
+        self.inner.search_facets(options).await
+    }
+
+    async fn suggest(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<SuggestResults, SearchClientError> {
+        self.inner.suggest(search_term, top).await
+    }
+
+    async fn autocomplete(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<AutocompleteResults, SearchClientError> {
+        self.inner.autocomplete(search_term, top).await
+    }
+
+    async fn filter_by_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.inner
+            .filter_by_collection_field(field_name, field_value)
+            .await
+    }
+
+    async fn filter_by_non_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.inner
+            .filter_by_non_collection_field(field_name, field_value)
+            .await
+    }
+
+    async fn get_document<T>(&self, key: &str) -> Result<Option<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.inner.get_document(key).await
+    }
+
+    async fn find_by_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.inner.find_by_field(field_name, field_value).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{filter::field, InMemorySearchClient};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::sync::Mutex;
+    use tokio_test::block_on;
+
+    #[derive(Default)]
+    struct RecordingRecorder(Mutex<Vec<SearchEvent>>);
+
+    impl SearchRecorder for RecordingRecorder {
+        fn record(&self, event: SearchEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    fn given_an_instrumented_client(
+        recorder: Arc<RecordingRecorder>,
+    ) -> InstrumentedSearch<InMemorySearchClient> {
+        let documents = vec![
+            json!({ "metadata_storage_name": "a", "product_name": "IBUPROFEN 200MG TABLETS", "doc_type": "Spc" }),
+            json!({ "metadata_storage_name": "b", "product_name": "IBUPROFEN 400MG TABLETS", "doc_type": "Pil" }),
+        ];
+        InstrumentedSearch::new(
+            InMemorySearchClient::from_documents("products-index", documents),
+            "products-index",
+        )
+        .with_recorder(recorder)
+    }
+
+    fn given_an_event(term: &str) -> SearchEvent {
+        SearchEvent {
+            timestamp: Utc::now(),
+            index: "products-index".to_string(),
+            term: term.to_string(),
+            filter: None,
+            result_count: Some(0),
+            latency_ms: 12,
+            top: Some(10),
+            skip: None,
+        }
+    }
+
+    #[test]
+    fn records_each_search_with_its_result_count() {
+        let recorder = Arc::new(RecordingRecorder::default());
+        let client = given_an_instrumented_client(recorder.clone());
+
+        let options = SearchOptions::new("Ibuprofen")
+            .filter(field("doc_type").eq("Spc"))
+            .top(10)
+            .skip(0);
+        let _: Value = block_on(client.search(options)).unwrap();
+        let _: Value = block_on(client.search(SearchOptions::new("Nurofen"))).unwrap();
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].term, "ibuprofen");
+        assert_eq!(events[0].filter.as_deref(), Some("doc_type eq 'Spc'"));
+        assert_eq!(events[0].result_count, Some(1));
+        assert_eq!((events[0].top, events[0].skip), (Some(10), Some(0)));
+        assert_eq!(events[1].term, "nurofen");
+        assert_eq!(events[1].result_count, Some(0));
+    }
+
+    #[test]
+    fn records_nothing_without_a_recorder() {
+        let client = InstrumentedSearch::new(
+            InMemorySearchClient::new_with_index("products-index".to_string()),
+            "products-index",
+        );
+
+        let results: Result<Value, _> = block_on(client.search(SearchOptions::new("x")));
+
+        assert!(results.is_ok());
+    }
+
+    #[test]
+    fn redacting_drops_free_text_but_keeps_strengths_and_licences() {
+        let event = given_an_event("john smith ibuprofen 200mg PL123451234");
+
+        assert_eq!(event.redacted().term, "* * * 200mg PL123451234");
+    }
+
+    #[test]
+    fn events_round_trip_through_json() {
+        let event = given_an_event("ibuprofen");
+
+        let json = serde_json::to_string(&event).unwrap();
+
+        assert_eq!(serde_json::from_str::<SearchEvent>(&json).unwrap(), event);
+    }
+}
@@ -0,0 +1,561 @@
+use crate::{
+    error::from_value,
+    models::{
+        AutocompleteResults, AzureIndexChangedResults, FacetQueryResults, FacetResults,
+        IndexBatchAction, IndexEntry, SuggestResults,
+    },
+    CreateIndexEntry, DeleteIndexEntry, FuzzinessRules, IndexBatch, Search, SearchClientError,
+    SearchOptions,
+};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The `Search` methods whose results are cached separately, each with its own policy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CachedMethod {
+    Search,
+    SearchByFacetField,
+    SearchFacets,
+    Suggest,
+    Autocomplete,
+    Filter,
+}
+
+/// How long results are served from memory, and how many are kept. A capacity of zero
+/// turns caching off for that method.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub ttl: Duration,
+    pub capacity: usize,
+}
+
+impl CachePolicy {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self { ttl, capacity }
+    }
+
+    fn default_for(method: CachedMethod) -> Self {
+        match method {
+            // The A-Z indexes only change when documents do, and there are few of them.
+            CachedMethod::SearchByFacetField => Self::new(Duration::from_secs(60 * 60), 100),
+            CachedMethod::SearchFacets => Self::new(Duration::from_secs(5 * 60), 100),
+            CachedMethod::Suggest | CachedMethod::Autocomplete => {
+                Self::new(Duration::from_secs(5 * 60), 1000)
+            }
+            CachedMethod::Search | CachedMethod::Filter => Self::new(Duration::from_secs(60), 500),
+        }
+    }
+}
+
+/// Serves repeated queries from memory rather than sending them to `inner` again.
+///
+/// Each method has a least-recently-used cache with its own time to live and size limit.
+/// Errors are never cached. Clones share their caches, and writes made through this client
+/// clear them; call `invalidate` when the index is changed some other way.
+///
+/// ```
+/// use search_client::{CachePolicy, CachedMethod, CachedSearch, InMemorySearchClient};
+/// use std::time::Duration;
+///
+/// let client = CachedSearch::new(InMemorySearchClient::new_with_index("products-index".to_string()))
+///     .with_policy(
+///         CachedMethod::Suggest,
+///         CachePolicy::new(Duration::from_secs(30), 200),
+///     );
+/// ```
+#[derive(Clone)]
+pub struct CachedSearch<S> {
+    inner: S,
+    caches: Arc<Caches>,
+}
+
+struct Caches {
+    values: HashMap<CachedMethod, Lru<String, Value>>,
+    facets_by_field: Lru<(String, String), FacetResults>,
+    facets: Lru<String, FacetQueryResults>,
+    suggestions: Lru<(String, i32), SuggestResults>,
+    completions: Lru<(String, i32), AutocompleteResults>,
+}
+
+impl<S> CachedSearch<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            caches: Arc::new(Caches {
+                values: [CachedMethod::Search, CachedMethod::Filter]
+                    .iter()
+                    .map(|method| (*method, Lru::with_default_policy(*method)))
+                    .collect(),
+                facets_by_field: Lru::with_default_policy(CachedMethod::SearchByFacetField),
+                facets: Lru::with_default_policy(CachedMethod::SearchFacets),
+                suggestions: Lru::with_default_policy(CachedMethod::Suggest),
+                completions: Lru::with_default_policy(CachedMethod::Autocomplete),
+            }),
+        }
+    }
+
+    /// Replaces the policy for `method`. Call this before the client is cloned or used, as
+    /// it starts the cache for `method` afresh.
+    pub fn with_policy(mut self, method: CachedMethod, policy: CachePolicy) -> Self {
+        let caches = Arc::get_mut(&mut self.caches)
+            .expect("Cache policies must be set before the client is shared");
+        match method {
+            CachedMethod::Search | CachedMethod::Filter => {
+                caches.values.insert(method, Lru::new(policy));
+            }
+            CachedMethod::SearchByFacetField => caches.facets_by_field = Lru::new(policy),
+            CachedMethod::SearchFacets => caches.facets = Lru::new(policy),
+            CachedMethod::Suggest => caches.suggestions = Lru::new(policy),
+            CachedMethod::Autocomplete => caches.completions = Lru::new(policy),
+        }
+        self
+    }
+
+    /// Forgets every cached result, e.g. after documents have been added to or removed from the index.
+    pub fn invalidate(&self) {
+        for cache in self.caches.values.values() {
+            cache.clear();
+        }
+        self.caches.facets_by_field.clear();
+        self.caches.facets.clear();
+        self.caches.suggestions.clear();
+        self.caches.completions.clear();
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn values(&self, method: CachedMethod) -> &Lru<String, Value> {
+        &self.caches.values[&method]
+    }
+}
+
+// The query that `options` sends to Azure, with any synonyms expanded. The fuzziness rules
+// are the same for every search through a client, so the defaults stand in for them here.
+fn cache_key(options: &SearchOptions) -> String {
+    let query = options.query(&FuzzinessRules::default(), "");
+    serde_json::to_string(&query).expect("Query parameters are always serialisable")
+}
+
+#[async_trait]
+impl<S> Search for CachedSearch<S>
+where
+    S: Search + Send + Sync,
+{
+    async fn search<T>(&self, options: SearchOptions) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let cache = self.values(CachedMethod::Search);
+        let key = cache_key(&options);
+        let value = match cache.get(&key) {
+            Some(value) => value,
+            None => {
+                let value: Value = self.inner.search(options).await?;
+                cache.insert(key, value.clone());
+                value
+            }
+        };
+        from_value(value)
+    }
+
+    async fn search_by_facet_field(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<FacetResults, SearchClientError> {
+        let cache = &self.caches.facets_by_field;
+        let key = (field_name.to_string(), field_value.to_string());
+        if let Some(results) = cache.get(&key) {
+            return Ok(results);
+        }
+        let results = self
+            .inner
+            .search_by_facet_field(field_name, field_value)
+            .await?;
+        cache.insert(key, results.clone());
+        Ok(results)
+    }
+
+    async fn search_facets(
+        &self,
+        options: SearchOptions,
+    ) -> Result<FacetQueryResults, SearchClientError> {
+        let cache = &self.caches.facets;
+        let key = cache_key(&options);
+        if let Some(results) = cache.get(&key) {
+            return Ok(results);
+        }
+        let results = self.inner.search_facets(options).await?;
+        cache.insert(key, results.clone());
+        Ok(results)
+    }
+
+    async fn suggest(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<SuggestResults, SearchClientError> {
+        let cache = &self.caches.suggestions;
+        let key = (search_term.to_string(), top);
+        if let Some(results) = cache.get(&key) {
+            return Ok(results);
+        }
+        let results = self.inner.suggest(search_term, top).await?;
+        cache.insert(key, results.clone());
+        Ok(results)
+    }
+
+    async fn autocomplete(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<AutocompleteResults, SearchClientError> {
+        let cache = &self.caches.completions;
+        let key = (search_term.to_string(), top);
+        if let Some(results) = cache.get(&key) {
+            return Ok(results);
+        }
+        let results = self.inner.autocomplete(search_term, top).await?;
+        cache.insert(key, results.clone());
+        Ok(results)
+    }
+
+    async fn filter_by_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let cache = self.values(CachedMethod::Filter);
+        let key = format!("any:{}:{}", field_name, field_value);
+        let value = match cache.get(&key) {
+            Some(value) => value,
+            None => {
+                let value: Value = self
+                    .inner
+                    .filter_by_collection_field(field_name, field_value)
+                    .await?;
+                cache.insert(key, value.clone());
+                value
+            }
+        };
+        from_value(value)
+    }
+
+    async fn filter_by_non_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let cache = self.values(CachedMethod::Filter);
+        let key = format!("eq:{}:{}", field_name, field_value);
+        let value = match cache.get(&key) {
+            Some(value) => value,
+            None => {
+                let value: Value = self
+                    .inner
+                    .filter_by_non_collection_field(field_name, field_value)
+                    .await?;
+                cache.insert(key, value.clone());
+                value
+            }
+        };
+        from_value(value)
+    }
+
+    // Lookups are used to find documents to change, so always go to the index.
+    async fn get_document<T>(&self, key: &str) -> Result<Option<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.inner.get_document(key).await
+    }
+
+    async fn find_by_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.inner.find_by_field(field_name, field_value).await
+    }
+}
+
+#[async_trait]
+impl<S> CreateIndexEntry for CachedSearch<S>
+where
+    S: CreateIndexEntry + Send + Sync,
+{
+    async fn create_index_entry(
+        &self,
+        key_values: IndexEntry,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        let results = self.inner.create_index_entry(key_values).await;
+        self.invalidate();
+        results
+    }
+}
+
+#[async_trait]
+impl<S> DeleteIndexEntry for CachedSearch<S>
+where
+    S: DeleteIndexEntry + Send + Sync,
+{
+    async fn delete_index_entry(
+        &self,
+        key_name: &str,
+        value: &str,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        let results = self.inner.delete_index_entry(key_name, value).await;
+        self.invalidate();
+        results
+    }
+}
+
+#[async_trait]
+impl<S> IndexBatch for CachedSearch<S>
+where
+    S: IndexBatch + Send + Sync,
+{
+    async fn index_batch(
+        &self,
+        actions: Vec<IndexBatchAction>,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        let results = self.inner.index_batch(actions).await;
+        self.invalidate();
+        results
+    }
+}
+
+// Small enough that finding the least recently used entry by scanning is cheaper than
+// keeping a separate ordering up to date.
+struct Lru<K, V> {
+    policy: CachePolicy,
+    entries: Mutex<LruEntries<K, V>>,
+}
+
+struct LruEntries<K, V> {
+    clock: u64,
+    map: HashMap<K, LruEntry<V>>,
+}
+
+struct LruEntry<V> {
+    value: V,
+    inserted: Instant,
+    last_used: u64,
+}
+
+impl<K, V> Lru<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    fn new(policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            entries: Mutex::new(LruEntries {
+                clock: 0,
+                map: HashMap::new(),
+            }),
+        }
+    }
+
+    fn with_default_policy(method: CachedMethod) -> Self {
+        Self::new(CachePolicy::default_for(method))
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().expect("Search cache was poisoned");
+        entries.clock += 1;
+        let clock = entries.clock;
+        let ttl = self.policy.ttl;
+
+        match entries.map.get_mut(key) {
+            Some(entry) if entry.inserted.elapsed() < ttl => {
+                entry.last_used = clock;
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                entries.map.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: K, value: V) {
+        if self.policy.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("Search cache was poisoned");
+        entries.clock += 1;
+        let clock = entries.clock;
+
+        if !entries.map.contains_key(&key) && entries.map.len() >= self.policy.capacity {
+            let ttl = self.policy.ttl;
+            entries
+                .map
+                .retain(|_, entry| entry.inserted.elapsed() < ttl);
+        }
+        if !entries.map.contains_key(&key) && entries.map.len() >= self.policy.capacity {
+            let least_recently_used = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                entries.map.remove(&least_recently_used);
+            }
+        }
+        entries.map.insert(
+            key,
+            LruEntry {
+                value,
+                inserted: Instant::now(),
+                last_used: clock,
+            },
+        );
+    }
+
+    fn clear(&self) {
+        self.entries
+            .lock()
+            .expect("Search cache was poisoned")
+            .map
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{InMemorySearchClient, SynonymMap};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use tokio_test::block_on;
+
+    fn given_a_policy(capacity: usize) -> CachePolicy {
+        CachePolicy::new(Duration::from_secs(60), capacity)
+    }
+
+    fn given_a_cached_client() -> CachedSearch<InMemorySearchClient> {
+        CachedSearch::new(InMemorySearchClient::from_documents(
+            "products-index",
+            vec![json!({
+                "metadata_storage_name": "a",
+                "product_name": "IBUPROFEN 200MG TABLETS",
+                "facets": ["I", "I, IBUPROFEN"]
+            })],
+        ))
+    }
+
+    #[test]
+    fn lru_evicts_the_least_recently_used_entry() {
+        let lru = Lru::new(given_a_policy(2));
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        lru.get(&"a");
+        lru.insert("c", 3);
+
+        assert_eq!(lru.get(&"a"), Some(1));
+        assert_eq!(lru.get(&"b"), None);
+        assert_eq!(lru.get(&"c"), Some(3));
+    }
+
+    #[test]
+    fn lru_expires_entries_after_their_ttl() {
+        let lru = Lru::new(CachePolicy::new(Duration::from_millis(0), 10));
+        lru.insert("a", 1);
+
+        assert_eq!(lru.get(&"a"), None);
+    }
+
+    #[test]
+    fn lru_with_no_capacity_caches_nothing() {
+        let lru = Lru::new(given_a_policy(0));
+        lru.insert("a", 1);
+
+        assert_eq!(lru.get(&"a"), None);
+    }
+
+    #[test]
+    fn repeated_searches_are_served_from_the_cache() {
+        let client = given_a_cached_client();
+        let search = || {
+            let results: Value = block_on(client.search(SearchOptions::new("ibuprofen"))).unwrap();
+            results["value"].as_array().unwrap().len()
+        };
+
+        assert_eq!(search(), 1);
+        block_on(
+            client
+                .inner()
+                .delete_index_entry("metadata_storage_name", "a"),
+        )
+        .unwrap();
+        assert_eq!(search(), 1, "Should still be cached");
+
+        client.invalidate();
+        assert_eq!(search(), 0);
+    }
+
+    #[test]
+    fn cache_keys_follow_the_query_azure_is_sent() {
+        let with_synonyms = |rules: &str| {
+            SearchOptions::new("nurofen").synonyms(Arc::new(SynonymMap::from_solr(rules)))
+        };
+
+        assert_eq!(
+            cache_key(&with_synonyms("nurofen, ibuprofen")),
+            cache_key(&with_synonyms("nurofen, ibuprofen"))
+        );
+        assert_eq!(
+            cache_key(&with_synonyms("aspirin, acetylsalicylic acid")),
+            cache_key(&SearchOptions::new("nurofen"))
+        );
+        assert!(
+            cache_key(&with_synonyms("nurofen, ibuprofen"))
+                != cache_key(&SearchOptions::new("nurofen"))
+        );
+        assert!(
+            cache_key(&SearchOptions::new("nurofen").top(10))
+                != cache_key(&SearchOptions::new("nurofen"))
+        );
+    }
+
+    #[test]
+    fn writes_through_the_cache_invalidate_it() {
+        let client = given_a_cached_client();
+        let facet_count = || {
+            block_on(client.search_by_facet_field("facets", "I"))
+                .unwrap()
+                .facet_results
+                .facets[0]
+                .count
+        };
+
+        assert_eq!(facet_count(), 1);
+        block_on(client.delete_index_entry("metadata_storage_name", "a")).unwrap();
+        assert!(block_on(client.search_by_facet_field("facets", "I"))
+            .unwrap()
+            .facet_results
+            .facets
+            .is_empty());
+    }
+}
@@ -0,0 +1,694 @@
+//! How requests to Azure services are authenticated: with a key, or with an Azure AD bearer
+//! token from a service principal or a managed identity.
+//!
+//! `AZURE_AUTH_MODE` chooses which:
+//!
+//! - `key` (the default) uses the service's key, as before.
+//! - `client-credentials` signs in as a service principal with `AZURE_TENANT_ID`,
+//!   `AZURE_CLIENT_ID` and `AZURE_CLIENT_SECRET`. `AZURE_AUTHORITY_HOST` overrides
+//!   `https://login.microsoftonline.com`, e.g. to point at a local stand-in.
+//! - `managed-identity` asks the managed identity endpoint for a token, using
+//!   `IDENTITY_ENDPOINT` and `IDENTITY_HEADER` where the platform sets them and the instance
+//!   metadata service otherwise. `AZURE_CLIENT_ID` picks a user-assigned identity.
+//!
+//! Any other value panics, rather than quietly falling back to a key.
+
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use serde_derive::Deserialize;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The scope of tokens for Azure Cognitive Search.
+pub const SEARCH_SCOPE: &str = "https://search.azure.com/.default";
+/// The scope of tokens for Azure Storage.
+pub const STORAGE_SCOPE: &str = "https://storage.azure.com/.default";
+
+const DEFAULT_AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+const IMDS_ENDPOINT: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+// Tokens are refreshed this long before they expire, so that one isn't sent just as it runs
+// out, or half way through their life if that is sooner.
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// Adds whatever a request needs to be accepted by the service.
+#[async_trait]
+pub trait Credential: Send + Sync {
+    async fn authorize(&self, request: &mut reqwest::Request) -> Result<(), CredentialError>;
+}
+
+/// Sends a service key in the `api-key` header.
+#[derive(Clone)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    pub fn new(key: &str) -> Self {
+        Self(key.to_string())
+    }
+}
+
+#[async_trait]
+impl Credential for ApiKey {
+    async fn authorize(&self, request: &mut reqwest::Request) -> Result<(), CredentialError> {
+        request
+            .headers_mut()
+            .insert("api-key", header_value(&self.0)?);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Instant,
+    refresh_at: Instant,
+}
+
+impl AccessToken {
+    pub fn new(token: &str, expires_in: Duration) -> Self {
+        let now = Instant::now();
+        let margin = if expires_in / 2 < REFRESH_MARGIN {
+            expires_in / 2
+        } else {
+            REFRESH_MARGIN
+        };
+
+        Self {
+            token: token.to_string(),
+            expires_at: now + expires_in,
+            refresh_at: now + expires_in - margin,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+
+    fn needs_refresh(&self) -> bool {
+        Instant::now() >= self.refresh_at
+    }
+}
+
+/// Somewhere to get a new bearer token from.
+#[async_trait]
+pub trait TokenSource: Send + Sync {
+    async fn fetch_token(&self, client: &reqwest::Client) -> Result<AccessToken, CredentialError>;
+}
+
+#[async_trait]
+impl TokenSource for Box<dyn TokenSource> {
+    async fn fetch_token(&self, client: &reqwest::Client) -> Result<AccessToken, CredentialError> {
+        self.as_ref().fetch_token(client).await
+    }
+}
+
+/// The OAuth2 client credentials flow, for a service principal with a secret.
+#[derive(Clone)]
+pub struct ClientCredentials {
+    authority_host: String,
+    tenant_id: String,
+    client_id: String,
+    client_secret: String,
+    scope: String,
+}
+
+impl ClientCredentials {
+    pub fn new(tenant_id: &str, client_id: &str, client_secret: &str, scope: &str) -> Self {
+        Self {
+            authority_host: DEFAULT_AUTHORITY_HOST.to_string(),
+            tenant_id: tenant_id.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scope: scope.to_string(),
+        }
+    }
+
+    pub fn from_env(scope: &str) -> Self {
+        Self::new(
+            &crate::get_env("AZURE_TENANT_ID"),
+            &crate::get_env("AZURE_CLIENT_ID"),
+            &crate::get_env("AZURE_CLIENT_SECRET"),
+            scope,
+        )
+        .authority_host(
+            &std::env::var("AZURE_AUTHORITY_HOST")
+                .unwrap_or_else(|_| DEFAULT_AUTHORITY_HOST.to_string()),
+        )
+    }
+
+    pub fn authority_host(mut self, authority_host: &str) -> Self {
+        self.authority_host = authority_host.trim_end_matches('/').to_string();
+        self
+    }
+
+    fn token_url(&self) -> String {
+        format!(
+            "{}/{}/oauth2/v2.0/token",
+            self.authority_host, self.tenant_id
+        )
+    }
+}
+
+#[async_trait]
+impl TokenSource for ClientCredentials {
+    async fn fetch_token(&self, client: &reqwest::Client) -> Result<AccessToken, CredentialError> {
+        let request = client.post(&self.token_url()).form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+            ("scope", self.scope.as_str()),
+        ]);
+
+        request_token(request).await
+    }
+}
+
+/// The token endpoint of the managed identity of the pod or VM we are running on.
+#[derive(Clone)]
+pub struct ManagedIdentity {
+    endpoint: String,
+    api_version: String,
+    identity_header: Option<String>,
+    client_id: Option<String>,
+    resource: String,
+}
+
+impl ManagedIdentity {
+    /// The instance metadata service, for the system-assigned identity.
+    pub fn new(scope: &str) -> Self {
+        Self {
+            endpoint: IMDS_ENDPOINT.to_string(),
+            api_version: "2018-02-01".to_string(),
+            identity_header: None,
+            client_id: None,
+            resource: scope.trim_end_matches("/.default").to_string(),
+        }
+    }
+
+    pub fn from_env(scope: &str) -> Self {
+        let identity = match (
+            std::env::var("IDENTITY_ENDPOINT"),
+            std::env::var("IDENTITY_HEADER"),
+        ) {
+            (Ok(endpoint), Ok(header)) => Self::new(scope).endpoint(&endpoint, Some(&header)),
+            _ => Self::new(scope),
+        };
+
+        match std::env::var("AZURE_CLIENT_ID") {
+            Ok(client_id) => identity.client_id(&client_id),
+            Err(_) => identity,
+        }
+    }
+
+    /// An endpoint other than the instance metadata service, such as the one App Service
+    /// provides, which wants its secret in the `X-IDENTITY-HEADER` header.
+    pub fn endpoint(mut self, endpoint: &str, identity_header: Option<&str>) -> Self {
+        self.endpoint = endpoint.to_string();
+        self.api_version = "2019-08-01".to_string();
+        self.identity_header = identity_header.map(String::from);
+        self
+    }
+
+    /// Use a user-assigned identity rather than the system-assigned one.
+    pub fn client_id(mut self, client_id: &str) -> Self {
+        self.client_id = Some(client_id.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl TokenSource for ManagedIdentity {
+    async fn fetch_token(&self, client: &reqwest::Client) -> Result<AccessToken, CredentialError> {
+        let mut query = vec![
+            ("api-version", self.api_version.as_str()),
+            ("resource", self.resource.as_str()),
+        ];
+        if let Some(client_id) = &self.client_id {
+            query.push(("client_id", client_id));
+        }
+
+        let mut request = client
+            .get(&self.endpoint)
+            .query(&query)
+            .header("Metadata", "true");
+        if let Some(identity_header) = &self.identity_header {
+            request = request.header("X-IDENTITY-HEADER", identity_header);
+        }
+
+        request_token(request).await
+    }
+}
+
+/// A bearer token from `source`, kept until shortly before it expires. Callers that want a
+/// token while it is being refreshed wait for the same refresh.
+pub struct TokenCredential<S> {
+    source: S,
+    client: reqwest::Client,
+    token: Mutex<Option<AccessToken>>,
+}
+
+impl<S> TokenCredential<S>
+where
+    S: TokenSource,
+{
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            client: reqwest::Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    pub async fn token(&self) -> Result<String, CredentialError> {
+        let mut cached = self.token.lock().await;
+
+        match cached.as_ref() {
+            Some(token) if !token.needs_refresh() => return Ok(token.token.clone()),
+            _ => {}
+        }
+
+        match self.source.fetch_token(&self.client).await {
+            Ok(token) => {
+                let value = token.token.clone();
+                *cached = Some(token);
+                Ok(value)
+            }
+            // The token we have is still good for a while, so carry on with it.
+            Err(e) => match cached.as_ref() {
+                Some(token) if !token.is_expired() => {
+                    tracing::warn!(
+                        "Could not refresh access token, using the current one: {}",
+                        e
+                    );
+                    Ok(token.token.clone())
+                }
+                _ => Err(e),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Credential for TokenCredential<S>
+where
+    S: TokenSource,
+{
+    async fn authorize(&self, request: &mut reqwest::Request) -> Result<(), CredentialError> {
+        let token = self.token().await?;
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, header_value(&format!("Bearer {}", token))?);
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum AuthMode {
+    Key,
+    ClientCredentials,
+    ManagedIdentity,
+}
+
+impl std::str::FromStr for AuthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "key" => Ok(AuthMode::Key),
+            "client-credentials" => Ok(AuthMode::ClientCredentials),
+            "managed-identity" => Ok(AuthMode::ManagedIdentity),
+            _ => Err(format!(
+                r#"AZURE_AUTH_MODE must be "key", "client-credentials" or "managed-identity", not "{}""#,
+                s
+            )),
+        }
+    }
+}
+
+impl AuthMode {
+    /// The mode named by `AZURE_AUTH_MODE`. Panics on any other value, so that a typo can't
+    /// quietly fall back to a key.
+    fn from_env() -> Self {
+        crate::get_env_or_default("AZURE_AUTH_MODE", "key")
+            .parse()
+            .unwrap_or_else(|e: String| panic!("{}", e))
+    }
+}
+
+/// The token source chosen by `AZURE_AUTH_MODE`, or `None` if keys should be used.
+pub fn token_source_from_env(scope: &str) -> Option<Box<dyn TokenSource>> {
+    match AuthMode::from_env() {
+        AuthMode::Key => None,
+        AuthMode::ClientCredentials => Some(Box::new(ClientCredentials::from_env(scope))),
+        AuthMode::ManagedIdentity => Some(Box::new(ManagedIdentity::from_env(scope))),
+    }
+}
+
+/// The credential chosen by `AZURE_AUTH_MODE`, reading the key from `key_variable` when
+/// keys should be used.
+pub fn from_env(key_variable: &str, scope: &str) -> Arc<dyn Credential> {
+    match token_source_from_env(scope) {
+        Some(source) => Arc::new(TokenCredential::new(source)),
+        None => Arc::new(ApiKey::new(&crate::get_env(key_variable))),
+    }
+}
+
+/// Why a token couldn't be had, or couldn't be sent.
+#[derive(Debug)]
+pub enum CredentialError {
+    /// The token endpoint couldn't be reached, or its response couldn't be read.
+    Request(reqwest::Error),
+    /// The token endpoint refused to issue a token.
+    Rejected {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    /// The token endpoint's response didn't contain a token we could use.
+    InvalidResponse(String),
+    /// The key or token can't be sent in a header.
+    InvalidHeader,
+}
+
+impl CredentialError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Request(e) => e.is_timeout() || e.is_connect(),
+            Self::Rejected { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+impl Display for CredentialError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "Could not request an access token: {}", e),
+            Self::Rejected { status, message } => write!(
+                f,
+                "Token endpoint refused to issue an access token ({}): {}",
+                status, message
+            ),
+            Self::InvalidResponse(message) => {
+                write!(f, "Unexpected response from token endpoint: {}", message)
+            }
+            Self::InvalidHeader => write!(f, "Credential is not a valid header value"),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for CredentialError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Request(e)
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, CredentialError> {
+    let mut value = HeaderValue::from_str(value).map_err(|_| CredentialError::InvalidHeader)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+// Azure AD gives the lifetime of a token as a number of seconds in `expires_in`; managed
+// identity endpoints give it as a string, and App Service only gives `expires_on`, a unix time.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<Value>,
+    expires_on: Option<Value>,
+}
+
+impl TokenResponse {
+    fn expires_in(&self) -> Option<Duration> {
+        if let Some(expires_in) = self.expires_in.as_ref().and_then(seconds) {
+            return Some(Duration::from_secs(expires_in));
+        }
+        let expires_on = self.expires_on.as_ref().and_then(seconds)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(Duration::from_secs(expires_on.saturating_sub(now)))
+    }
+}
+
+fn seconds(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+async fn request_token(request: reqwest::RequestBuilder) -> Result<AccessToken, CredentialError> {
+    let response = request.send().await?;
+    let status = response.status();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        return Err(CredentialError::Rejected {
+            status,
+            message: body,
+        });
+    }
+
+    let response: TokenResponse =
+        serde_json::from_str(&body).map_err(|e| CredentialError::InvalidResponse(e.to_string()))?;
+    let expires_in = response
+        .expires_in()
+        .ok_or_else(|| CredentialError::InvalidResponse("no expiry time".to_string()))?;
+
+    Ok(AccessToken::new(&response.access_token, expires_in))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio_test::block_on;
+
+    // A stand-in token endpoint, answering each request with the next of `responses` and
+    // keeping the requests it was sent.
+    fn given_a_token_endpoint(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::Mutex::new(vec![]));
+        let received = requests.clone();
+
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                received.lock().unwrap().push(read_request(&mut stream));
+                write!(
+                    stream,
+                    "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn read_request(stream: &mut std::net::TcpStream) -> String {
+        let mut request = vec![];
+        let mut buffer = [0; 1024];
+        loop {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(end_of_headers) = text.find("\r\n\r\n") {
+                let content_length = text
+                    .lines()
+                    .find_map(|line| {
+                        let line = line.to_ascii_lowercase();
+                        line.strip_prefix("content-length:")
+                            .map(|length| length.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if request.len() >= end_of_headers + 4 + content_length || read == 0 {
+                    return text;
+                }
+            }
+        }
+    }
+
+    fn given_a_request() -> reqwest::Request {
+        reqwest::Client::new()
+            .get("https://search_service.search.windows.net/indexes")
+            .build()
+            .unwrap()
+    }
+
+    struct CountingSource {
+        expires_in: Duration,
+        fetched: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TokenSource for CountingSource {
+        async fn fetch_token(&self, _: &reqwest::Client) -> Result<AccessToken, CredentialError> {
+            let count = self.fetched.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(AccessToken::new(
+                &format!("token-{}", count),
+                self.expires_in,
+            ))
+        }
+    }
+
+    fn given_a_counting_source(expires_in: Duration) -> TokenCredential<CountingSource> {
+        TokenCredential::new(CountingSource {
+            expires_in,
+            fetched: AtomicUsize::new(0),
+        })
+    }
+
+    #[test]
+    fn auth_modes_are_named_exactly() {
+        assert_eq!("key".parse::<AuthMode>(), Ok(AuthMode::Key));
+        assert_eq!(
+            "client-credentials".parse::<AuthMode>(),
+            Ok(AuthMode::ClientCredentials)
+        );
+        assert_eq!(
+            "managed-identity".parse::<AuthMode>(),
+            Ok(AuthMode::ManagedIdentity)
+        );
+    }
+
+    #[test]
+    fn misspelt_auth_modes_are_rejected() {
+        assert_eq!(
+            "managed_identity".parse::<AuthMode>(),
+            Err(r#"AZURE_AUTH_MODE must be "key", "client-credentials" or "managed-identity", not "managed_identity""#.to_string())
+        );
+    }
+
+    #[test]
+    fn api_key_is_sent_in_the_api_key_header() {
+        let mut request = given_a_request();
+
+        block_on(ApiKey::new("my_cool_api_key").authorize(&mut request)).unwrap();
+
+        assert_eq!(request.headers()["api-key"], "my_cool_api_key");
+        assert!(request.headers().get(AUTHORIZATION).is_none());
+    }
+
+    #[test]
+    fn token_credential_sends_a_bearer_token() {
+        let credential = given_a_counting_source(Duration::from_secs(3600));
+        let mut request = given_a_request();
+
+        block_on(credential.authorize(&mut request)).unwrap();
+
+        assert_eq!(request.headers()[AUTHORIZATION], "Bearer token-1");
+    }
+
+    #[test]
+    fn token_credential_reuses_a_token_until_it_is_due_for_refresh() {
+        let fresh = given_a_counting_source(Duration::from_secs(3600));
+        let expiring = given_a_counting_source(Duration::from_secs(0));
+
+        let fresh_tokens = block_on(async { (fresh.token().await, fresh.token().await) });
+        let expiring_tokens = block_on(async { (expiring.token().await, expiring.token().await) });
+
+        assert_eq!(fresh_tokens.1.unwrap(), "token-1");
+        assert_eq!(expiring_tokens.1.unwrap(), "token-2");
+    }
+
+    #[test]
+    fn access_tokens_are_refreshed_before_they_expire() {
+        let token = AccessToken::new("token", Duration::from_secs(3600));
+
+        assert_eq!(token.refresh_at + REFRESH_MARGIN, token.expires_at);
+        assert!(!token.needs_refresh());
+    }
+
+    #[test]
+    fn client_credentials_posts_the_secret_to_the_tenants_token_endpoint() {
+        let (url, requests) = given_a_token_endpoint(vec![(
+            200,
+            r#"{"token_type":"Bearer","expires_in":3599,"access_token":"aad-token"}"#,
+        )]);
+        let source = ClientCredentials::new("my-tenant", "my-client", "s3cret", SEARCH_SCOPE)
+            .authority_host(&url);
+
+        let token = block_on(source.fetch_token(&reqwest::Client::new())).unwrap();
+
+        assert_eq!(token.token, "aad-token");
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.starts_with("POST /my-tenant/oauth2/v2.0/token "));
+        assert!(request.ends_with("grant_type=client_credentials&client_id=my-client&client_secret=s3cret&scope=https%3A%2F%2Fsearch.azure.com%2F.default"));
+    }
+
+    #[test]
+    fn managed_identity_asks_for_a_token_for_the_resource() {
+        let (url, requests) = given_a_token_endpoint(vec![(
+            200,
+            r#"{"access_token":"mi-token","expires_in":"86399","resource":"https://storage.azure.com"}"#,
+        )]);
+        let source = ManagedIdentity::new(STORAGE_SCOPE)
+            .endpoint(&format!("{}/msi/token", url), Some("identity-secret"))
+            .client_id("user-assigned");
+
+        let token = block_on(source.fetch_token(&reqwest::Client::new())).unwrap();
+
+        assert_eq!(token.token, "mi-token");
+        let request = requests.lock().unwrap()[0].to_ascii_lowercase();
+        assert!(request.starts_with("get /msi/token?api-version=2019-08-01&resource=https%3a%2f%2fstorage.azure.com&client_id=user-assigned "));
+        assert!(request.contains("\r\nx-identity-header: identity-secret\r\n"));
+        assert!(request.contains("\r\nmetadata: true\r\n"));
+    }
+
+    #[test]
+    fn token_endpoint_errors_are_reported() {
+        let (url, _) = given_a_token_endpoint(vec![(401, r#"{"error":"invalid_client"}"#)]);
+        let source =
+            ClientCredentials::new("tenant", "client", "wrong", SEARCH_SCOPE).authority_host(&url);
+
+        let error = block_on(source.fetch_token(&reqwest::Client::new())).unwrap_err();
+
+        match error {
+            CredentialError::Rejected { status, message } => {
+                assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+                assert_eq!(message, r#"{"error":"invalid_client"}"#);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn token_lifetime_can_be_given_as_an_expiry_time() {
+        let expires_on = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 600;
+        let response: TokenResponse = serde_json::from_value(serde_json::json!({
+            "access_token": "token",
+            "expires_on": expires_on.to_string(),
+        }))
+        .unwrap();
+
+        let expires_in = response.expires_in().unwrap();
+
+        assert!(expires_in <= Duration::from_secs(600));
+        assert!(expires_in > Duration::from_secs(590));
+    }
+}
@@ -1,17 +1,16 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 #[cfg_attr(
     feature = "graphql",
-    async_graphql::Enum(desc = "Document type (SPC/PIL/PAR)"),
-    derive(Serialize, Deserialize, Debug, Ord, PartialOrd)
+    async_graphql::Enum(desc = "Document type (SPC/PIL/PAR/RMM/PSL/LABEL)"),
+    derive(Serialize, Debug, Ord, PartialOrd)
 )]
 #[cfg_attr(
     not(feature = "graphql"),
     derive(
         Serialize,
-        Deserialize,
         Debug,
         Copy,
         Clone,
@@ -22,12 +21,31 @@ use std::str::FromStr;
     )
 )]
 pub enum DocumentType {
-    #[serde(alias = "SPC")]
     Spc,
-    #[serde(alias = "PIL")]
     Pil,
-    #[serde(alias = "PAR")]
     Par,
+    /// Risk minimisation materials
+    Rmm,
+    /// Patient safety leaflet
+    Psl,
+    Label,
+    /// A type this version doesn't know about yet, so that documents indexed with
+    /// newer types can still be read.
+    Unknown,
+}
+
+impl DocumentType {
+    /// Every document type other than `Unknown`.
+    pub fn known() -> Vec<DocumentType> {
+        vec![
+            Self::Spc,
+            Self::Pil,
+            Self::Par,
+            Self::Rmm,
+            Self::Psl,
+            Self::Label,
+        ]
+    }
 }
 
 impl FromStr for DocumentType {
@@ -38,6 +56,10 @@ impl FromStr for DocumentType {
             "SPC" => Ok(Self::Spc),
             "PIL" => Ok(Self::Pil),
             "PAR" => Ok(Self::Par),
+            "RMM" => Ok(Self::Rmm),
+            "PSL" => Ok(Self::Psl),
+            "LABEL" => Ok(Self::Label),
+            "UNKNOWN" => Ok(Self::Unknown),
             _ => Err(DocTypeParseError {
                 source: s.to_string(),
             }),
@@ -51,10 +73,27 @@ impl Display for DocumentType {
             DocumentType::Spc => write!(f, "Spc"),
             DocumentType::Pil => write!(f, "Pil"),
             DocumentType::Par => write!(f, "Par"),
+            DocumentType::Rmm => write!(f, "Rmm"),
+            DocumentType::Psl => write!(f, "Psl"),
+            DocumentType::Label => write!(f, "Label"),
+            DocumentType::Unknown => write!(f, "Unknown"),
         }
     }
 }
 
+impl<'de> Deserialize<'de> for DocumentType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(s.parse().unwrap_or_else(|_| {
+            tracing::warn!("Unrecognised document type: {}", s);
+            Self::Unknown
+        }))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DocTypeParseError {
     source: String,
@@ -80,6 +119,10 @@ mod tests {
     #[test_case("Spc")]
     #[test_case("Pil")]
     #[test_case("Par")]
+    #[test_case("Rmm")]
+    #[test_case("Psl")]
+    #[test_case("Label")]
+    #[test_case("Unknown")]
     fn parses_document_and_formats_to_a_string(doc_type: &str) {
         use pretty_assertions::assert_eq;
 
@@ -96,6 +139,10 @@ mod tests {
     #[test_case("\"Pil\"", DocumentType::Pil; "pil titlecase")]
     #[test_case("\"PAR\"", DocumentType::Par; "par uppercase")]
     #[test_case("\"Par\"", DocumentType::Par; "par titlecase")]
+    #[test_case("\"RMM\"", DocumentType::Rmm; "rmm uppercase")]
+    #[test_case("\"Psl\"", DocumentType::Psl; "psl titlecase")]
+    #[test_case("\"label\"", DocumentType::Label; "label lowercase")]
+    #[test_case("\"Leaflet\"", DocumentType::Unknown; "unrecognised type")]
     fn deserializes_document_cases_insensitively(input: &str, expected: DocumentType) {
         use pretty_assertions::assert_eq;
 
@@ -103,4 +150,18 @@ mod tests {
 
         assert_eq!(from_str, expected);
     }
+
+    #[test]
+    fn does_not_parse_an_unrecognised_document_type() {
+        assert!("Leaflet".parse::<DocumentType>().is_err());
+    }
+
+    #[test]
+    fn serializes_new_document_types_as_they_are_displayed() {
+        use pretty_assertions::assert_eq;
+
+        let json = serde_json::to_string(&DocumentType::known()).unwrap();
+
+        assert_eq!(json, r#"["Spc","Pil","Par","Rmm","Psl","Label"]"#);
+    }
 }
@@ -0,0 +1,352 @@
+use crate::credentials::CredentialError;
+use crate::filter::InvalidFieldName;
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// The ways a request to the search service can fail, distinguished so that callers can
+/// tell a bad query from a missing index, or a throttled request worth retrying.
+#[derive(Debug)]
+pub enum SearchClientError {
+    /// 400: the search term, filter or document was rejected.
+    InvalidRequest(String),
+    /// 404: the index does not exist.
+    IndexNotFound(String),
+    /// 404 with no error body: the index exists, but has no document with the requested key.
+    DocumentNotFound,
+    /// 429 or 503: the service is busy. Azure may say how long to wait before retrying.
+    Throttled {
+        status: StatusCode,
+        retry_after: Option<Duration>,
+    },
+    /// Recent requests failed, so this one wasn't sent. Try again after `retry_after`.
+    CircuitOpen { retry_after: Duration },
+    /// 401 or 403: the api key or token was missing or rejected.
+    Unauthorized(StatusCode),
+    /// The service responded successfully but the body was not what we expected.
+    Deserialization {
+        source: serde_json::Error,
+        body: String,
+    },
+    /// Any other unsuccessful response.
+    UnexpectedStatus { status: StatusCode, message: String },
+    /// The request could not be built or sent, or the response could not be read.
+    Request(reqwest::Error),
+    /// No key or token could be had to authenticate the request.
+    Credential(CredentialError),
+}
+
+impl From<InvalidFieldName> for SearchClientError {
+    fn from(e: InvalidFieldName) -> Self {
+        Self::InvalidRequest(e.to_string())
+    }
+}
+
+impl SearchClientError {
+    pub(crate) fn from_response(
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+        index: &str,
+    ) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Self::InvalidRequest(error_message(body)),
+            // Looking up a missing document is the only 404 Azure sends without an error body.
+            StatusCode::NOT_FOUND if body.trim().is_empty() => Self::DocumentNotFound,
+            StatusCode::NOT_FOUND => Self::IndexNotFound(index.to_string()),
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => Self::Throttled {
+                status,
+                retry_after: retry_after(headers),
+            },
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized(status),
+            _ => Self::UnexpectedStatus {
+                status,
+                message: error_message(body),
+            },
+        }
+    }
+
+    /// The HTTP status of the response, if there was one.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::InvalidRequest(_) => Some(StatusCode::BAD_REQUEST),
+            Self::IndexNotFound(_) | Self::DocumentNotFound => Some(StatusCode::NOT_FOUND),
+            Self::Throttled { status, .. }
+            | Self::Unauthorized(status)
+            | Self::UnexpectedStatus { status, .. } => Some(*status),
+            Self::CircuitOpen { .. } | Self::Deserialization { .. } | Self::Credential(_) => None,
+            Self::Request(e) => e.status(),
+        }
+    }
+
+    /// Whether the same request might succeed if sent again later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Throttled { .. } | Self::CircuitOpen { .. } => true,
+            Self::UnexpectedStatus { status, .. } => status.is_server_error(),
+            Self::Request(e) => e.is_timeout() || e.is_connect(),
+            Self::Credential(e) => e.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// How long the service asked us to wait before retrying, if it said.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Throttled { retry_after, .. } => *retry_after,
+            Self::CircuitOpen { retry_after } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SearchClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRequest(message) => write!(f, "Invalid search request: {}", message),
+            Self::IndexNotFound(index) => write!(f, "Search index not found: {}", index),
+            Self::DocumentNotFound => write!(f, "Document not found in the search index"),
+            Self::Throttled {
+                status,
+                retry_after: Some(retry_after),
+            } => write!(
+                f,
+                "Search service is throttling requests ({}), retry after {}s",
+                status,
+                retry_after.as_secs_f32()
+            ),
+            Self::Throttled { status, .. } => {
+                write!(f, "Search service is throttling requests ({})", status)
+            }
+            Self::CircuitOpen { retry_after } => write!(
+                f,
+                "Search service is unavailable, not sending requests for {}s",
+                retry_after.as_secs_f32()
+            ),
+            Self::Unauthorized(status) => {
+                write!(f, "Not authorised to use the search service ({})", status)
+            }
+            Self::Deserialization { source, body } => write!(
+                f,
+                "Could not deserialise search response: {} (body: {})",
+                source, body
+            ),
+            Self::UnexpectedStatus { status, message } => {
+                write!(f, "Search service responded with {}: {}", status, message)
+            }
+            Self::Request(e) => write!(f, "{}", e),
+            Self::Credential(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SearchClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Deserialization { source, .. } => Some(source),
+            Self::Request(e) => Some(e),
+            Self::Credential(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SearchClientError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Request(e)
+    }
+}
+
+impl From<CredentialError> for SearchClientError {
+    fn from(e: CredentialError) -> Self {
+        Self::Credential(e)
+    }
+}
+
+/// Reads the body of a response, turning unsuccessful statuses into the matching error.
+pub(crate) async fn handle_response<T>(
+    response: reqwest::Response,
+    index: &str,
+) -> Result<T, SearchClientError>
+where
+    T: DeserializeOwned,
+{
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        return Err(SearchClientError::from_response(
+            status, &headers, &body, index,
+        ));
+    }
+
+    serde_json::from_str(&body)
+        .map_err(|source| SearchClientError::Deserialization { source, body })
+}
+
+pub(crate) fn from_value<T>(value: serde_json::Value) -> Result<T, SearchClientError>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_value(value.clone()).map_err(|source| SearchClientError::Deserialization {
+        source,
+        body: value.to_string(),
+    })
+}
+
+#[derive(Deserialize)]
+struct AzureErrorBody {
+    error: AzureErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct AzureErrorDetail {
+    message: String,
+}
+
+// Azure wraps error messages as `{"error": {"code": "", "message": "..."}}`.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<AzureErrorBody>(body)
+        .map(|body| body.error.message)
+        .unwrap_or_else(|_| body.to_string())
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+
+    header("retry-after-ms")
+        .map(Duration::from_millis)
+        .or_else(|| header("retry-after").map(Duration::from_secs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use test_case::test_case;
+
+    fn given_headers(name: &'static str, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn bad_requests_are_invalid_with_the_azure_message() {
+        let body =
+            r#"{"error":{"code":"","message":"Invalid expression: Syntax error at position 7"}}"#;
+        let error = SearchClientError::from_response(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            body,
+            "products-index",
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid search request: Invalid expression: Syntax error at position 7"
+        );
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn not_found_names_the_index() {
+        let error = SearchClientError::from_response(
+            StatusCode::NOT_FOUND,
+            &HeaderMap::new(),
+            r#"{"error": {"code": "", "message": "The index 'products-index' for service 'mhra' was not found."}}"#,
+            "products-index",
+        );
+
+        assert!(
+            matches!(error, SearchClientError::IndexNotFound(index) if index == "products-index")
+        );
+    }
+
+    #[test]
+    fn not_found_without_a_body_is_a_missing_document() {
+        let error = SearchClientError::from_response(
+            StatusCode::NOT_FOUND,
+            &HeaderMap::new(),
+            "",
+            "products-index",
+        );
+
+        assert!(matches!(error, SearchClientError::DocumentNotFound));
+    }
+
+    #[test_case(
+        StatusCode::TOO_MANY_REQUESTS,
+        given_headers("retry-after", "10"),
+        Some(Duration::from_secs(10))
+    )]
+    #[test_case(
+        StatusCode::SERVICE_UNAVAILABLE,
+        given_headers("retry-after-ms", "1500"),
+        Some(Duration::from_millis(1500))
+    )]
+    #[test_case(StatusCode::SERVICE_UNAVAILABLE, HeaderMap::new(), None)]
+    fn throttling_is_retryable_after_the_given_time(
+        status: StatusCode,
+        headers: HeaderMap,
+        expected: Option<Duration>,
+    ) {
+        let error = SearchClientError::from_response(status, &headers, "", "products-index");
+
+        assert!(error.is_retryable());
+        assert_eq!(error.retry_after(), expected);
+        assert_eq!(error.status(), Some(status));
+    }
+
+    #[test_case(StatusCode::UNAUTHORIZED)]
+    #[test_case(StatusCode::FORBIDDEN)]
+    fn auth_failures_are_not_retryable(status: StatusCode) {
+        let error =
+            SearchClientError::from_response(status, &HeaderMap::new(), "", "products-index");
+
+        assert!(matches!(error, SearchClientError::Unauthorized(_)));
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn server_errors_are_retryable() {
+        let error = SearchClientError::from_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &HeaderMap::new(),
+            "Oops",
+            "products-index",
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "Search service responded with 500 Internal Server Error: Oops"
+        );
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn deserialisation_failures_keep_the_body() {
+        let response = reqwest::Response::from(
+            http::Response::builder()
+                .status(StatusCode::OK)
+                .body(r#"{"unexpected": true}"#)
+                .unwrap(),
+        );
+
+        let error =
+            tokio_test::block_on(handle_response::<Vec<String>>(response, "products-index"))
+                .unwrap_err();
+
+        assert!(
+            matches!(error, SearchClientError::Deserialization { body, .. } if body == r#"{"unexpected": true}"#)
+        );
+    }
+}
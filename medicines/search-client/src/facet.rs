@@ -0,0 +1,119 @@
+use crate::search_options::SortOrder;
+use std::fmt::{Display, Formatter};
+
+/// What facet values are ordered by before the top `count` of them are returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FacetSort {
+    Count,
+    Value,
+}
+
+/// Buckets numeric or date values rather than counting each distinct value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FacetInterval {
+    Number(u32),
+    Day,
+    Month,
+    Year,
+}
+
+/// A facet to count over the results of a search, rendered as an Azure facet expression.
+///
+/// ```
+/// use search_client::{FacetSort, FacetSpec, SortOrder};
+///
+/// let facet = FacetSpec::new("doc_type")
+///     .count(5)
+///     .sort_by(FacetSort::Value, SortOrder::Asc);
+///
+/// assert_eq!(facet.to_string(), "doc_type,count:5,sort:value");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FacetSpec {
+    pub(crate) field: String,
+    pub(crate) count: Option<u32>,
+    pub(crate) sort: Option<(FacetSort, SortOrder)>,
+    pub(crate) interval: Option<FacetInterval>,
+}
+
+impl FacetSpec {
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            count: None,
+            sort: None,
+            interval: None,
+        }
+    }
+
+    /// The number of values to return. Azure returns 10 otherwise.
+    pub fn count(mut self, count: u32) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Orders values before they are truncated to `count`. Azure orders by descending count otherwise.
+    pub fn sort_by(mut self, sort: FacetSort, order: SortOrder) -> Self {
+        self.sort = Some((sort, order));
+        self
+    }
+
+    pub fn interval(mut self, interval: FacetInterval) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+}
+
+impl From<&str> for FacetSpec {
+    fn from(field: &str) -> Self {
+        Self::new(field)
+    }
+}
+
+impl Display for FacetInterval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FacetInterval::Number(interval) => write!(f, "{}", interval),
+            FacetInterval::Day => write!(f, "day"),
+            FacetInterval::Month => write!(f, "month"),
+            FacetInterval::Year => write!(f, "year"),
+        }
+    }
+}
+
+// Azure sorts counts descending and values ascending; a leading `-` reverses either.
+impl Display for FacetSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.field)?;
+        if let Some(count) = self.count {
+            write!(f, ",count:{}", count)?;
+        }
+        match self.sort {
+            Some((FacetSort::Count, SortOrder::Desc)) => write!(f, ",sort:count")?,
+            Some((FacetSort::Count, SortOrder::Asc)) => write!(f, ",sort:-count")?,
+            Some((FacetSort::Value, SortOrder::Asc)) => write!(f, ",sort:value")?,
+            Some((FacetSort::Value, SortOrder::Desc)) => write!(f, ",sort:-value")?,
+            None => {}
+        }
+        if let Some(interval) = self.interval {
+            write!(f, ",interval:{}", interval)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(FacetSpec::new("territory"), "territory")]
+    #[test_case(FacetSpec::new("facets").count(50000).sort_by(FacetSort::Value, SortOrder::Asc), "facets,count:50000,sort:value")]
+    #[test_case(FacetSpec::new("matrices").sort_by(FacetSort::Count, SortOrder::Asc), "matrices,sort:-count")]
+    #[test_case(FacetSpec::new("doc_type").sort_by(FacetSort::Value, SortOrder::Desc), "doc_type,sort:-value")]
+    #[test_case(FacetSpec::new("metadata_storage_size").interval(FacetInterval::Number(1000)), "metadata_storage_size,interval:1000")]
+    #[test_case(FacetSpec::new("created").count(3).interval(FacetInterval::Year), "created,count:3,interval:year")]
+    fn facet_spec_renders_as_azure_expression(facet: FacetSpec, expected: &str) {
+        assert_eq!(facet.to_string(), expected);
+    }
+}
@@ -0,0 +1,443 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::{Display, Formatter};
+
+// The variable name used for the element in `any`/`all` lambda expressions.
+pub(crate) const LAMBDA_VARIABLE: &str = "f";
+
+/// A typed OData `$filter` expression.
+///
+/// Filters are built from [`field`](fn.field.html) and render to a correctly
+/// escaped `$filter` string with `to_string()`:
+///
+/// ```
+/// use search_client::filter::field;
+///
+/// let filter = field("product_name")
+///     .eq("ST JOHN'S WORT")
+///     .and(field("territory").is_null());
+///
+/// assert_eq!(
+///     filter.to_string(),
+///     "(product_name eq 'ST JOHN''S WORT' and territory eq null)"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Comparison {
+        field: String,
+        operator: Operator,
+        value: Literal,
+    },
+    Any {
+        field: String,
+        predicate: Option<Box<Filter>>,
+    },
+    All {
+        field: String,
+        predicate: Box<Filter>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+    DateTime(DateTime<Utc>),
+}
+
+/// A field to filter on. Inside `any`/`all` lambdas this is the collection element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field(String);
+
+/// A field named in code. Panics if the name isn't a valid field path, which is a bug
+/// rather than something to handle; use `Field::new` for names that come from elsewhere.
+pub fn field(name: &str) -> Field {
+    Field::new(name).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// A name that can't be a field, so would change the meaning of a `$filter` it was put in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFieldName(pub String);
+
+impl Display for InvalidFieldName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid field name: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidFieldName {}
+
+impl Field {
+    /// Checks that `name` is a field path: letters, digits and underscores, with `/`
+    /// between the names of complex fields.
+    pub fn new(name: &str) -> Result<Self, InvalidFieldName> {
+        let valid = !name.is_empty()
+            && name.split('/').all(|part| {
+                matches!(part.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+                    && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+        if valid {
+            Ok(Field(name.to_string()))
+        } else {
+            Err(InvalidFieldName(name.to_string()))
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    fn compare(&self, operator: Operator, value: impl Into<Literal>) -> Filter {
+        Filter::Comparison {
+            field: self.0.clone(),
+            operator,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(&self, value: impl Into<Literal>) -> Filter {
+        self.compare(Operator::Eq, value)
+    }
+
+    pub fn ne(&self, value: impl Into<Literal>) -> Filter {
+        self.compare(Operator::Ne, value)
+    }
+
+    pub fn gt(&self, value: impl Into<Literal>) -> Filter {
+        self.compare(Operator::Gt, value)
+    }
+
+    pub fn ge(&self, value: impl Into<Literal>) -> Filter {
+        self.compare(Operator::Ge, value)
+    }
+
+    pub fn lt(&self, value: impl Into<Literal>) -> Filter {
+        self.compare(Operator::Lt, value)
+    }
+
+    pub fn le(&self, value: impl Into<Literal>) -> Filter {
+        self.compare(Operator::Le, value)
+    }
+
+    pub fn is_null(&self) -> Filter {
+        self.compare(Operator::Eq, Literal::Null)
+    }
+
+    pub fn is_not_null(&self) -> Filter {
+        self.compare(Operator::Ne, Literal::Null)
+    }
+
+    /// Matches collections with at least one element.
+    pub fn is_not_empty(&self) -> Filter {
+        Filter::Any {
+            field: self.0.clone(),
+            predicate: None,
+        }
+    }
+
+    /// Matches collections with at least one element satisfying `predicate`.
+    pub fn any(&self, predicate: impl FnOnce(&Field) -> Filter) -> Filter {
+        Filter::Any {
+            field: self.0.clone(),
+            predicate: Some(Box::new(predicate(&Field(LAMBDA_VARIABLE.to_string())))),
+        }
+    }
+
+    /// Matches collections where every element satisfies `predicate`.
+    pub fn all(&self, predicate: impl FnOnce(&Field) -> Filter) -> Filter {
+        Filter::All {
+            field: self.0.clone(),
+            predicate: Box::new(predicate(&Field(LAMBDA_VARIABLE.to_string()))),
+        }
+    }
+}
+
+impl Filter {
+    pub fn and(self, other: Filter) -> Filter {
+        match self {
+            Filter::And(mut filters) => {
+                filters.push(other);
+                Filter::And(filters)
+            }
+            filter => Filter::And(vec![filter, other]),
+        }
+    }
+
+    pub fn or(self, other: Filter) -> Filter {
+        match self {
+            Filter::Or(mut filters) => {
+                filters.push(other);
+                Filter::Or(filters)
+            }
+            filter => Filter::Or(vec![filter, other]),
+        }
+    }
+
+    /// Combines filters with `and`, or returns `None` if there are none.
+    pub fn all_of(filters: impl IntoIterator<Item = Filter>) -> Option<Filter> {
+        Self::combine(filters.into_iter().collect(), Filter::And)
+    }
+
+    /// Combines filters with `or`, or returns `None` if there are none.
+    pub fn any_of(filters: impl IntoIterator<Item = Filter>) -> Option<Filter> {
+        Self::combine(filters.into_iter().collect(), Filter::Or)
+    }
+
+    fn combine(mut filters: Vec<Filter>, join: fn(Vec<Filter>) -> Filter) -> Option<Filter> {
+        match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ => Some(join(filters)),
+        }
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Filter::And(filters) => write_group(f, filters, "and", "true"),
+            Filter::Or(filters) => write_group(f, filters, "or", "false"),
+            // A group of one is written without parentheses, so it needs them like any other filter.
+            Filter::Not(filter) => match filter.as_ref() {
+                Filter::And(filters) | Filter::Or(filters) if filters.len() > 1 => {
+                    write!(f, "not {}", filter)
+                }
+                _ => write!(f, "not ({})", filter),
+            },
+            Filter::Comparison {
+                field,
+                operator,
+                value,
+            } => write!(f, "{} {} {}", field, operator, value),
+            Filter::Any {
+                field,
+                predicate: None,
+            } => write!(f, "{}/any()", field),
+            Filter::Any {
+                field,
+                predicate: Some(predicate),
+            } => write!(f, "{}/any({}: {})", field, LAMBDA_VARIABLE, predicate),
+            Filter::All { field, predicate } => {
+                write!(f, "{}/all({}: {})", field, LAMBDA_VARIABLE, predicate)
+            }
+        }
+    }
+}
+
+fn write_group(
+    f: &mut Formatter<'_>,
+    filters: &[Filter],
+    separator: &str,
+    empty: &str,
+) -> std::fmt::Result {
+    match filters {
+        [] => write!(f, "{}", empty),
+        [filter] => write!(f, "{}", filter),
+        _ => write!(
+            f,
+            "({})",
+            filters
+                .iter()
+                .map(Filter::to_string)
+                .collect::<Vec<_>>()
+                .join(&format!(" {} ", separator))
+        ),
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let operator = match self {
+            Operator::Eq => "eq",
+            Operator::Ne => "ne",
+            Operator::Gt => "gt",
+            Operator::Ge => "ge",
+            Operator::Lt => "lt",
+            Operator::Le => "le",
+        };
+        write!(f, "{}", operator)
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Null => write!(f, "null"),
+            Literal::Bool(value) => write!(f, "{}", value),
+            Literal::Int(value) => write!(f, "{}", value),
+            // OData spells the values that aren't numbers `NaN`, `INF` and `-INF`.
+            Literal::Double(value) if value.is_nan() => write!(f, "NaN"),
+            Literal::Double(value) if value.is_infinite() => {
+                write!(f, "{}INF", if *value < 0.0 { "-" } else { "" })
+            }
+            Literal::Double(value) => write!(f, "{:?}", value),
+            Literal::String(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            Literal::DateTime(value) => {
+                write!(f, "{}", value.to_rfc3339_opts(SecondsFormat::Secs, true))
+            }
+        }
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(value: &str) -> Self {
+        Literal::String(value.to_string())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(value: String) -> Self {
+        Literal::String(value)
+    }
+}
+
+impl From<&String> for Literal {
+    fn from(value: &String) -> Self {
+        Literal::String(value.clone())
+    }
+}
+
+impl From<bool> for Literal {
+    fn from(value: bool) -> Self {
+        Literal::Bool(value)
+    }
+}
+
+impl From<i32> for Literal {
+    fn from(value: i32) -> Self {
+        Literal::Int(value.into())
+    }
+}
+
+impl From<i64> for Literal {
+    fn from(value: i64) -> Self {
+        Literal::Int(value)
+    }
+}
+
+impl From<f64> for Literal {
+    fn from(value: f64) -> Self {
+        Literal::Double(value)
+    }
+}
+
+impl From<DateTime<Utc>> for Literal {
+    fn from(value: DateTime<Utc>) -> Self {
+        Literal::DateTime(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn date(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test_case(field("doc_type").eq("Spc"), "doc_type eq 'Spc'")]
+    #[test_case(field("doc_type").ne("Spc"), "doc_type ne 'Spc'")]
+    #[test_case(field("product_name").eq("ST JOHN'S WORT"), "product_name eq 'ST JOHN''S WORT'"; "escapes quotes")]
+    #[test_case(field("product_name").eq("x' or true or 'x"), "product_name eq 'x'' or true or ''x'"; "prevents injection")]
+    #[test_case(field("territory").is_null(), "territory eq null")]
+    #[test_case(field("territory").is_not_null(), "territory ne null")]
+    #[test_case(field("metadata_storage_size").ge(1024), "metadata_storage_size ge 1024")]
+    #[test_case(field("score").lt(0.5), "score lt 0.5")]
+    #[test_case(field("score").lt(f64::NAN), "score lt NaN"; "not a number")]
+    #[test_case(field("score").lt(f64::INFINITY), "score lt INF"; "infinity")]
+    #[test_case(field("score").gt(f64::NEG_INFINITY), "score gt -INF"; "negative infinity")]
+    #[test_case(field("release_state").eq(true), "release_state eq true")]
+    #[test_case(field("created").gt(date("2020-01-02T03:04:05Z")), "created gt 2020-01-02T03:04:05Z"; "dates")]
+    #[test_case(field("substance_name").any(|f| f.eq("IBUPROFEN")), "substance_name/any(f: f eq 'IBUPROFEN')")]
+    #[test_case(field("facets").any(|f| f.eq("I").or(f.eq("J"))), "facets/any(f: (f eq 'I' or f eq 'J'))"; "any with or")]
+    #[test_case(field("facets").all(|f| f.ne("I")), "facets/all(f: f ne 'I')")]
+    #[test_case(field("facets").is_not_empty(), "facets/any()")]
+    #[test_case(!field("doc_type").eq("Spc"), "not (doc_type eq 'Spc')")]
+    #[test_case(!field("doc_type").eq("Spc").or(field("doc_type").eq("Pil")), "not (doc_type eq 'Spc' or doc_type eq 'Pil')"; "not a group")]
+    #[test_case(!Filter::And(vec![field("x").eq(1)]), "not (x eq 1)"; "not a group of one")]
+    #[test_case(!Filter::Or(vec![]), "not (false)"; "not an empty group")]
+    #[test_case(
+        field("doc_type").eq("Spc").and(field("territory").eq("UK").or(field("territory").is_null())),
+        "(doc_type eq 'Spc' and (territory eq 'UK' or territory eq null))";
+        "nested groups"
+    )]
+    #[test_case(
+        field("a").eq("1").and(field("b").eq("2")).and(field("c").eq("3")),
+        "(a eq '1' and b eq '2' and c eq '3')";
+        "flattens chained groups"
+    )]
+    fn renders_filters(filter: Filter, expected: &str) {
+        assert_eq!(filter.to_string(), expected);
+    }
+
+    #[test]
+    fn combining_no_filters_gives_none() {
+        assert_eq!(Filter::all_of(vec![]), None);
+        assert_eq!(Filter::any_of(vec![]), None);
+    }
+
+    #[test]
+    fn combining_a_single_filter_returns_it() {
+        let filter = field("doc_type").eq("Spc");
+        assert_eq!(Filter::any_of(vec![filter.clone()]), Some(filter));
+    }
+
+    #[test]
+    fn combining_filters_groups_them() {
+        let filter = Filter::any_of(vec![
+            field("doc_type").eq("Spc"),
+            field("doc_type").eq("Pil"),
+        ])
+        .unwrap();
+        assert_eq!(
+            filter.to_string(),
+            "(doc_type eq 'Spc' or doc_type eq 'Pil')"
+        );
+    }
+
+    #[test_case("doc_type")]
+    #[test_case("address/city"; "a sub-field")]
+    #[test_case("_private2")]
+    fn accepts_field_paths(name: &str) {
+        assert_eq!(Field::new(name).unwrap().name(), name);
+    }
+
+    #[test_case(""; "nothing")]
+    #[test_case("doc_type eq 'Spc' or true"; "an expression")]
+    #[test_case("2nd"; "a leading digit")]
+    #[test_case("address/"; "an empty sub-field")]
+    fn rejects_anything_else(name: &str) {
+        assert_eq!(Field::new(name), Err(InvalidFieldName(name.to_string())));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid field name: doc type")]
+    fn field_panics_on_an_invalid_name_in_release_builds_too() {
+        field("doc type");
+    }
+}
@@ -0,0 +1,163 @@
+use crate::get_env_or_default;
+
+// Lucene won't fuzz a term by more than two edits.
+const MAX_EDIT_DISTANCE: usize = 2;
+
+/// How fuzzily each word of a search is matched.
+///
+/// Short words are matched exactly, as one edit turns most of them into a different word,
+/// and longer words get more edits, up to `max_edits`. Words with digits in them, such as
+/// doses, strengths and licence numbers, are always matched exactly.
+///
+/// ```
+/// use search_client::FuzzinessRules;
+///
+/// let rules = FuzzinessRules::default().max_edits(2).prefix_last_term(true);
+///
+/// assert_eq!(rules.edit_distance("flu"), 0);
+/// assert_eq!(rules.edit_distance("aspirin"), 1);
+/// assert_eq!(rules.edit_distance("paracetamol"), 2);
+/// assert_eq!(rules.edit_distance("5mg"), 0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzinessRules {
+    max_edits: usize,
+    one_edit_min_length: usize,
+    two_edits_min_length: usize,
+    prefix_last_term: bool,
+    prefix_min_length: usize,
+}
+
+impl Default for FuzzinessRules {
+    fn default() -> Self {
+        Self {
+            max_edits: 1,
+            one_edit_min_length: 4,
+            two_edits_min_length: 8,
+            prefix_last_term: false,
+            prefix_min_length: 3,
+        }
+    }
+}
+
+impl FuzzinessRules {
+    /// The rules set by `AZURE_SEARCH_WORD_FUZZINESS` (the most edits, default 1),
+    /// `AZURE_SEARCH_FUZZY_MIN_LENGTH` (the shortest word to fuzz, default 4),
+    /// `AZURE_SEARCH_FUZZY_TWO_EDITS_MIN_LENGTH` (the shortest word to allow two edits,
+    /// default 8) and `AZURE_SEARCH_PREFIX_LAST_TERM` (default false).
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let env = |key: &str, default: usize| {
+            get_env_or_default(key, &default.to_string())
+                .parse()
+                .unwrap_or(default)
+        };
+
+        Self::default()
+            .max_edits(env("AZURE_SEARCH_WORD_FUZZINESS", defaults.max_edits))
+            .min_lengths(
+                env(
+                    "AZURE_SEARCH_FUZZY_MIN_LENGTH",
+                    defaults.one_edit_min_length,
+                ),
+                env(
+                    "AZURE_SEARCH_FUZZY_TWO_EDITS_MIN_LENGTH",
+                    defaults.two_edits_min_length,
+                ),
+            )
+            .prefix_last_term(
+                get_env_or_default("AZURE_SEARCH_PREFIX_LAST_TERM", "false") == "true",
+            )
+    }
+
+    /// The most edits any word is allowed, from 0 (no fuzzy matching) to 2.
+    pub fn max_edits(mut self, max_edits: usize) -> Self {
+        self.max_edits = max_edits.min(MAX_EDIT_DISTANCE);
+        self
+    }
+
+    /// The shortest words that are allowed one edit, and two.
+    pub fn min_lengths(mut self, one_edit: usize, two_edits: usize) -> Self {
+        self.one_edit_min_length = one_edit;
+        self.two_edits_min_length = two_edits.max(one_edit);
+        self
+    }
+
+    /// Whether the last word also matches any word it is the start of, as it may not have
+    /// been finished yet.
+    pub fn prefix_last_term(mut self, prefix_last_term: bool) -> Self {
+        self.prefix_last_term = prefix_last_term;
+        self
+    }
+
+    /// How many edits a word may be from the words it matches.
+    pub fn edit_distance(&self, word: &str) -> usize {
+        let length = word.chars().count();
+        let edits = if word.chars().any(|c| c.is_ascii_digit()) {
+            0
+        } else if length >= self.two_edits_min_length {
+            2
+        } else if length >= self.one_edit_min_length {
+            1
+        } else {
+            0
+        };
+        edits.min(self.max_edits)
+    }
+
+    /// Whether the word, being the last of the search, should also be matched as a prefix.
+    pub fn is_prefix(&self, word: &str) -> bool {
+        self.prefix_last_term
+            && word.chars().count() >= self.prefix_min_length
+            && !word.chars().any(|c| c.is_ascii_digit())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("flu", 0)]
+    #[test_case("hrt", 0)]
+    #[test_case("iron", 1)]
+    #[test_case("aspirin", 1)]
+    #[test_case("ibuprofen", 2)]
+    #[test_case("hydrocortisone", 2)]
+    #[test_case("b12", 0)]
+    #[test_case("covid-19", 0)]
+    #[test_case("PL123451234", 0)]
+    #[test_case("émollient", 2; "counts characters rather than bytes")]
+    fn edit_distance_depends_on_the_length_of_the_word(word: &str, expected: usize) {
+        let rules = FuzzinessRules::default().max_edits(2);
+
+        assert_eq!(rules.edit_distance(word), expected);
+    }
+
+    #[test_case(0, 0)]
+    #[test_case(1, 1)]
+    #[test_case(3, 2; "lucene allows two at most")]
+    fn edit_distance_is_capped(max_edits: usize, expected: usize) {
+        let rules = FuzzinessRules::default().max_edits(max_edits);
+
+        assert_eq!(rules.edit_distance("hydrocortisone"), expected);
+    }
+
+    #[test]
+    fn minimum_lengths_can_be_changed() {
+        let rules = FuzzinessRules::default().max_edits(2).min_lengths(3, 5);
+
+        assert_eq!(rules.edit_distance("flu"), 1);
+        assert_eq!(rules.edit_distance("cream"), 2);
+    }
+
+    #[test_case(false, "ibupro", false)]
+    #[test_case(true, "ibupro", true)]
+    #[test_case(true, "ib", false; "too short")]
+    #[test_case(true, "5m", false; "a dose")]
+    fn last_term_is_a_prefix_if_enabled(enabled: bool, word: &str, expected: bool) {
+        let rules = FuzzinessRules::default().prefix_last_term(enabled);
+
+        assert_eq!(rules.is_prefix(word), expected);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::filter::{Filter, Literal, Operator, LAMBDA_VARIABLE};
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+// Evaluates a typed `$filter` against a document the way Azure Search would:
+// comparisons, `and`/`or`/`not`, and `any`/`all` lambdas over collections.
+pub fn matches(filter: &Filter, document: &Map<String, Value>) -> bool {
+    evaluate(filter, document, None)
+}
+
+fn evaluate(filter: &Filter, document: &Map<String, Value>, element: Option<&Value>) -> bool {
+    match filter {
+        Filter::And(filters) => filters
+            .iter()
+            .all(|filter| evaluate(filter, document, element)),
+        Filter::Or(filters) => filters
+            .iter()
+            .any(|filter| evaluate(filter, document, element)),
+        Filter::Not(inner) => !evaluate(inner, document, element),
+        Filter::Comparison {
+            field,
+            operator,
+            value,
+        } => {
+            let actual = match element {
+                Some(element) if field == LAMBDA_VARIABLE => Some(element),
+                _ => document.get(field),
+            };
+            compare(actual, *operator, value)
+        }
+        Filter::Any { field, predicate } => {
+            let elements = collection(document, field);
+            match predicate {
+                Some(predicate) => elements
+                    .iter()
+                    .any(|element| evaluate(predicate, document, Some(element))),
+                None => !elements.is_empty(),
+            }
+        }
+        Filter::All { field, predicate } => collection(document, field)
+            .iter()
+            .all(|element| evaluate(predicate, document, Some(element))),
+    }
+}
+
+fn collection<'a>(document: &'a Map<String, Value>, field: &str) -> &'a [Value] {
+    match document.get(field) {
+        Some(Value::Array(elements)) => elements,
+        _ => &[],
+    }
+}
+
+fn compare(actual: Option<&Value>, operator: Operator, expected: &Literal) -> bool {
+    let ordering = match (actual, expected) {
+        (None, Literal::Null) | (Some(Value::Null), Literal::Null) => Some(Ordering::Equal),
+        (Some(Value::String(actual)), Literal::String(expected)) => {
+            Some(actual.as_str().cmp(expected.as_str()))
+        }
+        (Some(Value::String(actual)), Literal::DateTime(expected)) => {
+            DateTime::parse_from_rfc3339(actual)
+                .ok()
+                .map(|actual| actual.with_timezone(&Utc).cmp(expected))
+        }
+        (Some(Value::Number(actual)), Literal::Int(expected)) => actual
+            .as_f64()
+            .and_then(|actual| actual.partial_cmp(&(*expected as f64))),
+        (Some(Value::Number(actual)), Literal::Double(expected)) => actual
+            .as_f64()
+            .and_then(|actual| actual.partial_cmp(expected)),
+        (Some(Value::Bool(actual)), Literal::Bool(expected)) => Some(actual.cmp(expected)),
+        _ => None,
+    };
+
+    match (operator, ordering) {
+        (Operator::Eq, ordering) => ordering == Some(Ordering::Equal),
+        (Operator::Ne, ordering) => ordering != Some(Ordering::Equal),
+        (Operator::Gt, Some(ordering)) => ordering == Ordering::Greater,
+        (Operator::Ge, Some(ordering)) => ordering != Ordering::Less,
+        (Operator::Lt, Some(ordering)) => ordering == Ordering::Less,
+        (Operator::Le, Some(ordering)) => ordering != Ordering::Greater,
+        (_, None) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::filter::field;
+    use serde_json::json;
+    use test_case::test_case;
+
+    fn date(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn given_a_document() -> Map<String, Value> {
+        match json!({
+            "product_name": "ST JOHN'S WORT TABLETS",
+            "doc_type": "Spc",
+            "territory": null,
+            "created": "2020-01-10T05:06:00+00:00",
+            "metadata_storage_size": 1024,
+            "substance_name": ["HYPERICUM PERFORATUM"],
+            "facets": ["H", "H, HYPERICUM PERFORATUM"]
+        }) {
+            Value::Object(document) => document,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test_case(field("doc_type").eq("Spc"), true)]
+    #[test_case(field("doc_type").eq("Pil"), false)]
+    #[test_case(field("doc_type").ne("Pil"), true)]
+    #[test_case(field("product_name").eq("ST JOHN'S WORT TABLETS"), true; "quoted value")]
+    #[test_case(field("territory").is_null(), true)]
+    #[test_case(field("missing_field").is_null(), true)]
+    #[test_case(field("territory").eq("UK").or(field("territory").is_null()), true; "or")]
+    #[test_case(field("doc_type").eq("Spc").and(field("territory").eq("UK")), false; "and")]
+    #[test_case(!field("doc_type").eq("Pil"), true; "not")]
+    #[test_case(field("substance_name").any(|f| f.eq("HYPERICUM PERFORATUM")), true)]
+    #[test_case(field("substance_name").any(|f| f.eq("IBUPROFEN")), false)]
+    #[test_case(field("facets").any(|f| f.eq("H").or(f.eq("I"))), true; "any with or")]
+    #[test_case(field("facets").all(|f| f.ne("I")), true)]
+    #[test_case(field("facets").is_not_empty(), true)]
+    #[test_case(field("created").gt(date("2020-01-01T00:00:00Z")), true; "after date")]
+    #[test_case(field("created").lt(date("2020-01-01T00:00:00Z")), false; "before date")]
+    #[test_case(field("metadata_storage_size").ge(1024), true)]
+    #[test_case(field("metadata_storage_size").lt(1024), false)]
+    #[test_case(Filter::all_of(vec![
+        field("product_name").eq("ST JOHN'S WORT TABLETS"),
+        field("doc_type").eq("Spc").or(field("doc_type").eq("Pil")),
+        field("territory").eq("GB").or(field("territory").eq("UK")).or(field("territory").is_null()),
+    ]).unwrap(), true; "combined")]
+    fn evaluates_filters_against_a_document(filter: Filter, expected: bool) {
+        assert_eq!(matches(&filter, &given_a_document()), expected);
+    }
+}
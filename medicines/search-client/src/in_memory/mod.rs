@@ -0,0 +1,1624 @@
+use crate::{
+    error::handle_response,
+    facet_options,
+    filter::Field,
+    get_env_or_default,
+    models::{
+        AutocompleteResults, AzureIndexChangedResult, AzureIndexChangedResults, Documents,
+        FacetQueryResults, FacetResults, IndexAction, IndexBatchAction, IndexEntry, SuggestResults,
+    },
+    query_normalizer::normalize_strengths,
+    query_parser::SearchQuery,
+    CreateIndexEntry, DeleteIndexEntry, FacetInterval, FacetSort, FacetSpec, FuzzinessRules,
+    IndexBatch, Search, SearchClientError, SearchMode, SearchOptions, SortOrder,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, RwLock},
+};
+
+mod filter;
+mod schema;
+
+use schema::{IndexSchema, ScoringProfile};
+
+type Document = Map<String, Value>;
+
+const DEFAULT_TOP: usize = 50;
+const DEFAULT_FACET_COUNT: usize = 10;
+const MAX_HIGHLIGHTS: usize = 5;
+
+// Searchable fields across both the products and BMGF index definitions.
+const SEARCHABLE_FIELDS: &[&str] = &[
+    "content",
+    "product_name",
+    "keywords",
+    "title",
+    "pl_number",
+    "file_name",
+    "metadata_storage_name",
+    "substance_name",
+    "products",
+    "summary",
+    "pl_numbers",
+    "active_substances",
+    "pbpk_models",
+    "matrices",
+    "pregnancy_trimesters",
+    "report_name",
+];
+
+const NON_RETRIEVABLE_FIELDS: &[&str] = &["content"];
+
+const MAX_SUGGEST_TERM_LENGTH: usize = 100;
+
+lazy_static! {
+    static ref INDEXES: Mutex<HashMap<String, Arc<RwLock<Vec<Document>>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// An in-process stand-in for Azure Search, for running the API and the
+/// doc-index-updater workers without a network connection.
+///
+/// Clients created with `new_with_index` share their documents with every other
+/// client for the same index in the process. Setting `SEARCH_IN_MEMORY_DATA_DIR`
+/// seeds each index from `<dir>/<index name>.json` the first time it is used.
+#[derive(Clone)]
+pub struct InMemorySearchClient {
+    index_name: String,
+    schema: &'static IndexSchema,
+    documents: Arc<RwLock<Vec<Document>>>,
+    fuzziness: FuzzinessRules,
+    search_exactness_boost: f32,
+}
+
+impl Default for InMemorySearchClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemorySearchClient {
+    pub fn new() -> Self {
+        let default_index = get_env_or_default("AZURE_SEARCH_INDEX", "products-index");
+        InMemorySearchClient::new_with_index(default_index)
+    }
+
+    pub fn new_with_index(index: String) -> Self {
+        let documents = INDEXES
+            .lock()
+            .expect("In-memory index registry was poisoned")
+            .entry(index.clone())
+            .or_insert_with(|| seed_documents(&index))
+            .clone();
+
+        Self::with_documents(index, documents)
+    }
+
+    /// Creates a client over its own, unshared set of documents.
+    pub fn from_documents(index: &str, documents: Vec<Value>) -> Self {
+        let client = Self::with_documents(index.to_string(), Arc::new(RwLock::new(vec![])));
+        for document in documents {
+            client.upsert(document);
+        }
+        client
+    }
+
+    fn with_documents(index_name: String, documents: Arc<RwLock<Vec<Document>>>) -> Self {
+        let fuzziness = FuzzinessRules::from_env();
+        let search_exactness_boost = get_env_or_default("AZURE_SEARCH_EXACTNESS_BOOST", "4")
+            .parse()
+            .unwrap_or(4.0);
+
+        Self {
+            schema: IndexSchema::for_index(&index_name),
+            index_name,
+            documents,
+            fuzziness,
+            search_exactness_boost,
+        }
+    }
+
+    /// Adds documents from JSON, either a plain array or an Azure search response
+    /// (`{"value": [...]}`), returning how many were loaded.
+    pub fn load_documents(&self, json: &str) -> Result<usize, serde_json::Error> {
+        let documents = match serde_json::from_str::<Value>(json)? {
+            Value::Object(mut response) => match response.remove("value") {
+                Some(Value::Array(documents)) => documents,
+                _ => vec![Value::Object(response)],
+            },
+            Value::Array(documents) => documents,
+            other => vec![other],
+        };
+        let count = documents.len();
+        for document in documents {
+            self.upsert(document);
+        }
+        Ok(count)
+    }
+
+    fn upsert(&self, document: Value) -> Option<String> {
+        let document = match document {
+            Value::Object(document) => document,
+            _ => return None,
+        };
+        let key = document
+            .get(self.schema.key)
+            .and_then(Value::as_str)
+            .map(String::from);
+
+        let mut documents = self
+            .documents
+            .write()
+            .expect("In-memory index was poisoned");
+        let existing = key.as_ref().and_then(|key| {
+            documents
+                .iter()
+                .position(|d| d.get(self.schema.key).and_then(Value::as_str) == Some(key))
+        });
+        match existing {
+            Some(position) => documents[position] = document,
+            None => documents.push(document),
+        }
+        key
+    }
+
+    fn remove(&self, key_name: &str, value: &str) {
+        self.documents
+            .write()
+            .expect("In-memory index was poisoned")
+            .retain(|document| document.get(key_name).and_then(Value::as_str) != Some(value));
+    }
+
+    fn apply(&self, batch_action: IndexBatchAction) -> AzureIndexChangedResult {
+        let IndexBatchAction { action, document } = batch_action;
+        let key = match document.get(self.schema.key).and_then(Value::as_str) {
+            Some(key) => key.to_string(),
+            None => {
+                return AzureIndexChangedResult {
+                    key: String::default(),
+                    status: false,
+                    error_message: Some(format!("Missing document key {}", self.schema.key)),
+                    status_code: 400,
+                }
+            }
+        };
+
+        let mut documents = self
+            .documents
+            .write()
+            .expect("In-memory index was poisoned");
+        let existing = documents
+            .iter()
+            .position(|d| d.get(self.schema.key).and_then(Value::as_str) == Some(&key));
+
+        let status_code = match (action, existing) {
+            (IndexAction::Delete, Some(position)) => {
+                documents.remove(position);
+                200
+            }
+            (IndexAction::Delete, None) => 200,
+            (IndexAction::Upload, Some(position)) => {
+                documents[position] = document;
+                200
+            }
+            (IndexAction::Merge, Some(position)) | (IndexAction::MergeOrUpload, Some(position)) => {
+                documents[position].extend(document);
+                200
+            }
+            (IndexAction::Upload, None) | (IndexAction::MergeOrUpload, None) => {
+                documents.push(document);
+                201
+            }
+            (IndexAction::Merge, None) => {
+                return AzureIndexChangedResult {
+                    key,
+                    status: false,
+                    error_message: Some("Document not found.".to_string()),
+                    status_code: 404,
+                }
+            }
+        };
+
+        AzureIndexChangedResult {
+            key,
+            status: true,
+            error_message: None,
+            status_code,
+        }
+    }
+
+    fn query(&self, options: &SearchOptions) -> Result<Value, String> {
+        let query = SearchQuery::parse(&options.search_term);
+        let terms = tokenize(&query.included_text())
+            .into_iter()
+            .map(|term| with_synonyms(term, options))
+            .collect::<Vec<_>>();
+        let excluded = tokenize(&query.excluded_text());
+        let profile = match options.scoring_profile.as_deref() {
+            Some(name) => Some(self.schema.scoring_profile(name)?),
+            None => None,
+        };
+        for facet in &options.facets {
+            self.schema.check_facetable(&facet.field)?;
+        }
+
+        let documents = self.documents.read().expect("In-memory index was poisoned");
+        let mut hits: Vec<(f32, &Document)> = documents
+            .iter()
+            .filter(|document| match &options.filter {
+                Some(filter) => filter::matches(filter, document),
+                None => true,
+            })
+            .filter(|document| !contains_any(document, &excluded))
+            .filter_map(|document| {
+                self.score(&terms, document, options.search_mode, profile)
+                    .map(|score| {
+                        let boost = profile.map_or(1.0, |profile| profile.boost(document));
+                        (score * boost, document)
+                    })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        if !options.order_by.is_empty() {
+            hits.sort_by(|a, b| compare_documents(a.1, b.1, &options.order_by));
+        }
+
+        let mut response = Map::new();
+        response.insert(
+            "@odata.context".to_string(),
+            json!(format!(
+                "in-memory://indexes('{}')/$metadata#docs(*)",
+                self.index_name
+            )),
+        );
+        if options.include_count {
+            response.insert("@odata.count".to_string(), json!(hits.len()));
+        }
+        if !options.facets.is_empty() {
+            let facets = options
+                .facets
+                .iter()
+                .map(|facet| (facet.field.clone(), Value::Array(count_facet(facet, &hits))))
+                .collect();
+            response.insert("@search.facets".to_string(), Value::Object(facets));
+        }
+
+        let top = options.top.map_or(DEFAULT_TOP, |top| top.max(0) as usize);
+        let skip = options.skip.map_or(0, |skip| skip.max(0) as usize);
+        let value = hits
+            .into_iter()
+            .skip(skip)
+            .take(top)
+            .map(|(score, document)| self.to_result(score, document, &terms.concat(), options))
+            .collect();
+        response.insert("value".to_string(), Value::Array(value));
+
+        Ok(Value::Object(response))
+    }
+
+    // With `searchMode=all` every term, or one of its synonyms, has to match at least one
+    // searchable field.
+    fn score(
+        &self,
+        terms: &[Vec<String>],
+        document: &Document,
+        search_mode: SearchMode,
+        profile: Option<&ScoringProfile>,
+    ) -> Option<f32> {
+        if terms.is_empty() {
+            return Some(1.0);
+        }
+
+        let scores = terms.iter().map(|names| {
+            SEARCHABLE_FIELDS
+                .iter()
+                .filter_map(|field| {
+                    let best = field_text(document, field)
+                        .iter()
+                        .flat_map(|text| tokenize(text))
+                        .flat_map(|token| {
+                            names
+                                .iter()
+                                .filter_map(|name| self.term_score(name, &token))
+                                .collect::<Vec<_>>()
+                        })
+                        .fold(None, |best: Option<f32>, score| {
+                            Some(best.map_or(score, |best| best.max(score)))
+                        })?;
+                    Some(best * profile.map_or(1.0, |profile| profile.weight(field)))
+                })
+                .fold(None, |best: Option<f32>, score| {
+                    Some(best.map_or(score, |best| best.max(score)))
+                })
+        });
+
+        match search_mode {
+            SearchMode::All => scores.sum(),
+            SearchMode::Any => {
+                let matched = scores.flatten().collect::<Vec<_>>();
+                if matched.is_empty() {
+                    None
+                } else {
+                    Some(matched.iter().sum())
+                }
+            }
+        }
+    }
+
+    fn term_score(&self, term: &str, token: &str) -> Option<f32> {
+        let edits = self.fuzziness.edit_distance(term);
+        if term == token {
+            Some(self.search_exactness_boost)
+        } else if edits > 0 && edit_distance(term, token) <= edits {
+            Some(1.0)
+        } else {
+            None
+        }
+    }
+
+    fn to_result(
+        &self,
+        score: f32,
+        document: &Document,
+        terms: &[String],
+        options: &SearchOptions,
+    ) -> Value {
+        let mut result = retrievable(document);
+        if !options.select.is_empty() {
+            result.retain(|field, _| options.select.contains(field));
+        }
+        result.insert("@search.score".to_string(), json!(score));
+
+        let (pre_tag, post_tag) = match &options.highlight_tags {
+            Some((pre_tag, post_tag)) => (pre_tag.as_str(), post_tag.as_str()),
+            None => ("<em>", "</em>"),
+        };
+        let mut highlights = Map::new();
+        for field in &options.highlight_fields {
+            let fragments = field_text(document, field)
+                .iter()
+                .flat_map(|text| text.split(&['.', '\n'][..]))
+                .filter_map(|sentence| self.highlight(sentence.trim(), terms, pre_tag, post_tag))
+                .take(MAX_HIGHLIGHTS)
+                .collect::<Vec<_>>();
+            if !fragments.is_empty() {
+                highlights.insert(field.clone(), json!(fragments));
+            }
+        }
+        if !highlights.is_empty() {
+            result.insert("@search.highlights".to_string(), Value::Object(highlights));
+        }
+
+        Value::Object(result)
+    }
+
+    fn highlight(
+        &self,
+        sentence: &str,
+        terms: &[String],
+        pre_tag: &str,
+        post_tag: &str,
+    ) -> Option<String> {
+        let mut highlighted = String::with_capacity(sentence.len());
+        let mut found = false;
+        let mut rest = sentence;
+
+        while let Some(start) = rest.find(char::is_alphanumeric) {
+            let (before, word_and_rest) = rest.split_at(start);
+            let end = word_and_rest
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(word_and_rest.len());
+            let (word, after) = word_and_rest.split_at(end);
+
+            highlighted.push_str(before);
+            let token = word.to_lowercase();
+            if terms
+                .iter()
+                .any(|term| self.term_score(term, &token).is_some())
+            {
+                found = true;
+                highlighted.push_str(pre_tag);
+                highlighted.push_str(word);
+                highlighted.push_str(post_tag);
+            } else {
+                highlighted.push_str(word);
+            }
+            rest = after;
+        }
+        highlighted.push_str(rest);
+
+        if found {
+            Some(highlighted)
+        } else {
+            None
+        }
+    }
+
+    // Like the `analyzingInfixMatching` suggester: every term must prefix a word in one source field.
+    fn query_suggestions(&self, search_term: &str, top: usize) -> Result<Value, String> {
+        let terms = tokenize(validate_suggest_term(search_term)?);
+        let documents = self.documents.read().expect("In-memory index was poisoned");
+
+        let value = documents
+            .iter()
+            .filter_map(|document| {
+                self.schema
+                    .suggester
+                    .iter()
+                    .flat_map(|field| field_text(document, field))
+                    .find(|text| {
+                        let tokens = tokenize(text);
+                        terms
+                            .iter()
+                            .all(|term| tokens.iter().any(|token| token.starts_with(term)))
+                    })
+            })
+            .take(top)
+            .map(|text| json!({ "@search.text": text }))
+            .collect();
+
+        Ok(json!({ "value": Value::Array(value) }))
+    }
+
+    // Like `autocompleteMode=oneTerm`: completes the last term from words in the source fields.
+    fn query_completions(&self, search_term: &str, top: usize) -> Result<Value, String> {
+        let mut terms = tokenize(validate_suggest_term(search_term)?);
+        let partial = terms.pop().unwrap_or_default();
+        let documents = self.documents.read().expect("In-memory index was poisoned");
+
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for document in documents.iter() {
+            let mut completions = self
+                .schema
+                .suggester
+                .iter()
+                .flat_map(|field| field_text(document, field))
+                .flat_map(tokenize)
+                .filter(|token| token.starts_with(&partial))
+                .collect::<Vec<_>>();
+            completions.sort();
+            completions.dedup();
+            for completion in completions {
+                *counts.entry(completion).or_default() += 1;
+            }
+        }
+
+        let mut completions = counts.into_iter().collect::<Vec<_>>();
+        completions.sort_by_key(|(_, count)| Reverse(*count));
+        let value = completions
+            .into_iter()
+            .take(top)
+            .map(|(text, _)| {
+                let query_plus_text = terms
+                    .iter()
+                    .chain(std::iter::once(&text))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                json!({ "text": text, "queryPlusText": query_plus_text })
+            })
+            .collect();
+
+        Ok(json!({ "value": Value::Array(value) }))
+    }
+
+    async fn respond<T>(&self, options: &SearchOptions) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        into_response(self.query(options), &self.index_name).await
+    }
+}
+
+async fn into_response<T>(body: Result<Value, String>, index: &str) -> Result<T, SearchClientError>
+where
+    T: DeserializeOwned,
+{
+    let response = match body {
+        Ok(body) => http::Response::builder()
+            .status(reqwest::StatusCode::OK)
+            .body(body.to_string()),
+        Err(message) => {
+            tracing::debug!("Invalid in-memory search request: {}", &message);
+            http::Response::builder()
+                .status(reqwest::StatusCode::BAD_REQUEST)
+                .body(json!({ "error": { "code": "", "message": message } }).to_string())
+        }
+    }
+    .expect("Failed to build in-memory search response");
+
+    handle_response(reqwest::Response::from(response), index).await
+}
+
+fn validate_suggest_term(search_term: &str) -> Result<&str, String> {
+    let search_term = search_term.trim();
+    if search_term.is_empty() || search_term.chars().count() > MAX_SUGGEST_TERM_LENGTH {
+        return Err(format!(
+            "The search term must be between 1 and {} characters",
+            MAX_SUGGEST_TERM_LENGTH
+        ));
+    }
+    Ok(search_term)
+}
+
+// Orders documents by the `$orderby` fields, with missing values last as Azure does.
+fn compare_documents(a: &Document, b: &Document, order_by: &[(String, SortOrder)]) -> Ordering {
+    order_by
+        .iter()
+        .map(|(field, order)| {
+            let ordering = match (a.get(field), b.get(field)) {
+                (Some(Value::Number(a)), Some(Value::Number(b))) => a
+                    .as_f64()
+                    .partial_cmp(&b.as_f64())
+                    .unwrap_or(Ordering::Equal),
+                (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+                (Some(Value::Bool(a)), Some(Value::Bool(b))) => a.cmp(b),
+                (Some(Value::Null), Some(Value::Null)) | (None, None) => Ordering::Equal,
+                (None, _) | (Some(Value::Null), _) => return Ordering::Greater,
+                (_, None) | (_, Some(Value::Null)) => return Ordering::Less,
+                _ => Ordering::Equal,
+            };
+            match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+// Numbers are bucketed as whole numbers, which is enough for the sizes we facet on.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum FacetKey {
+    Number(i64),
+    Text(String),
+}
+
+fn count_facet(facet: &FacetSpec, hits: &[(f32, &Document)]) -> Vec<Value> {
+    let mut counts = BTreeMap::<FacetKey, i32>::new();
+    for (_, document) in hits {
+        for key in facet_keys(document, facet) {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+
+    let mut counts: Vec<(FacetKey, i32)> = counts.into_iter().collect();
+    match facet.sort.unwrap_or((FacetSort::Count, SortOrder::Desc)) {
+        (FacetSort::Count, SortOrder::Desc) => counts.sort_by_key(|(_, count)| Reverse(*count)),
+        (FacetSort::Count, SortOrder::Asc) => counts.sort_by_key(|(_, count)| *count),
+        (FacetSort::Value, SortOrder::Asc) => {}
+        (FacetSort::Value, SortOrder::Desc) => counts.reverse(),
+    }
+    counts
+        .into_iter()
+        .take(
+            facet
+                .count
+                .map_or(DEFAULT_FACET_COUNT, |count| count as usize),
+        )
+        .map(|(key, count)| match key {
+            FacetKey::Number(value) => json!({ "value": value, "count": count }),
+            FacetKey::Text(value) => json!({ "value": value, "count": count }),
+        })
+        .collect()
+}
+
+fn facet_keys(document: &Document, facet: &FacetSpec) -> Vec<FacetKey> {
+    let values = match document.get(&facet.field) {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => vec![],
+    };
+
+    values
+        .into_iter()
+        .filter_map(|value| match (value, facet.interval) {
+            (Value::Number(number), Some(FacetInterval::Number(interval))) => {
+                let interval = f64::from(interval.max(1));
+                number
+                    .as_f64()
+                    .map(|number| FacetKey::Number(((number / interval).floor() * interval) as i64))
+            }
+            (Value::Number(number), _) => number
+                .as_f64()
+                .map(|number| FacetKey::Number(number as i64)),
+            (Value::String(date), Some(FacetInterval::Day)) => date_bucket(date, 10),
+            (Value::String(date), Some(FacetInterval::Month)) => date_bucket(date, 7),
+            (Value::String(date), Some(FacetInterval::Year)) => date_bucket(date, 4),
+            (Value::String(text), _) => Some(FacetKey::Text(text.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+// Truncates an ISO 8601 date to the start of its day, month or year, as Azure reports them.
+fn date_bucket(date: &str, precision: usize) -> Option<FacetKey> {
+    date.get(..precision).map(|prefix| {
+        FacetKey::Text(format!(
+            "{}{}T00:00:00Z",
+            prefix,
+            &"-01-01"[..10 - precision]
+        ))
+    })
+}
+
+fn seed_documents(index: &str) -> Arc<RwLock<Vec<Document>>> {
+    let client = InMemorySearchClient::from_documents(index, vec![]);
+
+    if let Ok(dir) = std::env::var("SEARCH_IN_MEMORY_DATA_DIR") {
+        let path = std::path::Path::new(&dir).join(format!("{}.json", index));
+        match std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| client.load_documents(&json).map_err(anyhow::Error::from))
+        {
+            Ok(count) => {
+                tracing::info!("Loaded {} documents into {} from {:?}", count, index, path)
+            }
+            Err(e) => tracing::warn!(
+                "Could not load documents into {} from {:?}: {}",
+                index,
+                path,
+                e
+            ),
+        }
+    }
+
+    client.documents
+}
+
+fn field_text<'a>(document: &'a Document, field: &str) -> Vec<&'a str> {
+    match document.get(field) {
+        Some(Value::String(text)) => vec![text],
+        Some(Value::Array(values)) => values.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    }
+}
+
+impl ScoringProfile {
+    fn weight(&self, field: &str) -> f32 {
+        self.weights
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map_or(1.0, |(_, weight)| *weight)
+    }
+
+    fn boost(&self, document: &Document) -> f32 {
+        let freshness = match &self.freshness {
+            Some(freshness) => freshness,
+            None => return 1.0,
+        };
+        let created = document
+            .get(freshness.field)
+            .and_then(Value::as_str)
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok());
+        match created {
+            Some(created) => {
+                let age = Utc::now().signed_duration_since(created).num_days();
+                let remaining = (freshness.days - age.max(0)).max(0) as f32 / freshness.days as f32;
+                1.0 + (freshness.boost - 1.0) * remaining
+            }
+            None => 1.0,
+        }
+    }
+}
+
+// Strengths are normalised first, so `200 mg` in a document matches `200mg` in a search.
+fn tokenize(text: &str) -> Vec<String> {
+    normalize_strengths(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// The document as Azure returns it, without the fields it doesn't store.
+fn retrievable(document: &Document) -> Document {
+    let mut document = document.clone();
+    for field in NON_RETRIEVABLE_FIELDS {
+        document.remove(*field);
+    }
+    document
+}
+
+// Multi-word synonyms are left out, as terms are matched one word at a time.
+fn with_synonyms(term: String, options: &SearchOptions) -> Vec<String> {
+    let synonyms = options
+        .synonyms
+        .as_ref()
+        .map(|synonyms| {
+            synonyms
+                .expand(&term)
+                .into_iter()
+                .filter(|synonym| !synonym.contains(' '))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    std::iter::once(term).chain(synonyms).collect()
+}
+
+fn contains_any(document: &Document, terms: &[String]) -> bool {
+    !terms.is_empty()
+        && SEARCHABLE_FIELDS.iter().any(|field| {
+            field_text(document, field)
+                .iter()
+                .flat_map(|text| tokenize(text))
+                .any(|token| terms.contains(&token))
+        })
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn index_changed(key: &str, status_code: u16) -> AzureIndexChangedResults {
+    AzureIndexChangedResults::new(AzureIndexChangedResult {
+        key: key.to_string(),
+        status: true,
+        error_message: None,
+        status_code,
+    })
+}
+
+#[async_trait]
+impl Search for InMemorySearchClient {
+    async fn search<T>(&self, options: SearchOptions) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.respond(&options).await
+    }
+
+    async fn search_by_facet_field(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<FacetResults, SearchClientError> {
+        self.respond(
+            &SearchOptions::default()
+                .filter(Field::new(field_name)?.any(|f| f.eq(field_value)))
+                .facet(
+                    FacetSpec::new("facets")
+                        .count(50000)
+                        .sort_by(FacetSort::Value, SortOrder::Asc),
+                )
+                .highlight(&[])
+                .top(0),
+        )
+        .await
+    }
+
+    async fn search_facets(
+        &self,
+        options: SearchOptions,
+    ) -> Result<FacetQueryResults, SearchClientError> {
+        self.respond(&facet_options(options)).await
+    }
+
+    async fn suggest(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<SuggestResults, SearchClientError> {
+        into_response(
+            self.query_suggestions(search_term, top.max(0) as usize),
+            &self.index_name,
+        )
+        .await
+    }
+
+    async fn autocomplete(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<AutocompleteResults, SearchClientError> {
+        into_response(
+            self.query_completions(search_term, top.max(0) as usize),
+            &self.index_name,
+        )
+        .await
+    }
+
+    async fn filter_by_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.respond(
+            &SearchOptions::default()
+                .filter(Field::new(field_name)?.any(|f| f.eq(field_value)))
+                .highlight(&[]),
+        )
+        .await
+    }
+
+    async fn filter_by_non_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.respond(
+            &SearchOptions::default()
+                .filter(Field::new(field_name)?.eq(field_value))
+                .highlight(&[]),
+        )
+        .await
+    }
+
+    async fn get_document<T>(&self, key: &str) -> Result<Option<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let document = self
+            .documents
+            .read()
+            .expect("In-memory index was poisoned")
+            .iter()
+            .find(|document| document.get(self.schema.key).and_then(Value::as_str) == Some(key))
+            .map(retrievable);
+
+        match document {
+            Some(document) => into_response(Ok(Value::Object(document)), &self.index_name)
+                .await
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_by_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let documents: Documents<T> = self
+            .filter_by_non_collection_field(field_name, field_value)
+            .await?;
+        Ok(documents.value)
+    }
+}
+
+#[async_trait]
+impl CreateIndexEntry for InMemorySearchClient {
+    async fn create_index_entry(
+        &self,
+        key_values: IndexEntry,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        let document = serde_json::to_value(&key_values)
+            .map_err(|e| SearchClientError::InvalidRequest(e.to_string()))?;
+        let key = self.upsert(document).unwrap_or_default();
+
+        Ok(index_changed(&key, 201))
+    }
+}
+
+#[async_trait]
+impl DeleteIndexEntry for InMemorySearchClient {
+    async fn delete_index_entry(
+        &self,
+        key_name: &str,
+        value: &str,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        self.remove(key_name, value);
+
+        Ok(index_changed(value, 200))
+    }
+}
+
+#[async_trait]
+impl IndexBatch for InMemorySearchClient {
+    async fn index_batch(
+        &self,
+        actions: Vec<IndexBatchAction>,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        Ok(AzureIndexChangedResults::from_results(
+            actions
+                .into_iter()
+                .map(|action| self.apply(action))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::filter::field;
+    use crate::models::{DocumentType, IndexResult, IndexResults, ReportResults, TerritoryType};
+    use crate::{scoring_profiles, AzurePagination, SynonymMap};
+    use pretty_assertions::assert_eq;
+    use std::convert::TryFrom;
+    use tokio_test::block_on;
+
+    fn given_a_product_document(
+        name: &str,
+        product: &str,
+        substance: &str,
+        doc_type: &str,
+    ) -> Value {
+        let first_letter = &substance[..1];
+        json!({
+            "content": format!("{} contains {}. Take one tablet daily.", product, substance),
+            "rev_label": "1",
+            "metadata_storage_path": format!("https://example.com/docs/{}", name),
+            "product_name": product,
+            "created": "2020-01-10T05:06:00+00:00",
+            "release_state": "Y",
+            "keywords": null,
+            "title": format!("{} {}", doc_type, product),
+            "pl_number": ["PL123451234"],
+            "territory": "UK",
+            "file_name": format!("CON{}", name),
+            "metadata_storage_size": 1024,
+            "metadata_storage_name": name,
+            "doc_type": doc_type,
+            "suggestions": [],
+            "substance_name": [substance],
+            "facets": [
+                first_letter,
+                format!("{}, {}", first_letter, substance),
+                format!("{}, {}, {}", first_letter, substance, product),
+            ]
+        })
+    }
+
+    fn given_a_client() -> InMemorySearchClient {
+        InMemorySearchClient::from_documents(
+            "products-index",
+            vec![
+                given_a_product_document("a", "IBUPROFEN 200MG TABLETS", "IBUPROFEN", "Spc"),
+                given_a_product_document("b", "IBUPROFEN 400MG TABLETS", "IBUPROFEN", "Pil"),
+                given_a_product_document("c", "PARACETAMOL 500MG TABLETS", "PARACETAMOL", "Spc"),
+                given_a_product_document("d", "ST JOHN'S WORT TABLETS", "HYPERICUM", "Par"),
+            ],
+        )
+    }
+
+    fn names(results: &IndexResults) -> Vec<&str> {
+        results
+            .search_results
+            .iter()
+            .map(|result| result.metadata_storage_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn search_requires_every_term_to_match() {
+        let client = given_a_client();
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("paracetamol tablets"))).unwrap();
+        assert_eq!(names(&results), vec!["c"]);
+
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("ibuprofen paracetamol"))).unwrap();
+        assert!(results.search_results.is_empty());
+    }
+
+    #[test]
+    fn search_leaves_out_documents_with_excluded_terms() {
+        let client = given_a_client();
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("tablets -ibuprofen"))).unwrap();
+        assert_eq!(names(&results), vec!["c", "d"]);
+    }
+
+    #[test]
+    fn search_finds_synonyms_when_asked_to() {
+        let client = given_a_client();
+        let synonyms = Arc::new(SynonymMap::from_solr("paracetamol, acetaminophen"));
+
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("acetaminophen"))).unwrap();
+        assert!(results.search_results.is_empty());
+
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("acetaminophen tablets").synonyms(synonyms)))
+                .unwrap();
+        assert_eq!(names(&results), vec!["c"]);
+    }
+
+    #[test]
+    fn prefer_recent_ranks_newer_documents_first() {
+        let mut recent =
+            given_a_product_document("new", "IBUPROFEN 200MG TABLETS", "IBUPROFEN", "Pil");
+        recent["created"] = json!(Utc::now().to_rfc3339());
+        let client = InMemorySearchClient::from_documents(
+            "products-index",
+            vec![
+                given_a_product_document("old", "IBUPROFEN 200MG TABLETS", "IBUPROFEN", "Pil"),
+                recent,
+            ],
+        );
+
+        let results: IndexResults = block_on(client.search(
+            SearchOptions::new("ibuprofen").scoring_profile(Some(scoring_profiles::PREFER_RECENT)),
+        ))
+        .unwrap();
+
+        assert_eq!(names(&results), vec!["new", "old"]);
+        assert!(results.search_results[0].score > results.search_results[1].score);
+    }
+
+    #[test]
+    fn search_rejects_an_undeclared_scoring_profile() {
+        let client = given_a_client();
+        let error = block_on(client.search::<IndexResults>(
+            SearchOptions::new("ibuprofen").scoring_profile(Some("preferNothing")),
+        ))
+        .unwrap_err();
+        assert!(matches!(error, SearchClientError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn search_rejects_a_scoring_profile_from_the_other_index() {
+        let products = given_a_client();
+        let reports = InMemorySearchClient::from_documents("bmgf-index", vec![]);
+        let search =
+            |client: &InMemorySearchClient, profile: &str| {
+                block_on(client.search::<Value>(
+                    SearchOptions::new("ibuprofen").scoring_profile(Some(profile)),
+                ))
+            };
+
+        assert!(search(&products, scoring_profiles::PREFER_RECENT).is_ok());
+        assert!(matches!(
+            search(&products, scoring_profiles::PREFER_REPORT_NAME),
+            Err(SearchClientError::InvalidRequest(_))
+        ));
+        assert!(search(&reports, scoring_profiles::PREFER_REPORT_NAME).is_ok());
+        assert!(matches!(
+            search(&reports, scoring_profiles::PREFER_RECENT),
+            Err(SearchClientError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn suggest_matches_word_prefixes_in_suggester_fields() {
+        let client = given_a_client();
+        let results = block_on(Search::suggest(&client, "ibu 400", 5)).unwrap();
+        let texts = results
+            .suggestions
+            .iter()
+            .map(|suggestion| suggestion.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["Pil IBUPROFEN 400MG TABLETS"]);
+    }
+
+    #[test]
+    fn suggest_matches_substance_names() {
+        let client = given_a_client();
+        let results = block_on(Search::suggest(&client, "hyper", 5)).unwrap();
+        let texts = results
+            .suggestions
+            .iter()
+            .map(|suggestion| suggestion.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["HYPERICUM"]);
+    }
+
+    #[test]
+    fn suggest_requires_a_search_term() {
+        let client = given_a_client();
+        let error = block_on(Search::suggest(&client, " ", 5)).unwrap_err();
+        assert!(matches!(error, SearchClientError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn autocomplete_completes_the_last_term() {
+        let client = given_a_client();
+        let results = block_on(Search::autocomplete(&client, "tablets ibu", 5)).unwrap();
+        assert_eq!(results.completions.len(), 1);
+        assert_eq!(results.completions[0].text, "ibuprofen");
+        assert_eq!(results.completions[0].query_plus_text, "tablets ibuprofen");
+    }
+
+    #[test]
+    fn autocomplete_returns_the_top_completions() {
+        let client = given_a_client();
+        let results = block_on(Search::autocomplete(&client, "p", 2)).unwrap();
+        let texts = results
+            .completions
+            .iter()
+            .map(|completion| completion.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["par", "paracetamol"]);
+    }
+
+    #[test]
+    fn search_supports_fuzzy_matches_but_prefers_exact_ones() {
+        let client = given_a_client();
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("ibuprofin"))).unwrap();
+        assert_eq!(names(&results), vec!["a", "b"]);
+
+        let fuzzy = results.search_results[0].score;
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("ibuprofen"))).unwrap();
+        assert!(results.search_results[0].score > fuzzy);
+    }
+
+    #[test]
+    fn search_matches_strengths_regardless_of_spacing() {
+        let client = InMemorySearchClient::from_documents(
+            "products-index",
+            vec![
+                given_a_product_document("a", "IBUPROFEN 200MG TABLETS", "IBUPROFEN", "Spc"),
+                given_a_product_document(
+                    "b",
+                    "Amoxicillin 250 mg / 5 ml Oral Suspension",
+                    "AMOXICILLIN",
+                    "Spc",
+                ),
+            ],
+        );
+        let search = |term: &str| -> Vec<String> {
+            let results: IndexResults = block_on(client.search(SearchOptions::new(term))).unwrap();
+            names(&results).into_iter().map(String::from).collect()
+        };
+
+        assert_eq!(search("ibuprofen 200 mg tablets"), vec!["a"]);
+        assert_eq!(search("ibuprofen 200 milligrams"), vec!["a"]);
+        assert_eq!(search("amoxicillin 250mg/5ml"), vec!["b"]);
+        assert!(search("ibuprofen 400 mg").is_empty());
+    }
+
+    #[test]
+    fn search_normalizes_product_licences() {
+        let client = given_a_client();
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("PL 12345/1234"))).unwrap();
+        assert_eq!(results.search_results.len(), 4);
+    }
+
+    #[test]
+    fn search_with_pagination_counts_and_pages() {
+        let client = given_a_client();
+        let results: IndexResults = block_on(
+            client.search(
+                SearchOptions::new(" ")
+                    .pagination(AzurePagination {
+                        result_count: 2,
+                        offset: 1,
+                    })
+                    .include_count(true),
+            ),
+        )
+        .unwrap();
+        assert_eq!(results.count, Some(4));
+        assert_eq!(names(&results), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn search_with_filter_applies_the_filter() {
+        let client = given_a_client();
+        let results: IndexResults = block_on(
+            client.search(
+                SearchOptions::new("tablets").filter(
+                    field("doc_type")
+                        .eq("Spc")
+                        .and(field("territory").eq("UK").or(field("territory").is_null())),
+                ),
+            ),
+        )
+        .unwrap();
+        assert_eq!(names(&results), vec!["a", "c"]);
+        assert_eq!(results.search_results[0].doc_type, DocumentType::Spc);
+        assert_eq!(results.search_results[0].territory, Some(TerritoryType::UK));
+    }
+
+    #[test]
+    fn search_mode_any_matches_any_term() {
+        let client = given_a_client();
+        let results: IndexResults = block_on(
+            client.search(SearchOptions::new("ibuprofen paracetamol").search_mode(SearchMode::Any)),
+        )
+        .unwrap();
+        assert_eq!(names(&results), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn search_orders_by_fields_and_selects_fields() {
+        let client = given_a_client();
+        let results: Value = block_on(
+            client.search(
+                SearchOptions::new("tablets")
+                    .order_by("doc_type", SortOrder::Asc)
+                    .order_by("product_name", SortOrder::Desc)
+                    .select(&["metadata_storage_name"])
+                    .highlight(&[]),
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            results["value"],
+            json!([
+                { "@search.score": 12.0, "metadata_storage_name": "d" },
+                { "@search.score": 12.0, "metadata_storage_name": "b" },
+                { "@search.score": 12.0, "metadata_storage_name": "c" },
+                { "@search.score": 12.0, "metadata_storage_name": "a" },
+            ])
+        );
+    }
+
+    #[test]
+    fn search_highlights_the_requested_fields() {
+        let client = given_a_client();
+        let results: Value = block_on(
+            client.search(
+                SearchOptions::new("paracetamol")
+                    .highlight(&["title"])
+                    .highlight_tags("<b>", "</b>"),
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            results["value"][0]["@search.highlights"],
+            json!({ "title": ["Spc <b>PARACETAMOL</b> 500MG TABLETS"] })
+        );
+    }
+
+    #[test]
+    fn search_highlights_matches_in_content() {
+        let client = given_a_client();
+        let results: IndexResults =
+            block_on(client.search(SearchOptions::new("paracetamol"))).unwrap();
+        let highlights = results.search_results[0].highlights.clone().unwrap();
+        assert_eq!(
+            highlights.content,
+            vec!["<em>PARACETAMOL</em> 500MG TABLETS contains <em>PARACETAMOL</em>"]
+        );
+    }
+
+    #[test]
+    fn search_by_facet_field_counts_facets_sorted_by_value() {
+        let client = given_a_client();
+        let results = block_on(client.search_by_facet_field("facets", "I")).unwrap();
+        let facets = results
+            .facet_results
+            .facets
+            .iter()
+            .map(|facet| (facet.value.as_str(), facet.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            facets,
+            vec![
+                ("I", 2),
+                ("I, IBUPROFEN", 2),
+                ("I, IBUPROFEN, IBUPROFEN 200MG TABLETS", 1),
+                ("I, IBUPROFEN, IBUPROFEN 400MG TABLETS", 1),
+            ]
+        );
+        assert!(results.search_results.is_empty());
+    }
+
+    #[test]
+    fn search_facets_counts_each_requested_field() {
+        let client = given_a_client();
+        let results = block_on(
+            client.search_facets(
+                SearchOptions::new("tablets")
+                    .filter(field("territory").eq("UK"))
+                    .facet(FacetSpec::new("doc_type").sort_by(FacetSort::Value, SortOrder::Desc))
+                    .facet(FacetSpec::new("product_name").count(1)),
+            ),
+        )
+        .unwrap();
+
+        let counts = |field: &str| {
+            results
+                .field(field)
+                .iter()
+                .map(|facet| (facet.value.to_string(), facet.count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(results.count, Some(4));
+        assert_eq!(
+            counts("doc_type"),
+            vec![
+                ("Spc".to_string(), 2),
+                ("Pil".to_string(), 1),
+                ("Par".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            counts("product_name"),
+            vec![("IBUPROFEN 200MG TABLETS".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn search_facets_rejects_fields_that_are_not_facetable() {
+        let client = given_a_client();
+        let error =
+            block_on(client.search_facets(
+                SearchOptions::new("tablets").facet(FacetSpec::new("substance_name")),
+            ))
+            .unwrap_err();
+
+        assert!(matches!(error, SearchClientError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn facet_intervals_bucket_numbers_and_dates() {
+        let documents = [
+            json!({ "size": 1500, "created": "2020-01-10T05:06:00+00:00" }),
+            json!({ "size": 1999, "created": "2020-03-01T00:00:00+00:00" }),
+            json!({ "size": 2500, "created": "2019-12-31T23:59:00+00:00" }),
+        ];
+        let hits = documents
+            .iter()
+            .map(|document| (1.0, document.as_object().unwrap()))
+            .collect::<Vec<_>>();
+        let counts = |facet: FacetSpec| {
+            count_facet(&facet, &hits)
+                .into_iter()
+                .map(|facet| (facet["value"].to_string(), facet["count"].as_i64().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            counts(FacetSpec::new("size").interval(FacetInterval::Number(1000))),
+            vec![("1000".to_string(), 2), ("2000".to_string(), 1)]
+        );
+        assert_eq!(
+            counts(
+                FacetSpec::new("created")
+                    .interval(FacetInterval::Year)
+                    .sort_by(FacetSort::Value, SortOrder::Asc)
+            ),
+            vec![
+                (r#""2019-01-01T00:00:00Z""#.to_string(), 1),
+                (r#""2020-01-01T00:00:00Z""#.to_string(), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_by_collection_field_matches_quoted_values() {
+        let client = given_a_client();
+        let results: IndexResults = block_on(
+            client.filter_by_non_collection_field("product_name", "ST JOHN'S WORT TABLETS"),
+        )
+        .unwrap();
+        assert_eq!(names(&results), vec!["d"]);
+
+        let results: IndexResults =
+            block_on(client.filter_by_collection_field("substance_name", "IBUPROFEN")).unwrap();
+        assert_eq!(names(&results), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn documents_can_be_looked_up_by_key_or_exact_field_value() {
+        let client = given_a_client();
+
+        let document: Option<IndexResult> = block_on(client.get_document("c")).unwrap();
+        assert_eq!(document.unwrap().file_name, "CONc");
+        let missing: Option<IndexResult> = block_on(client.get_document("z")).unwrap();
+        assert!(missing.is_none());
+
+        let documents: Vec<IndexResult> =
+            block_on(client.find_by_field("file_name", "CONb")).unwrap();
+        assert_eq!(
+            documents
+                .iter()
+                .map(|document| document.metadata_storage_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+        let documents: Vec<IndexResult> =
+            block_on(client.find_by_field("file_name", "CON")).unwrap();
+        assert!(documents.is_empty());
+    }
+
+    #[test]
+    fn created_entries_can_be_found_and_deleted() {
+        let client = given_a_client();
+        let results: IndexResults = block_on(client.search(SearchOptions::new("wort"))).unwrap();
+        let result: IndexResult = results.search_results[0].clone();
+
+        block_on(client.delete_index_entry("metadata_storage_name", "d")).unwrap();
+        let results: IndexResults = block_on(client.search(SearchOptions::new("wort"))).unwrap();
+        assert!(results.search_results.is_empty());
+
+        let created =
+            block_on(client.create_index_entry(IndexEntry::try_from(result).unwrap())).unwrap();
+        assert_eq!(created.value[0].key, "d");
+        let results: IndexResults = block_on(client.search(SearchOptions::new("wort"))).unwrap();
+        assert_eq!(names(&results), vec!["d"]);
+    }
+
+    #[test]
+    fn index_batch_reports_results_per_key() {
+        let client = given_a_client();
+        let mut release_state = Map::new();
+        release_state.insert("metadata_storage_name".to_string(), json!("c"));
+        release_state.insert("release_state".to_string(), json!("N"));
+        let mut missing = release_state.clone();
+        missing.insert("metadata_storage_name".to_string(), json!("z"));
+
+        let results = block_on(client.index_batch(vec![
+            IndexBatchAction::delete("metadata_storage_name", "a"),
+            IndexBatchAction {
+                action: IndexAction::Merge,
+                document: release_state,
+            },
+            IndexBatchAction {
+                action: IndexAction::Merge,
+                document: missing,
+            },
+        ]))
+        .unwrap();
+
+        let statuses = results
+            .value
+            .iter()
+            .map(|result| (result.key.as_str(), result.status, result.status_code))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![("a", true, 200), ("c", true, 200), ("z", false, 404)]
+        );
+        let failed = results
+            .failed()
+            .map(|result| result.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(failed, vec!["z"]);
+
+        let results: IndexResults = block_on(client.search(SearchOptions::new("tablets"))).unwrap();
+        assert_eq!(names(&results), vec!["b", "c", "d"]);
+        assert_eq!(
+            results.search_results[1].release_state,
+            Some("N".to_string())
+        );
+    }
+
+    #[test]
+    fn clients_for_the_same_index_share_documents() {
+        let first = InMemorySearchClient::new_with_index("shared-test-index".to_string());
+        let second = InMemorySearchClient::new_with_index("shared-test-index".to_string());
+        first
+            .load_documents(
+                &given_a_product_document("a", "IBUPROFEN 200MG TABLETS", "IBUPROFEN", "Spc")
+                    .to_string(),
+            )
+            .unwrap();
+
+        let results: IndexResults =
+            block_on(second.search(SearchOptions::new("ibuprofen"))).unwrap();
+        assert_eq!(names(&results), vec!["a"]);
+    }
+
+    #[test]
+    fn loads_and_searches_reports() {
+        let client = InMemorySearchClient::from_documents("bmgf-index", vec![]);
+        let loaded = client
+            .load_documents(
+                r#"{"value": [{
+                    "id": "1",
+                    "report_name": "Lamotrigine report",
+                    "summary": "Levels of lamotrigine in pregnancy",
+                    "file_name": "lamotrigine.pdf",
+                    "metadata_storage_path": "https://example.com/lamotrigine.pdf",
+                    "metadata_storage_name": "lamotrigine",
+                    "metadata_storage_size": 2048,
+                    "active_substances": ["LAMOTRIGINE"],
+                    "facets": ["L", "L, LAMOTRIGINE"]
+                }]}"#,
+            )
+            .unwrap();
+        assert_eq!(loaded, 1);
+
+        let results: ReportResults = block_on(
+            client.search(
+                SearchOptions::new("lamotrigine")
+                    .filter(field("active_substances").any(|substance| substance.eq("LAMOTRIGINE")))
+                    .include_count(true),
+            ),
+        )
+        .unwrap();
+        assert_eq!(results.count, Some(1));
+        assert_eq!(results.search_results[0].report_name, "Lamotrigine report");
+    }
+
+    #[test]
+    fn reports_are_keyed_by_id() {
+        let report = |id: &str, report_name: &str| json!({ "id": id, "report_name": report_name, "metadata_storage_name": "same" });
+        let client = InMemorySearchClient::from_documents(
+            "bmgf-index",
+            vec![report("1", "Lamotrigine"), report("2", "Valproate")],
+        );
+
+        let document: Option<Value> = block_on(client.get_document("2")).unwrap();
+
+        assert_eq!(document.unwrap()["report_name"], "Valproate");
+        assert_eq!(client.documents.read().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn reports_facet_on_trimesters_and_matrices() {
+        let report = |id: &str, trimesters: &[&str], matrices: &[&str]| {
+            json!({
+                "id": id,
+                "report_name": "Lamotrigine report",
+                "pregnancy_trimesters": trimesters,
+                "matrices": matrices,
+            })
+        };
+        let client = InMemorySearchClient::from_documents(
+            "bmgf-index",
+            vec![
+                report("1", &["First", "Second"], &["Plasma"]),
+                report("2", &["Second"], &["Plasma", "Cord blood"]),
+            ],
+        );
+
+        let results = block_on(
+            client.search_facets(
+                SearchOptions::new("lamotrigine")
+                    .facet(FacetSpec::new("pregnancy_trimesters"))
+                    .facet(FacetSpec::new("matrices")),
+            ),
+        )
+        .unwrap();
+
+        let counts = |field: &str| {
+            results
+                .field(field)
+                .iter()
+                .map(|facet| (facet.value.to_string(), facet.count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            counts("pregnancy_trimesters"),
+            vec![("Second".to_string(), 2), ("First".to_string(), 1)]
+        );
+        assert_eq!(
+            counts("matrices"),
+            vec![("Plasma".to_string(), 2), ("Cord blood".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn get_document_leaves_out_fields_that_are_not_retrievable() {
+        let client = given_a_client();
+
+        let document: Option<Value> = block_on(client.get_document("a")).unwrap();
+
+        let document = document.unwrap();
+        assert_eq!(document["product_name"], "IBUPROFEN 200MG TABLETS");
+        assert!(document.get("content").is_none());
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("ibuprofen", "ibuprofen"), 0);
+        assert_eq!(edit_distance("ibuprofin", "ibuprofen"), 1);
+        assert_eq!(edit_distance("ibuprofen", "ibuprofens"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{get_env_or_default, scoring_profiles};
+
+/// The parts of an index definition in `search/definitions/indexes` that change how the
+/// in-memory client behaves, so that it treats each index as Azure would.
+pub(crate) struct IndexSchema {
+    /// The field with `"key": true`.
+    pub(crate) key: &'static str,
+    /// The fields with `"facetable": true`. Azure rejects a facet on any other.
+    pub(crate) facetable: &'static [&'static str],
+    /// The `sourceFields` of the `azure-suggester`.
+    pub(crate) suggester: &'static [&'static str],
+    /// The `scoringProfiles`. Azure rejects a search with any other profile.
+    pub(crate) scoring_profiles: &'static [ScoringProfile],
+}
+
+pub(crate) struct ScoringProfile {
+    pub(crate) name: &'static str,
+    pub(crate) weights: &'static [(&'static str, f32)],
+    pub(crate) freshness: Option<Freshness>,
+}
+
+// Boosts documents by up to `boost` times, the most for those dated today and falling
+// linearly to none for those `days` old, as Azure's linear interpolation does.
+pub(crate) struct Freshness {
+    pub(crate) field: &'static str,
+    pub(crate) boost: f32,
+    pub(crate) days: i64,
+}
+
+const PRODUCT_KEYWORD_WEIGHTS: &[(&str, f32)] = &[
+    ("keywords", 3.0),
+    ("pl_number", 10.0),
+    ("product_name", 3.0),
+];
+const REPORT_KEYWORD_WEIGHTS: &[(&str, f32)] = &[
+    ("pl_numbers", 10.0),
+    ("report_name", 5.0),
+    ("active_substances", 3.0),
+    ("products", 3.0),
+];
+const REPORT_NAME_WEIGHTS: &[(&str, f32)] = &[
+    ("report_name", 10.0),
+    ("pl_numbers", 5.0),
+    ("active_substances", 2.0),
+    ("products", 2.0),
+];
+
+// `search/definitions/indexes/default.json`
+static PRODUCTS: IndexSchema = IndexSchema {
+    key: "metadata_storage_name",
+    facetable: &[
+        "product_name",
+        "pl_number",
+        "territory",
+        "doc_type",
+        "facets",
+    ],
+    suggester: &["title", "product_name", "substance_name"],
+    scoring_profiles: &[
+        ScoringProfile {
+            name: scoring_profiles::PREFER_KEYWORDS,
+            weights: PRODUCT_KEYWORD_WEIGHTS,
+            freshness: None,
+        },
+        ScoringProfile {
+            name: scoring_profiles::PREFER_RECENT,
+            weights: PRODUCT_KEYWORD_WEIGHTS,
+            freshness: Some(Freshness {
+                field: "created",
+                boost: 2.0,
+                days: 365,
+            }),
+        },
+    ],
+};
+
+// `search/definitions/indexes/bmgf.json`
+static BMGF: IndexSchema = IndexSchema {
+    key: "id",
+    facetable: &["matrices", "facets", "pregnancy_trimesters"],
+    suggester: &["report_name", "active_substances", "products"],
+    scoring_profiles: &[
+        ScoringProfile {
+            name: scoring_profiles::PREFER_KEYWORDS,
+            weights: REPORT_KEYWORD_WEIGHTS,
+            freshness: None,
+        },
+        ScoringProfile {
+            name: scoring_profiles::PREFER_REPORT_NAME,
+            weights: REPORT_NAME_WEIGHTS,
+            freshness: None,
+        },
+    ],
+};
+
+impl IndexSchema {
+    /// The BMGF definition for the index named by `BMGF_AZURE_SEARCH_INDEX` (as the API is
+    /// configured), and the products definition for any other.
+    pub(crate) fn for_index(index: &str) -> &'static Self {
+        if index == get_env_or_default("BMGF_AZURE_SEARCH_INDEX", "bmgf-index") {
+            &BMGF
+        } else {
+            &PRODUCTS
+        }
+    }
+
+    pub(crate) fn check_facetable(&self, field: &str) -> Result<(), String> {
+        if self.facetable.contains(&field) {
+            Ok(())
+        } else {
+            Err(format!("Field '{}' is not facetable.", field))
+        }
+    }
+
+    pub(crate) fn scoring_profile(&self, name: &str) -> Result<&'static ScoringProfile, String> {
+        self.scoring_profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .ok_or_else(|| format!("Unknown scoring profile '{}'.", name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{json, Value};
+    use test_case::test_case;
+
+    fn definition(file: &str) -> Value {
+        let path = format!(
+            "{}/../search/definitions/indexes/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            file
+        );
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn fields_where(definition: &Value, attribute: &str) -> Vec<String> {
+        definition["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|field| field[attribute] == Value::Bool(true))
+            .map(|field| field["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test_case("products-index", "default.json")]
+    #[test_case("bmgf-index", "bmgf.json")]
+    fn schema_matches_the_index_definition(index: &str, file: &str) {
+        let schema = IndexSchema::for_index(index);
+        let definition = definition(file);
+
+        assert_eq!(fields_where(&definition, "key"), vec![schema.key]);
+        assert_eq!(fields_where(&definition, "facetable"), schema.facetable);
+        assert_eq!(
+            definition["suggesters"][0]["sourceFields"],
+            json!(schema.suggester)
+        );
+        for (profile, expected) in schema
+            .scoring_profiles
+            .iter()
+            .zip(definition["scoringProfiles"].as_array().unwrap())
+        {
+            assert_eq!(profile.name, expected["name"]);
+            let weights = profile
+                .weights
+                .iter()
+                .map(|(field, weight)| (field.to_string(), json!(*weight as u64)))
+                .collect::<serde_json::Map<_, _>>();
+            assert_eq!(Value::Object(weights), expected["text"]["weights"]);
+            match &profile.freshness {
+                Some(freshness) => {
+                    let function = &expected["functions"][0];
+                    assert_eq!(function["fieldName"], freshness.field);
+                    assert_eq!(function["boost"], json!(freshness.boost as u64));
+                    assert_eq!(
+                        function["freshness"]["boostingDuration"],
+                        format!("P{}D", freshness.days)
+                    );
+                }
+                None => assert!(expected["functions"].is_null()),
+            }
+        }
+        assert_eq!(
+            schema.scoring_profiles.len(),
+            definition["scoringProfiles"].as_array().unwrap().len()
+        );
+
+        let searchable = fields_where(&definition, "searchable");
+        for field in schema.suggester {
+            assert!(searchable.contains(&field.to_string()), "{}", field);
+        }
+        // Azure only applies synonym maps to searchable fields, and rejects them on any other.
+        for field in definition["fields"].as_array().unwrap() {
+            if field["synonymMaps"]
+                .as_array()
+                .iter()
+                .any(|maps| !maps.is_empty())
+            {
+                assert_eq!(field["searchable"], true, "{}", field["name"]);
+            }
+        }
+    }
+}
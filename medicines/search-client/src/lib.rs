@@ -1,35 +1,86 @@
+mod analytics;
+mod cache;
+pub mod credentials;
 mod document_type;
+mod error;
+mod facet;
+pub mod filter;
+mod fuzziness;
+mod in_memory;
 pub mod models;
+mod product_licence;
 mod query_normalizer;
+mod query_parser;
+mod read_only;
+mod retry;
+mod scan;
+mod search_options;
+mod synonyms;
 mod territory_type;
 
 #[macro_use]
 extern crate lazy_static;
 
-use crate::models::{AzureIndexChangedResults, FacetResults, IndexEntry};
-use crate::query_normalizer::{
-    escape_special_characters, escape_special_words, normalize_product_licences,
-    prefer_exact_match_but_support_fuzzy_match,
+pub use crate::analytics::{InstrumentedSearch, SearchEvent, SearchRecorder};
+pub use crate::cache::{CachePolicy, CachedMethod, CachedSearch};
+use crate::credentials::Credential;
+use crate::error::handle_response;
+pub use crate::error::SearchClientError;
+pub use crate::facet::{FacetInterval, FacetSort, FacetSpec};
+use crate::filter::{Field, Filter};
+pub use crate::fuzziness::FuzzinessRules;
+pub use crate::in_memory::InMemorySearchClient;
+use crate::models::{
+    AutocompleteResults, AzureIndexChangedResults, Documents, FacetQueryResults, FacetResults,
+    IndexBatchAction, IndexEntry, SuggestResults,
 };
+pub use crate::read_only::ReadOnlySearchClient;
+use crate::retry::Resilience;
+pub use crate::scan::{scan, scan_pages, ScanOptions, ScanPage};
+pub use crate::search_options::{
+    scoring_profiles, QueryType, SearchMode, SearchOptions, SortOrder,
+};
+pub use crate::synonyms::SynonymMap;
 use async_trait::async_trait;
 use core::fmt::Debug;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+// Azure Search accepts at most 1000 documents, or 16 MB, per indexing request.
+const MAX_INDEX_BATCH_SIZE: usize = 1000;
+const MAX_INDEX_BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+// The suggester declared in the index definitions (see search/definitions/indexes).
+const SUGGESTER_NAME: &str = "azure-suggester";
 
 #[derive(Clone)]
 struct AzureConfig {
-    search_service: String,
+    base_url: String,
     search_index: String,
-    api_key: String,
     api_version: String,
-    search_fuzziness: String,
+    fuzziness: FuzzinessRules,
     search_exactness_boost: String,
 }
 
+impl AzureConfig {
+    fn docs_url(&self, path: &str) -> String {
+        format!(
+            "{base_url}/indexes/{search_index}/docs{path}",
+            base_url = self.base_url.trim_end_matches('/'),
+            search_index = self.search_index,
+            path = path
+        )
+    }
+}
+
+#[derive(Clone)]
 pub struct AzureSearchClient {
     client: reqwest::Client,
     config: AzureConfig,
+    credential: Arc<dyn Credential>,
+    resilience: Resilience,
 }
 
 pub struct AzurePagination {
@@ -54,26 +105,82 @@ impl AzureSearchClient {
     }
 
     pub fn new_with_index(index: String) -> Self {
-        let api_key = get_env("AZURE_API_ADMIN_KEY");
+        let credential = credentials::from_env("AZURE_API_ADMIN_KEY", credentials::SEARCH_SCOPE);
+        AzureSearchClient::new_with_credential(index, credential)
+    }
+
+    pub(crate) fn new_with_credential(index: String, credential: Arc<dyn Credential>) -> Self {
         let search_index = index;
-        let search_service = get_env("SEARCH_SERVICE");
+        let base_url = search_service_url();
         let api_version = get_env("AZURE_SEARCH_API_VERSION");
 
-        let search_fuzziness = get_env_or_default("AZURE_SEARCH_WORD_FUZZINESS", "1");
+        let fuzziness = FuzzinessRules::from_env();
         let search_exactness_boost = get_env_or_default("AZURE_SEARCH_EXACTNESS_BOOST", "4");
 
         AzureSearchClient {
             client: reqwest::Client::new(),
             config: AzureConfig {
-                api_key,
                 search_index,
-                search_service,
+                base_url,
                 api_version,
-                search_fuzziness,
+                fuzziness,
                 search_exactness_boost,
             },
+            credential,
+            resilience: Resilience::from_env(),
         }
     }
+
+    /// Match words by `fuzziness` rather than the rules set in the environment.
+    pub fn with_fuzziness(mut self, fuzziness: FuzzinessRules) -> Self {
+        self.config.fuzziness = fuzziness;
+        self
+    }
+
+    /// Authenticate with `credential` rather than the one chosen by `AZURE_AUTH_MODE`.
+    pub fn with_credential(mut self, credential: Arc<dyn Credential>) -> Self {
+        self.credential = credential;
+        self
+    }
+
+    async fn send<T>(&self, request: reqwest::Request) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        tracing::debug!("Requesting from URL: {}", &request.url());
+
+        let client = &self.client;
+        let credential = self.credential.as_ref();
+        let index = self.config.search_index.as_str();
+        self.resilience
+            .run(move || {
+                let mut request = request
+                    .try_clone()
+                    .expect("Search requests are built with buffered bodies");
+                // Authorised on each attempt, as a token may need refreshing between them.
+                async move {
+                    credential.authorize(&mut request).await?;
+                    handle_response(client.execute(request).await?, index).await
+                }
+            })
+            .await
+    }
+
+    // Azure responds with 207 when only some of the documents were indexed, with the failures
+    // reported per key in the body.
+    async fn update_index<T>(
+        &self,
+        values: &[T],
+    ) -> Result<AzureIndexChangedResults, SearchClientError>
+    where
+        T: Serialize + Debug,
+    {
+        let request = build_index_request(values, &self.client, &self.config)?;
+
+        tracing::debug!("\nBody: {:?}", &values);
+        tracing::debug!("\nRequest: {:?}", &request);
+        self.send(request).await
+    }
 }
 
 pub fn get_env(key: &str) -> String {
@@ -87,32 +194,250 @@ pub fn get_env_or_default(key: &str, default: &str) -> String {
     })
 }
 
-pub fn factory() -> impl Search + DeleteIndexEntry + CreateIndexEntry {
-    AzureSearchClient::new()
+// `SEARCH_SERVICE_URL` points the client somewhere other than Azure, such as a mock server
+// or emulator running locally over plain http.
+fn search_service_url() -> String {
+    std::env::var("SEARCH_SERVICE_URL")
+        .unwrap_or_else(|_| format!("https://{}.search.windows.net", get_env("SEARCH_SERVICE")))
+}
+
+pub fn factory() -> impl Search + DeleteIndexEntry + CreateIndexEntry + IndexBatch {
+    SearchClient::new()
+}
+
+/// The backends a `SearchClient` can use.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Backend {
+    Azure,
+    InMemory,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "azure" => Ok(Backend::Azure),
+            "in-memory" => Ok(Backend::InMemory),
+            _ => Err(format!(
+                r#"SEARCH_CLIENT_BACKEND must be "azure" or "in-memory", not "{}""#,
+                s
+            )),
+        }
+    }
+}
+
+impl Backend {
+    /// The backend named by `SEARCH_CLIENT_BACKEND`. Panics on any other value, so that a
+    /// typo can't quietly point a service at Azure.
+    pub(crate) fn from_env() -> Self {
+        get_env_or_default("SEARCH_CLIENT_BACKEND", "azure")
+            .parse()
+            .unwrap_or_else(|e: String| panic!("{}", e))
+    }
+}
+
+/// Either the Azure Search service or an in-process stand-in for it, chosen by
+/// `SEARCH_CLIENT_BACKEND` (`azure`, the default, or `in-memory`).
+#[derive(Clone)]
+pub enum SearchClient {
+    Azure(AzureSearchClient),
+    InMemory(InMemorySearchClient),
+}
+
+impl Default for SearchClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchClient {
+    pub fn new() -> Self {
+        let default_index = get_env("AZURE_SEARCH_INDEX");
+        SearchClient::new_with_index(default_index)
+    }
+
+    pub fn new_with_index(index: String) -> Self {
+        match Backend::from_env() {
+            Backend::InMemory => {
+                SearchClient::InMemory(InMemorySearchClient::new_with_index(index))
+            }
+            Backend::Azure => SearchClient::Azure(AzureSearchClient::new_with_index(index)),
+        }
+    }
 }
 
 #[async_trait]
-pub trait Search {
-    async fn search<T>(&self, search_term: &str) -> Result<T, reqwest::Error>
+impl Search for SearchClient {
+    async fn search<T>(&self, options: SearchOptions) -> Result<T, SearchClientError>
     where
-        T: DeserializeOwned;
+        T: DeserializeOwned,
+    {
+        match self {
+            SearchClient::Azure(client) => client.search(options).await,
+            SearchClient::InMemory(client) => client.search(options).await,
+        }
+    }
+
+    async fn search_by_facet_field(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<FacetResults, SearchClientError> {
+        match self {
+            SearchClient::Azure(client) => {
+                client.search_by_facet_field(field_name, field_value).await
+            }
+            SearchClient::InMemory(client) => {
+                client.search_by_facet_field(field_name, field_value).await
+            }
+        }
+    }
+
+    async fn search_facets(
+        &self,
+        options: SearchOptions,
+    ) -> Result<FacetQueryResults, SearchClientError> {
+        match self {
+            SearchClient::Azure(client) => client.search_facets(options).await,
+            SearchClient::InMemory(client) => client.search_facets(options).await,
+        }
+    }
 
-    async fn search_with_pagination<T>(
+    async fn suggest(
         &self,
         search_term: &str,
-        pagination: AzurePagination,
-        include_count: bool,
-    ) -> Result<T, reqwest::Error>
-    where
-        T: DeserializeOwned;
+        top: i32,
+    ) -> Result<SuggestResults, SearchClientError> {
+        match self {
+            SearchClient::Azure(client) => client.suggest(search_term, top).await,
+            SearchClient::InMemory(client) => client.suggest(search_term, top).await,
+        }
+    }
 
-    async fn search_with_pagination_and_filter<T>(
+    async fn autocomplete(
         &self,
         search_term: &str,
-        pagination: AzurePagination,
-        include_count: bool,
-        filter: Option<&str>,
-    ) -> Result<T, reqwest::Error>
+        top: i32,
+    ) -> Result<AutocompleteResults, SearchClientError> {
+        match self {
+            SearchClient::Azure(client) => client.autocomplete(search_term, top).await,
+            SearchClient::InMemory(client) => client.autocomplete(search_term, top).await,
+        }
+    }
+
+    async fn filter_by_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            SearchClient::Azure(client) => {
+                client
+                    .filter_by_collection_field(field_name, field_value)
+                    .await
+            }
+            SearchClient::InMemory(client) => {
+                client
+                    .filter_by_collection_field(field_name, field_value)
+                    .await
+            }
+        }
+    }
+
+    async fn filter_by_non_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            SearchClient::Azure(client) => {
+                client
+                    .filter_by_non_collection_field(field_name, field_value)
+                    .await
+            }
+            SearchClient::InMemory(client) => {
+                client
+                    .filter_by_non_collection_field(field_name, field_value)
+                    .await
+            }
+        }
+    }
+
+    async fn get_document<T>(&self, key: &str) -> Result<Option<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            SearchClient::Azure(client) => client.get_document(key).await,
+            SearchClient::InMemory(client) => client.get_document(key).await,
+        }
+    }
+
+    async fn find_by_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            SearchClient::Azure(client) => client.find_by_field(field_name, field_value).await,
+            SearchClient::InMemory(client) => client.find_by_field(field_name, field_value).await,
+        }
+    }
+}
+
+#[async_trait]
+impl CreateIndexEntry for SearchClient {
+    async fn create_index_entry(
+        &self,
+        key_values: IndexEntry,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        match self {
+            SearchClient::Azure(client) => client.create_index_entry(key_values).await,
+            SearchClient::InMemory(client) => client.create_index_entry(key_values).await,
+        }
+    }
+}
+
+#[async_trait]
+impl DeleteIndexEntry for SearchClient {
+    async fn delete_index_entry(
+        &self,
+        key_name: &str,
+        value: &str,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        match self {
+            SearchClient::Azure(client) => client.delete_index_entry(key_name, value).await,
+            SearchClient::InMemory(client) => client.delete_index_entry(key_name, value).await,
+        }
+    }
+}
+
+#[async_trait]
+impl IndexBatch for SearchClient {
+    async fn index_batch(
+        &self,
+        actions: Vec<IndexBatchAction>,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        match self {
+            SearchClient::Azure(client) => client.index_batch(actions).await,
+            SearchClient::InMemory(client) => client.index_batch(actions).await,
+        }
+    }
+}
+
+#[async_trait]
+pub trait Search {
+    async fn search<T>(&self, options: SearchOptions) -> Result<T, SearchClientError>
     where
         T: DeserializeOwned;
 
@@ -120,13 +445,32 @@ pub trait Search {
         &self,
         field_name: &str,
         field_value: &str,
-    ) -> Result<FacetResults, reqwest::Error>;
+    ) -> Result<FacetResults, SearchClientError>;
+
+    /// Counts the facets requested in `options` over the documents matching its search term
+    /// and filter, without returning the documents themselves.
+    async fn search_facets(
+        &self,
+        options: SearchOptions,
+    ) -> Result<FacetQueryResults, SearchClientError>;
+
+    async fn suggest(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<SuggestResults, SearchClientError>;
+
+    async fn autocomplete(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<AutocompleteResults, SearchClientError>;
 
     async fn filter_by_collection_field<T>(
         &self,
         field_name: &str,
         field_value: &str,
-    ) -> Result<T, reqwest::Error>
+    ) -> Result<T, SearchClientError>
     where
         T: DeserializeOwned;
 
@@ -134,287 +478,237 @@ pub trait Search {
         &self,
         field_name: &str,
         field_value: &str,
-    ) -> Result<T, reqwest::Error>
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned;
+
+    /// Fetches the document with the given key, or `None` if there isn't one.
+    async fn get_document<T>(&self, key: &str) -> Result<Option<T>, SearchClientError>
+    where
+        T: DeserializeOwned;
+
+    /// Finds the documents whose `field_name` is exactly `field_value`. Unlike a search, this
+    /// doesn't depend on how the field is analysed, but the field must be filterable.
+    async fn find_by_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<T>, SearchClientError>
     where
         T: DeserializeOwned;
 }
 
 #[async_trait]
 impl Search for AzureSearchClient {
-    async fn search<T>(&self, search_term: &str) -> Result<T, reqwest::Error>
+    async fn search<T>(&self, options: SearchOptions) -> Result<T, SearchClientError>
     where
         T: DeserializeOwned,
     {
-        search::<T>(search_term, None, None, None, &self.client, &self.config).await
+        let request = build_search(&options, &self.client, &self.config)?;
+        self.send(request).await
+    }
+
+    async fn search_by_facet_field(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<FacetResults, SearchClientError> {
+        let filter = Field::new(field_name)?.any(|f| f.eq(field_value));
+        let request = build_facet_search(&filter, &self.client, &self.config)?;
+        self.send(request).await
+    }
+
+    async fn search_facets(
+        &self,
+        options: SearchOptions,
+    ) -> Result<FacetQueryResults, SearchClientError> {
+        self.search(facet_options(options)).await
     }
 
-    async fn search_with_pagination<T>(
+    async fn suggest(
         &self,
         search_term: &str,
-        pagination: AzurePagination,
-        include_count: bool,
-    ) -> Result<T, reqwest::Error>
-    where
-        T: DeserializeOwned,
-    {
-        search::<T>(
-            search_term,
-            Some(pagination),
-            Some(include_count),
-            None,
-            &self.client,
-            &self.config,
-        )
-        .await
+        top: i32,
+    ) -> Result<SuggestResults, SearchClientError> {
+        let request = build_suggest_request(search_term, top, &self.client, &self.config)?;
+        self.send(request).await
     }
 
-    async fn search_with_pagination_and_filter<T>(
+    async fn autocomplete(
         &self,
         search_term: &str,
-        pagination: AzurePagination,
-        include_count: bool,
-        filter: Option<&str>,
-    ) -> Result<T, reqwest::Error>
-    where
-        T: DeserializeOwned,
-    {
-        search::<T>(
-            search_term,
-            Some(pagination),
-            Some(include_count),
-            filter,
-            &self.client,
-            &self.config,
-        )
-        .await
+        top: i32,
+    ) -> Result<AutocompleteResults, SearchClientError> {
+        let request = build_autocomplete_request(search_term, top, &self.client, &self.config)?;
+        self.send(request).await
     }
 
-    async fn search_by_facet_field(
+    async fn filter_by_collection_field<T>(
         &self,
         field_name: &str,
         field_value: &str,
-    ) -> Result<FacetResults, reqwest::Error> {
-        let request =
-            build_facet_search(field_name, field_value, "eq", &self.client, &self.config)?;
-
-        tracing::debug!("Requesting from URL: {}", &request.url());
-        self.client
-            .execute(request)
-            .await?
-            .error_for_status()?
-            .json::<FacetResults>()
-            .await
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let filter = Field::new(field_name)?.any(|f| f.eq(field_value));
+        let request = build_filter_request(&filter, &self.client, &self.config)?;
+        self.send(request).await
     }
 
-    async fn filter_by_collection_field<T>(
+    async fn filter_by_non_collection_field<T>(
         &self,
         field_name: &str,
         field_value: &str,
-    ) -> Result<T, reqwest::Error>
+    ) -> Result<T, SearchClientError>
     where
         T: DeserializeOwned,
     {
-        let request = build_filter_by_collection_request(
-            field_name,
-            field_value,
-            "eq",
-            &self.client,
-            &self.config,
-        )?;
+        let filter = Field::new(field_name)?.eq(field_value);
+        let request = build_filter_request(&filter, &self.client, &self.config)?;
+        self.send(request).await
+    }
 
-        tracing::debug!("Requesting from URL: {}", &request.url());
-        self.client
-            .execute(request)
-            .await?
-            .error_for_status()?
-            .json::<T>()
-            .await
+    async fn get_document<T>(&self, key: &str) -> Result<Option<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let request = build_get_document_request(key, &self.client, &self.config)?;
+        match self.send(request).await {
+            Ok(document) => Ok(Some(document)),
+            Err(SearchClientError::DocumentNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
     }
 
-    async fn filter_by_non_collection_field<T>(
+    async fn find_by_field<T>(
         &self,
         field_name: &str,
         field_value: &str,
-    ) -> Result<T, reqwest::Error>
+    ) -> Result<Vec<T>, SearchClientError>
     where
         T: DeserializeOwned,
     {
-        let request = build_filter_by_field_request(
-            field_name,
-            field_value,
-            "eq",
-            &self.client,
-            &self.config,
-        )?;
-
-        self.client
-            .execute(request)
-            .await?
-            .error_for_status()?
-            .json::<T>()
-            .await
+        let documents: Documents<T> = self
+            .filter_by_non_collection_field(field_name, field_value)
+            .await?;
+        Ok(documents.value)
     }
 }
 
-fn clean_up_search_term(search_term: &str) -> String {
-    let search_term = normalize_product_licences(&search_term);
-    let search_term = escape_special_characters(&search_term);
-    escape_special_words(&search_term)
+fn build_search(
+    options: &SearchOptions,
+    client: &reqwest::Client,
+    config: &AzureConfig,
+) -> Result<reqwest::Request, reqwest::Error> {
+    let base_url = config.docs_url("");
+
+    let mut query = vec![("api-version", config.api_version.clone())];
+    query.extend(options.query(&config.fuzziness, &config.search_exactness_boost));
+
+    client.get(&base_url).query(&query).build()
 }
 
-fn add_fuzzy_search(
-    search_term: &str,
-    search_fuzziness: &str,
-    search_exactness_boost: &str,
-) -> String {
-    search_term
-        .split(' ')
-        .filter(|word| !word.is_empty())
-        .map(|word| {
-            prefer_exact_match_but_support_fuzzy_match(
-                word,
-                search_fuzziness,
-                search_exactness_boost,
-            )
-        })
-        .collect::<Vec<String>>()
-        .join(" ")
+// Only the counts are wanted, so skip the documents and their highlights.
+pub(crate) fn facet_options(options: SearchOptions) -> SearchOptions {
+    options.highlight(&[]).include_count(true).top(0)
 }
 
-fn build_search(
-    search_term: &str,
-    pagination: Option<AzurePagination>,
-    include_count: Option<bool>,
-    filter: Option<&str>,
+fn build_filter_request(
+    filter: &Filter,
     client: &reqwest::Client,
     config: &AzureConfig,
 ) -> Result<reqwest::Request, reqwest::Error> {
-    let base_url = format!(
-        "https://{search_service}.search.windows.net/indexes/{search_index}/docs",
-        search_service = config.search_service,
-        search_index = config.search_index
-    );
-
-    let include_count = include_count.unwrap_or(false).to_string();
-
-    let search_term = clean_up_search_term(search_term);
-    let search_term = add_fuzzy_search(
-        &search_term,
-        &config.search_fuzziness,
-        &config.search_exactness_boost,
-    );
-
-    let mut request_builder = client
+    let base_url = config.docs_url("");
+
+    client
         .get(&base_url)
         .query(&[
-            ("api-version", config.api_version.as_str()),
-            ("highlight", "content"),
-            ("queryType", "full"),
-            ("search", &search_term),
-            ("scoringProfile", "preferKeywords"),
-            ("searchMode", "all"),
-            ("$count", &include_count),
+            ("api-version", &config.api_version),
+            ("$filter", &filter.to_string()),
         ])
-        .header("api-key", &config.api_key);
+        .build()
+}
 
-    if let Some(filter) = filter {
-        request_builder = request_builder.query(&[("$filter", filter)]);
-    }
+fn build_get_document_request(
+    key: &str,
+    client: &reqwest::Client,
+    config: &AzureConfig,
+) -> Result<reqwest::Request, reqwest::Error> {
+    let mut request = client
+        .get(&config.docs_url(""))
+        .query(&[("api-version", &config.api_version)])
+        .build()?;
 
-    match pagination {
-        Some(pagination) => Ok(request_builder
-            .query(&[
-                ("$top", pagination.result_count.to_string()),
-                ("$skip", pagination.offset.to_string()),
-            ])
-            .build()?),
-        None => Ok(request_builder.build()?),
-    }
+    // Pushing the key as a path segment escapes any characters that aren't allowed in a path.
+    request
+        .url_mut()
+        .path_segments_mut()
+        .expect("Search service URLs can have a path")
+        .push(key);
+    Ok(request)
 }
 
-fn build_filter_by_collection_request(
-    field_name: &str,
-    value: &str,
-    operator: &str,
+fn build_facet_search(
+    filter: &Filter,
     client: &reqwest::Client,
     config: &AzureConfig,
 ) -> Result<reqwest::Request, reqwest::Error> {
-    let base_url = format!(
-        "https://{search_service}.search.windows.net/indexes/{search_index}/docs",
-        search_service = config.search_service,
-        search_index = config.search_index
-    );
-
-    let filter = format!(
-        "{field_name}/any(f: f {operator} '{value}')",
-        field_name = field_name,
-        value = value,
-        operator = operator,
-    );
+    let base_url = config.docs_url("");
 
     client
         .get(&base_url)
-        .query(&[("api-version", &config.api_version), ("$filter", &filter)])
-        .header("api-key", &config.api_key)
+        .query(&[
+            ("api-version", &config.api_version),
+            ("$filter", &filter.to_string()),
+            (
+                "facet",
+                &FacetSpec::new("facets")
+                    .count(50000)
+                    .sort_by(FacetSort::Value, SortOrder::Asc)
+                    .to_string(),
+            ),
+            ("$top", &String::from("0")),
+        ])
         .build()
 }
 
-fn build_facet_search(
-    field_name: &str,
-    value: &str,
-    operator: &str,
+fn build_suggest_request(
+    search_term: &str,
+    top: i32,
     client: &reqwest::Client,
     config: &AzureConfig,
 ) -> Result<reqwest::Request, reqwest::Error> {
-    let base_url = format!(
-        "https://{search_service}.search.windows.net/indexes/{search_index}/docs",
-        search_service = config.search_service,
-        search_index = config.search_index
-    );
-
-    let filter = format!(
-        "{field_name}/any(f: f {operator} '{value}')",
-        field_name = field_name,
-        value = value,
-        operator = operator,
-    );
+    let base_url = config.docs_url("/suggest");
 
     client
         .get(&base_url)
         .query(&[
-            ("api-version", &config.api_version),
-            ("$filter", &filter),
-            ("facet", &String::from("facets,count:50000,sort:value")),
-            ("$top", &String::from("0")),
+            ("api-version", config.api_version.as_str()),
+            ("search", search_term),
+            ("suggesterName", SUGGESTER_NAME),
+            ("$top", &top.to_string()),
         ])
-        .header("api-key", &config.api_key)
         .build()
 }
 
-fn build_filter_by_field_request(
-    field_name: &str,
-    value: &str,
-    operator: &str,
+fn build_autocomplete_request(
+    search_term: &str,
+    top: i32,
     client: &reqwest::Client,
     config: &AzureConfig,
 ) -> Result<reqwest::Request, reqwest::Error> {
-    let base_url = format!(
-        "https://{search_service}.search.windows.net/indexes/{search_index}/docs",
-        search_service = config.search_service,
-        search_index = config.search_index
-    );
-
-    let filter = format!(
-        "{field_name} {operator} '{value}'",
-        field_name = field_name,
-        value = value,
-        operator = operator,
-    );
+    let base_url = config.docs_url("/autocomplete");
 
     client
         .get(&base_url)
-        .query(&[("api-version", &config.api_version), ("$filter", &filter)])
-        .header("api-key", &config.api_key)
+        .query(&[
+            ("api-version", config.api_version.as_str()),
+            ("search", search_term),
+            ("suggesterName", SUGGESTER_NAME),
+            ("autocompleteMode", "oneTerm"),
+            ("$top", &top.to_string()),
+        ])
         .build()
 }
 
@@ -424,7 +718,7 @@ pub trait DeleteIndexEntry {
         &self,
         key_name: &str,
         value: &str,
-    ) -> Result<AzureIndexChangedResults, anyhow::Error>;
+    ) -> Result<AzureIndexChangedResults, SearchClientError>;
 }
 
 #[async_trait]
@@ -433,12 +727,12 @@ impl DeleteIndexEntry for AzureSearchClient {
         &self,
         key_name: &str,
         value: &str,
-    ) -> Result<AzureIndexChangedResults, anyhow::Error> {
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
         let mut key_values = HashMap::new();
         key_values.insert(key_name, value);
         key_values.insert("@search.action", "delete");
 
-        update_index(key_values, &self.client, &self.config).await
+        ensure_indexed(self.update_index(&[key_values]).await?)
     }
 }
 
@@ -447,7 +741,7 @@ pub trait CreateIndexEntry {
     async fn create_index_entry(
         &self,
         key_values: IndexEntry,
-    ) -> Result<AzureIndexChangedResults, anyhow::Error>;
+    ) -> Result<AzureIndexChangedResults, SearchClientError>;
 }
 
 #[async_trait]
@@ -455,85 +749,119 @@ impl CreateIndexEntry for AzureSearchClient {
     async fn create_index_entry(
         &self,
         key_values: IndexEntry,
-    ) -> Result<AzureIndexChangedResults, anyhow::Error> {
-        update_index(key_values, &self.client, &self.config).await
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        ensure_indexed(self.update_index(&[key_values]).await?)
     }
 }
 
-async fn search<T>(
-    search_term: &str,
-    pagination: Option<AzurePagination>,
-    include_count: Option<bool>,
-    filter: Option<&str>,
-    client: &reqwest::Client,
-    config: &AzureConfig,
-) -> Result<T, reqwest::Error>
-where
-    T: DeserializeOwned,
-{
-    let req = build_search(
-        search_term,
-        pagination,
-        include_count,
-        filter,
-        &client,
-        &config,
-    )?;
-
-    tracing::debug!("Requesting from URL: {}", &req.url());
-    client
-        .execute(req)
-        .await?
-        .error_for_status()?
-        .json::<T>()
-        .await
+#[async_trait]
+pub trait IndexBatch {
+    /// Applies a mix of upload, merge and delete actions, split into as many requests as the
+    /// service limits require. Failures of individual documents are reported per key rather
+    /// than as an error, so that callers can retry just those keys.
+    async fn index_batch(
+        &self,
+        actions: Vec<IndexBatchAction>,
+    ) -> Result<AzureIndexChangedResults, SearchClientError>;
+}
+
+#[async_trait]
+impl IndexBatch for AzureSearchClient {
+    async fn index_batch(
+        &self,
+        actions: Vec<IndexBatchAction>,
+    ) -> Result<AzureIndexChangedResults, SearchClientError> {
+        let mut results = vec![];
+        for chunk in chunk_index_actions(actions, MAX_INDEX_BATCH_SIZE, MAX_INDEX_BATCH_BYTES) {
+            let chunk_results = self.update_index(&chunk).await?;
+            results.extend(chunk_results.value);
+        }
+
+        Ok(AzureIndexChangedResults::from_results(results))
+    }
 }
 
-async fn update_index<T>(
-    key_values: T,
+// A single document that failed to index comes back as a 207 with its error in the body.
+fn ensure_indexed(
+    results: AzureIndexChangedResults,
+) -> Result<AzureIndexChangedResults, SearchClientError> {
+    if let Some(failed) = results.failed().next() {
+        return Err(SearchClientError::UnexpectedStatus {
+            status: reqwest::StatusCode::MULTI_STATUS,
+            message: failed.error_message.clone().unwrap_or_default(),
+        });
+    }
+
+    Ok(results)
+}
+
+fn build_index_request<T>(
+    values: &[T],
     client: &reqwest::Client,
     config: &AzureConfig,
-) -> Result<AzureIndexChangedResults, anyhow::Error>
+) -> Result<reqwest::Request, reqwest::Error>
 where
-    T: Serialize + Sized + Debug,
+    T: Serialize,
 {
-    let base_url = format!(
-        "https://{search_service}.search.windows.net/indexes/{search_index}/docs/index",
-        search_service = config.search_service,
-        search_index = config.search_index
-    );
+    let base_url = config.docs_url("/index");
 
     let mut body = HashMap::new();
-    body.insert("value", [key_values]);
+    body.insert("value", values);
 
-    let req = client
+    client
         .post(&base_url)
         .query(&[("api-version", &config.api_version)])
-        .header("api-key", &config.api_key)
         .header("Content-Type", "application/json")
         .json(&body)
-        .build()?;
-
-    tracing::debug!("\nBody: {:?}", &body);
-    tracing::debug!("\nRequest: {:?}", &req);
-    tracing::debug!("\nRequesting from URL: {}", &req.url());
-
-    let h = client.execute(req).await?;
+        .build()
+}
 
-    if h.status() == reqwest::StatusCode::OK {
-        h.json::<AzureIndexChangedResults>()
-            .await
-            .map_err(|e| anyhow::anyhow!(e))
-    } else {
-        let error_message = h.text().await?;
-        Err(anyhow::anyhow!(error_message))
+fn chunk_index_actions(
+    actions: Vec<IndexBatchAction>,
+    max_size: usize,
+    max_bytes: usize,
+) -> Vec<Vec<IndexBatchAction>> {
+    let mut chunks = vec![];
+    let mut chunk = vec![];
+    let mut chunk_bytes = 0;
+
+    for action in actions {
+        let bytes = serde_json::to_vec(&action).map_or(0, |json| json.len());
+        if !chunk.is_empty() && (chunk.len() == max_size || chunk_bytes + bytes > max_bytes) {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_bytes = 0;
+        }
+        chunk_bytes += bytes;
+        chunk.push(action);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
     }
+
+    chunks
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::filter::field;
     use pretty_assertions::assert_eq;
+    #[test]
+    fn backends_are_named_exactly() {
+        assert_eq!("azure".parse::<Backend>(), Ok(Backend::Azure));
+        assert_eq!("in-memory".parse::<Backend>(), Ok(Backend::InMemory));
+    }
+
+    #[test]
+    fn misspelt_backends_are_rejected() {
+        assert_eq!(
+            "inmemory".parse::<Backend>(),
+            Err(
+                r#"SEARCH_CLIENT_BACKEND must be "azure" or "in-memory", not "inmemory""#
+                    .to_string()
+            )
+        );
+    }
 
     fn given_we_have_a_search_client() -> reqwest::Client {
         reqwest::Client::new()
@@ -554,12 +882,11 @@ mod test {
 
     fn given_we_have_a_config() -> AzureConfig {
         AzureConfig {
-            api_key: "api_key".to_string(),
             search_index: "search_index".to_string(),
-            search_service: "search_service".to_string(),
+            base_url: "https://search_service.search.windows.net".to_string(),
             api_version: "api_version".to_string(),
             search_exactness_boost: "4".to_string(),
-            search_fuzziness: "1".to_string(),
+            fuzziness: FuzzinessRules::default(),
         }
     }
 
@@ -568,7 +895,7 @@ mod test {
         search_term: String,
         config: AzureConfig,
     ) -> Result<reqwest::Request, reqwest::Error> {
-        build_search(&search_term, None, None, None, &client, &config)
+        build_search(&SearchOptions::new(&search_term), &client, &config)
     }
 
     fn then_search_url_without_pagination_is_as_expected(
@@ -591,7 +918,7 @@ mod test {
         if let Ok(actual) = actual_result {
             let actual = actual.url().to_string();
             // Decoded search term: (Something~1+||+Something^4)+(challenging~1+||+challenging^4)+(and~1+||+and^4)+(with~1+||+with^4)+(forbidden~1+||+forbidden^4)+(symbols~1+||+symbols^4)+(\\*\\!~1+||+\\*\\!^4)+(\\(\\)~1+||+\\(\\)^4)+(or~1+||+or^4)+(%~1+||+%^4)+(keywords~1+||+keywords^4)+(not~1+||+not^4)+(PL123451234~1+||+PL123451234^4)
-            let expected = "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&highlight=content&queryType=full&search=%28Something%7E1+%7C%7C+Something%5E4%29+%28challenging%7E1+%7C%7C+challenging%5E4%29+%28and%7E1+%7C%7C+and%5E4%29+%28with%7E1+%7C%7C+with%5E4%29+%28forbidden%7E1+%7C%7C+forbidden%5E4%29+%28symbols%7E1+%7C%7C+symbols%5E4%29+%28%5C*%5C%21%7E1+%7C%7C+%5C*%5C%21%5E4%29+%28%5C%28%5C%29%7E1+%7C%7C+%5C%28%5C%29%5E4%29+%28or%7E1+%7C%7C+or%5E4%29+%28%25%7E1+%7C%7C+%25%5E4%29+%28keywords%7E1+%7C%7C+keywords%5E4%29+%28not%7E1+%7C%7C+not%5E4%29+%28PL123451234%7E1+%7C%7C+PL123451234%5E4%29&scoringProfile=preferKeywords&searchMode=all&%24count=false"
+            let expected = "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&highlight=content&queryType=full&search=%28Something%7E1+%7C%7C+Something%5E4%29+%28challenging%7E1+%7C%7C+challenging%5E4%29+and+%28with%7E1+%7C%7C+with%5E4%29+%28forbidden%7E1+%7C%7C+forbidden%5E4%29+%28symbols%7E1+%7C%7C+symbols%5E4%29+%5C*%5C%21+%5C%28%5C%29+or+%25+%28keywords%7E1+%7C%7C+keywords%5E4%29+not+PL123451234&scoringProfile=preferKeywords&searchMode=all&%24count=false"
                 .to_string();
 
             assert_eq!(actual, expected);
@@ -624,13 +951,12 @@ mod test {
         config: AzureConfig,
     ) -> Result<reqwest::Request, reqwest::Error> {
         build_search(
-            &search_term,
-            Some(AzurePagination {
-                result_count: 10,
-                offset: 50,
-            }),
-            Some(true),
-            None,
+            &SearchOptions::new(&search_term)
+                .pagination(AzurePagination {
+                    result_count: 10,
+                    offset: 50,
+                })
+                .include_count(true),
             &client,
             &config,
         )
@@ -642,13 +968,17 @@ mod test {
         config: AzureConfig,
     ) -> Result<reqwest::Request, reqwest::Error> {
         build_search(
-            &search_term,
-            Some(AzurePagination {
-                result_count: 10,
-                offset: 50,
-            }),
-            Some(true),
-            Some("(my_cool_field eq 'my cool value' xor my_cool_field ne 'my uncool value')"),
+            &SearchOptions::new(&search_term)
+                .pagination(AzurePagination {
+                    result_count: 10,
+                    offset: 50,
+                })
+                .include_count(true)
+                .filter(
+                    field("my_cool_field")
+                        .eq("my cool value")
+                        .or(field("my_cool_field").ne("my uncool value")),
+                ),
             &client,
             &config,
         )
@@ -659,9 +989,7 @@ mod test {
         config: AzureConfig,
     ) -> Result<reqwest::Request, reqwest::Error> {
         build_facet_search(
-            &String::from("field"),
-            &String::from("I, IBUPROFEN"),
-            &String::from("eq"),
+            &field("field").any(|f| f.eq("I, IBUPROFEN")),
             &client,
             &config,
         )
@@ -686,7 +1014,7 @@ mod test {
     ) {
         if let Ok(actual) = actual_result {
             let actual = actual.url().to_string();
-            let expected = "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&highlight=content&queryType=full&search=&scoringProfile=preferKeywords&searchMode=all&%24count=true&%24filter=%28my_cool_field+eq+%27my+cool+value%27+xor+my_cool_field+ne+%27my+uncool+value%27%29&%24top=10&%24skip=50"
+            let expected = "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&highlight=content&queryType=full&search=&scoringProfile=preferKeywords&searchMode=all&%24count=true&%24filter=%28my_cool_field+eq+%27my+cool+value%27+or+my_cool_field+ne+%27my+uncool+value%27%29&%24top=10&%24skip=50"
                 .to_string();
 
             assert_eq!(actual, expected);
@@ -700,7 +1028,7 @@ mod test {
     ) {
         if let Ok(actual) = actual_result {
             let actual = actual.url().to_string();
-            let expected = "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&highlight=content&queryType=full&search=%28cool%7E1+%7C%7C+cool%5E4%29+%28beans%7E1+%7C%7C+beans%5E4%29&scoringProfile=preferKeywords&searchMode=all&%24count=true&%24filter=%28my_cool_field+eq+%27my+cool+value%27+xor+my_cool_field+ne+%27my+uncool+value%27%29&%24top=10&%24skip=50"
+            let expected = "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&highlight=content&queryType=full&search=%28cool%7E1+%7C%7C+cool%5E4%29+%28beans%7E1+%7C%7C+beans%5E4%29&scoringProfile=preferKeywords&searchMode=all&%24count=true&%24filter=%28my_cool_field+eq+%27my+cool+value%27+or+my_cool_field+ne+%27my+uncool+value%27%29&%24top=10&%24skip=50"
                 .to_string();
 
             assert_eq!(actual, expected);
@@ -760,30 +1088,101 @@ mod test {
         then_search_with_facets_and_filter_is_as_expected(actual);
     }
 
+    #[test]
+    fn test_build_search_with_all_options() {
+        let client = given_we_have_a_search_client();
+        let config = given_we_have_a_config();
+
+        let actual = build_search(
+            &SearchOptions::new("cool beans")
+                .query_type(QueryType::Simple)
+                .search_mode(SearchMode::Any)
+                .scoring_profile(None)
+                .highlight(&["content", "title"])
+                .highlight_tags("<b>", "</b>")
+                .facet("doc_type")
+                .order_by("created", SortOrder::Desc)
+                .order_by("title", SortOrder::Asc)
+                .select(&["title", "created"])
+                .top(5),
+            &client,
+            &config,
+        )
+        .unwrap()
+        .url()
+        .to_string();
+
+        assert_eq!(actual, "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&highlight=content%2Ctitle&queryType=simple&search=cool+beans&searchMode=any&%24count=false&facet=doc_type&%24orderby=created+desc%2Ctitle+asc&%24select=title%2Ccreated&highlightPreTag=%3Cb%3E&highlightPostTag=%3C%2Fb%3E&%24top=5");
+    }
+
+    #[test]
+    fn test_build_facet_search_with_options() {
+        let client = given_we_have_a_search_client();
+        let config = given_we_have_a_config();
+        let options = facet_options(
+            SearchOptions::new("")
+                .query_type(QueryType::Simple)
+                .filter(field("doc_type").eq("Spc"))
+                .facet("territory")
+                .facet(
+                    FacetSpec::new("matrices")
+                        .count(20)
+                        .sort_by(FacetSort::Count, SortOrder::Desc),
+                ),
+        );
+
+        let actual = build_search(&options, &client, &config)
+            .unwrap()
+            .url()
+            .to_string();
+
+        assert_eq!(actual, "https://search_service.search.windows.net/indexes/search_index/docs?api-version=api_version&queryType=simple&search=&scoringProfile=preferKeywords&searchMode=all&%24count=true&%24filter=doc_type+eq+%27Spc%27&facet=territory&facet=matrices%2Ccount%3A20%2Csort%3Acount&%24top=0");
+    }
+
+    #[test]
+    fn test_build_suggest_request() {
+        let client = given_we_have_a_search_client();
+        let config = given_we_have_a_config();
+
+        let actual = build_suggest_request("ibupro", 5, &client, &config)
+            .unwrap()
+            .url()
+            .to_string();
+
+        assert_eq!(actual, "https://search_service.search.windows.net/indexes/search_index/docs/suggest?api-version=api_version&search=ibupro&suggesterName=azure-suggester&%24top=5");
+    }
+
+    #[test]
+    fn test_build_autocomplete_request() {
+        let client = given_we_have_a_search_client();
+        let config = given_we_have_a_config();
+
+        let actual = build_autocomplete_request("ibupro", 5, &client, &config)
+            .unwrap()
+            .url()
+            .to_string();
+
+        assert_eq!(actual, "https://search_service.search.windows.net/indexes/search_index/docs/autocomplete?api-version=api_version&search=ibupro&suggesterName=azure-suggester&autocompleteMode=oneTerm&%24top=5");
+    }
+
     #[test]
     fn test_build_filter_by_collection_request() {
         let client = reqwest::Client::new();
         let config = AzureConfig {
-            search_service: "my_cool_service".to_string(),
+            base_url: "https://my_cool_service.search.windows.net".to_string(),
             search_index: "my_cool_search_index".to_string(),
-            api_key: "my_cool_api_key".to_string(),
             api_version: "2017-11-11".to_string(),
-            search_fuzziness: "1".to_string(),
+            fuzziness: FuzzinessRules::default(),
             search_exactness_boost: "4".to_string(),
         };
 
-        let req = build_filter_by_collection_request(
-            &"my_cool_field".to_string(),
-            &"my cool value".to_string(),
-            &"cooler_than".to_string(),
+        let req = build_filter_request(
+            &field("my_cool_field").any(|f| f.eq("my cool value")),
             &client,
             &config,
         )
         .unwrap();
 
-        let api_key = req.headers().get("api-key").unwrap().to_str().unwrap();
-        assert_eq!(api_key, config.api_key);
-
         let url = req.url();
         assert_eq!(url.scheme(), "https");
         assert_eq!(url.host_str(), Some("my_cool_service.search.windows.net"));
@@ -802,7 +1201,110 @@ mod test {
                 .find(|query_pair| query_pair.0 == "$filter")
                 .unwrap()
                 .1,
-            "my_cool_field/any(f: f cooler_than 'my cool value')"
+            "my_cool_field/any(f: f eq 'my cool value')"
+        );
+    }
+
+    #[test]
+    fn test_build_filter_request_escapes_values() {
+        let client = given_we_have_a_search_client();
+        let config = given_we_have_a_config();
+
+        let req = build_filter_request(
+            &field("product_name").eq("ST JOHN'S WORT"),
+            &client,
+            &config,
+        )
+        .unwrap();
+
+        let filter = req
+            .url()
+            .query_pairs()
+            .find(|query_pair| query_pair.0 == "$filter")
+            .unwrap()
+            .1
+            .to_string();
+        assert_eq!(filter, "product_name eq 'ST JOHN''S WORT'");
+    }
+
+    #[test]
+    fn test_build_get_document_request_escapes_the_key() {
+        let client = given_we_have_a_search_client();
+        let config = given_we_have_a_config();
+
+        let req = build_get_document_request("a/b c==", &client, &config).unwrap();
+
+        assert_eq!(
+            req.url().as_str(),
+            "https://search_service.search.windows.net/indexes/search_index/docs/a%2Fb%20c==?api-version=api_version"
+        );
+    }
+
+    fn given_an_index_action(key: &str) -> IndexBatchAction {
+        IndexBatchAction::delete("metadata_storage_name", key)
+    }
+
+    #[test]
+    fn test_chunk_index_actions_by_count() {
+        let actions = (0..5)
+            .map(|key| given_an_index_action(&key.to_string()))
+            .collect();
+
+        let chunks = chunk_index_actions(actions, 2, MAX_INDEX_BATCH_BYTES);
+
+        let sizes = chunks.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_chunk_index_actions_by_size() {
+        let action_bytes = serde_json::to_vec(&given_an_index_action("0"))
+            .unwrap()
+            .len();
+        let actions = (0..5)
+            .map(|key| given_an_index_action(&key.to_string()))
+            .collect();
+
+        let chunks = chunk_index_actions(actions, MAX_INDEX_BATCH_SIZE, action_bytes * 3);
+
+        let sizes = chunks.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![3, 2]);
+    }
+
+    #[test]
+    fn test_build_index_request() {
+        let client = given_we_have_a_search_client();
+        let config = given_we_have_a_config();
+
+        let req = build_index_request(&[given_an_index_action("abc")], &client, &config).unwrap();
+
+        assert_eq!(
+            req.url().to_string(),
+            "https://search_service.search.windows.net/indexes/search_index/docs/index?api-version=api_version"
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(req.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "value": [{ "@search.action": "delete", "metadata_storage_name": "abc" }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_requests_can_go_to_a_local_http_base_url() {
+        let client = given_we_have_a_search_client();
+        let config = AzureConfig {
+            base_url: "http://localhost:8080/".to_string(),
+            ..given_we_have_a_config()
+        };
+
+        let req = build_suggest_request("ibupro", 5, &client, &config).unwrap();
+
+        assert_eq!(
+            req.url().to_string(),
+            "http://localhost:8080/indexes/search_index/docs/suggest?api-version=api_version&search=ibupro&suggesterName=azure-suggester&%24top=5"
         );
     }
 }
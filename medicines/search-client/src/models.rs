@@ -1,9 +1,13 @@
 pub use crate::document_type::{DocTypeParseError, DocumentType};
+pub use crate::product_licence::{LicencePrefix, ProductLicence, ProductLicenceParseError};
 pub use crate::territory_type::{TerritoryType, TerritoryTypeParseError};
 use chrono::{SecondsFormat, Utc};
 use core::fmt::Debug;
 use serde_derive::{Deserialize, Serialize};
 use std::clone::Clone;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct AzureHighlight {
@@ -27,12 +31,19 @@ pub struct IndexResult {
     pub release_state: Option<String>,
     pub rev_label: Option<String>,
     pub suggestions: Vec<String>,
-    #[serde(rename = "@search.score")]
+    // Documents fetched by key aren't scored.
+    #[serde(rename = "@search.score", default)]
     pub score: f32,
     #[serde(rename = "@search.highlights")]
     pub highlights: Option<AzureHighlight>,
 }
 
+// Just the documents from a search response, for when nothing else in it is wanted.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Documents<T> {
+    pub(crate) value: Vec<T>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct IndexResults {
     #[serde(rename = "value")]
@@ -84,7 +95,7 @@ pub struct FacetResult {
     pub facets: Vec<Facet>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct FacetResults {
     #[serde(rename = "value")]
     pub search_results: Vec<IndexResult>,
@@ -94,6 +105,70 @@ pub struct FacetResults {
     pub context: String,
 }
 
+/// A faceted value: text for string fields and dates, a number for numeric fields.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum FacetValue {
+    Text(String),
+    Number(f64),
+}
+
+impl Display for FacetValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FacetValue::Text(text) => write!(f, "{}", text),
+            FacetValue::Number(number) => write!(f, "{}", number),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct FacetCount {
+    pub value: FacetValue,
+    pub count: i32,
+}
+
+/// Facet counts keyed by field, with the number of documents they were counted over.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FacetQueryResults {
+    #[serde(rename = "@search.facets", default)]
+    pub facets: BTreeMap<String, Vec<FacetCount>>,
+    #[serde(rename = "@odata.count")]
+    pub count: Option<i32>,
+}
+
+impl FacetQueryResults {
+    /// The counts for `field`, or none if it wasn't requested.
+    pub fn field(&self, field: &str) -> &[FacetCount] {
+        self.facets.get(field).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SuggestResult {
+    #[serde(rename = "@search.text")]
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SuggestResults {
+    #[serde(rename = "value")]
+    pub suggestions: Vec<SuggestResult>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AutocompleteResult {
+    pub text: String,
+    #[serde(rename = "queryPlusText")]
+    pub query_plus_text: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AutocompleteResults {
+    #[serde(rename = "value")]
+    pub completions: Vec<AutocompleteResult>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AzureIndexChangedResults {
     pub value: Vec<AzureIndexChangedResult>,
@@ -103,11 +178,19 @@ pub struct AzureIndexChangedResults {
 
 impl AzureIndexChangedResults {
     pub fn new(index_changed_result: AzureIndexChangedResult) -> AzureIndexChangedResults {
+        AzureIndexChangedResults::from_results(vec![index_changed_result])
+    }
+
+    pub fn from_results(index_changed_results: Vec<AzureIndexChangedResult>) -> Self {
         AzureIndexChangedResults {
             context: "context".to_string(),
-            value: vec![index_changed_result],
+            value: index_changed_results,
         }
     }
+
+    pub fn failed(&self) -> impl Iterator<Item = &AzureIndexChangedResult> {
+        self.value.iter().filter(|result| !result.status)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -120,6 +203,46 @@ pub struct AzureIndexChangedResult {
     pub status_code: u16,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IndexAction {
+    Upload,
+    Merge,
+    MergeOrUpload,
+    Delete,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexBatchAction {
+    #[serde(rename = "@search.action")]
+    pub action: IndexAction,
+    #[serde(flatten)]
+    pub document: serde_json::Map<String, serde_json::Value>,
+}
+
+impl IndexBatchAction {
+    pub fn new<T>(action: IndexAction, document: &T) -> Result<Self, serde_json::Error>
+    where
+        T: serde::Serialize,
+    {
+        match serde_json::to_value(document)? {
+            serde_json::Value::Object(document) => Ok(Self { action, document }),
+            _ => Err(serde::ser::Error::custom(
+                "Index documents must serialize to a JSON object",
+            )),
+        }
+    }
+
+    pub fn delete(key_name: &str, key: &str) -> Self {
+        let mut document = serde_json::Map::new();
+        document.insert(key_name.to_string(), key.into());
+        Self {
+            action: IndexAction::Delete,
+            document,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct IndexEntry {
     pub content: String,
@@ -146,11 +269,38 @@ pub struct IndexEntry {
     pub facets: Vec<String>,
 }
 
+/// The index record has a `doc_type` this version doesn't recognise. It was read as `Unknown`,
+/// so writing it back would overwrite the stored type.
+#[derive(Debug, Clone)]
+pub struct UnrecognisedDocumentType {
+    pub metadata_storage_name: String,
+}
+
+impl Display for UnrecognisedDocumentType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Document {} has an unrecognised doc_type, which would be lost by writing it back to the index",
+            self.metadata_storage_name
+        )
+    }
+}
+
+impl std::error::Error for UnrecognisedDocumentType {}
+
 // The IndexResult model does not contain all of the information we want in the index,
 // however, the automatic index rebuild will populate the missing information.
-impl From<IndexResult> for IndexEntry {
-    fn from(res: IndexResult) -> Self {
-        Self {
+impl TryFrom<IndexResult> for IndexEntry {
+    type Error = UnrecognisedDocumentType;
+
+    fn try_from(res: IndexResult) -> Result<Self, Self::Error> {
+        if res.doc_type == DocumentType::Unknown {
+            return Err(UnrecognisedDocumentType {
+                metadata_storage_name: res.metadata_storage_name,
+            });
+        }
+
+        Ok(Self {
             content: "Content not yet available".to_owned(),
             rev_label: match res.rev_label {
                 Some(rl) => rl,
@@ -188,7 +338,7 @@ impl From<IndexResult> for IndexEntry {
             metadata_storage_path: res.metadata_storage_path,
             metadata_content_type: String::default(),
             metadata_language: String::default(),
-        }
+        })
     }
 }
 
@@ -217,6 +367,37 @@ mod tests {
         assert_eq!(results.search_results[3].doc_type, DocumentType::Spc);
     }
 
+    fn given_an_index_record_with_doc_type(doc_type: &str) -> IndexResult {
+        serde_json::from_value(serde_json::json!({
+            "doc_type": doc_type,
+            "file_name": "CON123",
+            "metadata_storage_name": "abc",
+            "metadata_storage_path": "https://example.com/docs/abc",
+            "substance_name": [],
+            "title": "spc-doc_PL 27041-0012.pdf",
+            "facets": [],
+            "metadata_storage_size": 100,
+            "suggestions": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn index_records_with_a_known_doc_type_can_be_written_back() {
+        let entry = IndexEntry::try_from(given_an_index_record_with_doc_type("Pil")).unwrap();
+
+        assert_eq!(entry.doc_type, DocumentType::Pil);
+        assert_eq!(entry.metadata_storage_name, "abc");
+    }
+
+    #[test]
+    fn index_records_with_an_unrecognised_doc_type_are_not_written_back() {
+        let error =
+            IndexEntry::try_from(given_an_index_record_with_doc_type("Leaflet")).unwrap_err();
+
+        assert_eq!(error.metadata_storage_name, "abc");
+    }
+
     #[test]
     fn index_results_deserializes_correctly() {
         let json = "{\"@odata.context\":\"https://mhraproductsproduction.search.windows.net/indexes('products-index')/$metadata#docs(*)\",\"value\":[],\"@search.facets\":{\"facets\":[
@@ -234,4 +415,55 @@ mod tests {
         );
         assert_eq!(results.facet_results.facets[2].count, 6);
     }
+
+    #[test]
+    fn facet_query_results_are_keyed_by_field() {
+        let json = r#"{"@odata.count":12,"value":[],"@search.facets":{
+            "doc_type":[{"value":"Spc","count":7},{"value":"Pil","count":5}],
+            "metadata_storage_size":[{"value":0,"count":4},{"value":100000,"count":8}]
+        }}"#;
+
+        let results: FacetQueryResults = serde_json::from_str(json).unwrap();
+
+        assert_eq!(results.count, Some(12));
+        assert_eq!(
+            results.field("doc_type")[0],
+            FacetCount {
+                value: FacetValue::Text("Spc".to_string()),
+                count: 7
+            }
+        );
+        assert_eq!(
+            results.field("metadata_storage_size")[1].value,
+            FacetValue::Number(100000.0)
+        );
+        assert!(results.field("territory").is_empty());
+    }
+
+    #[test]
+    fn index_batch_actions_serialize_with_the_action_inline() {
+        let mut document = serde_json::Map::new();
+        document.insert("metadata_storage_name".into(), "abc".into());
+        document.insert("release_state".into(), "N".into());
+        let merge = IndexBatchAction {
+            action: IndexAction::MergeOrUpload,
+            document,
+        };
+
+        assert_eq!(
+            serde_json::to_value(merge).unwrap(),
+            serde_json::json!({
+                "@search.action": "mergeOrUpload",
+                "metadata_storage_name": "abc",
+                "release_state": "N"
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(IndexBatchAction::delete("metadata_storage_name", "abc")).unwrap(),
+            serde_json::json!({
+                "@search.action": "delete",
+                "metadata_storage_name": "abc"
+            })
+        );
+    }
 }
@@ -0,0 +1,341 @@
+use crate::territory_type::TerritoryType;
+use regex::{Captures, Regex};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Longer prefixes first, so that `PLGB` isn't read as `PL` followed by junk.
+const PREFIXES: &str = "PLGB|PLNI|PLPI|PL|THRGB|THRNI|THR|NRGB|NRNI|NR";
+const SEPARATORS: &str = r"[\s/_-]*";
+
+lazy_static! {
+    static ref RE_PRODUCT_LICENCE: Regex = Regex::new(&format!(
+        r"(?i)(?P<prefix>{prefixes}){separators}(?P<company>\d{{5}}){separators}(?P<product>\d{{4}})",
+        prefixes = PREFIXES,
+        separators = SEPARATORS
+    ))
+    .unwrap();
+    static ref RE_PRODUCT_LICENCE_OR_NUMBER: Regex = Regex::new(&format!(
+        r"(?i)(?:(?P<prefix>{prefixes})|\b){separators}(?P<company>\d{{5}}){separators}(?P<product>\d{{4}})",
+        prefixes = PREFIXES,
+        separators = SEPARATORS
+    ))
+    .unwrap();
+    static ref RE_WHOLE_PRODUCT_LICENCE: Regex =
+        Regex::new(&format!(r"^{}$", RE_PRODUCT_LICENCE.as_str())).unwrap();
+}
+
+/// The kind of licence, which for newer licences also says where it applies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LicencePrefix {
+    Pl,
+    Plgb,
+    Plni,
+    /// Parallel import.
+    Plpi,
+    /// Traditional herbal registration.
+    Thr,
+    Thrgb,
+    Thrni,
+    /// Homeopathic national rules scheme.
+    Nr,
+    Nrgb,
+    Nrni,
+}
+
+impl LicencePrefix {
+    /// Where a licence with this prefix applies, if the prefix says.
+    pub fn territory(self) -> Option<TerritoryType> {
+        match self {
+            Self::Pl | Self::Plpi => Some(TerritoryType::UK),
+            Self::Plgb | Self::Thrgb | Self::Nrgb => Some(TerritoryType::GB),
+            Self::Plni | Self::Thrni | Self::Nrni => Some(TerritoryType::NI),
+            Self::Thr | Self::Nr => None,
+        }
+    }
+}
+
+impl FromStr for LicencePrefix {
+    type Err = ProductLicenceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "PL" => Ok(Self::Pl),
+            "PLGB" => Ok(Self::Plgb),
+            "PLNI" => Ok(Self::Plni),
+            "PLPI" => Ok(Self::Plpi),
+            "THR" => Ok(Self::Thr),
+            "THRGB" => Ok(Self::Thrgb),
+            "THRNI" => Ok(Self::Thrni),
+            "NR" => Ok(Self::Nr),
+            "NRGB" => Ok(Self::Nrgb),
+            "NRNI" => Ok(Self::Nrni),
+            _ => Err(ProductLicenceParseError {
+                source: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl Display for LicencePrefix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pl => write!(f, "PL"),
+            Self::Plgb => write!(f, "PLGB"),
+            Self::Plni => write!(f, "PLNI"),
+            Self::Plpi => write!(f, "PLPI"),
+            Self::Thr => write!(f, "THR"),
+            Self::Thrgb => write!(f, "THRGB"),
+            Self::Thrni => write!(f, "THRNI"),
+            Self::Nr => write!(f, "NR"),
+            Self::Nrgb => write!(f, "NRGB"),
+            Self::Nrni => write!(f, "NRNI"),
+        }
+    }
+}
+
+/// A product licence (marketing authorisation) number such as `PL 12345/1234`: a prefix, the
+/// five digit number of the company holding the licence, and a four digit product number.
+///
+/// Licences are written many ways (`pl12345/1234`, `PL-12345-1234`, `PL_12345_1234`...) but are
+/// always indexed, searched for and serialised in the canonical form, `PL123451234`.
+///
+/// ```
+/// use search_client::models::{LicencePrefix, ProductLicence, TerritoryType};
+///
+/// let licence: ProductLicence = "plgb 12345-0123".parse().unwrap();
+///
+/// assert_eq!(licence.prefix, LicencePrefix::Plgb);
+/// assert_eq!(licence.canonical(), "PLGB123450123");
+/// assert_eq!(licence.to_string(), "PLGB 12345/0123");
+/// assert_eq!(licence.territory(), Some(TerritoryType::GB));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProductLicence {
+    pub prefix: LicencePrefix,
+    pub company_number: u32,
+    pub product_number: u32,
+}
+
+impl ProductLicence {
+    pub fn new(prefix: LicencePrefix, company_number: u32, product_number: u32) -> Self {
+        Self {
+            prefix,
+            company_number,
+            product_number,
+        }
+    }
+
+    /// The form used in the index and in searches, e.g. `PL123451234`.
+    pub fn canonical(&self) -> String {
+        format!(
+            "{}{:05}{:04}",
+            self.prefix, self.company_number, self.product_number
+        )
+    }
+
+    pub fn territory(&self) -> Option<TerritoryType> {
+        self.prefix.territory()
+    }
+
+    /// Every licence number in `text`, in the order they appear.
+    pub fn find_all(text: &str) -> Vec<Self> {
+        RE_PRODUCT_LICENCE
+            .captures_iter(text)
+            .filter_map(|captures| from_captures(&captures, None))
+            .collect()
+    }
+
+    /// Like `find_all`, but also reads bare `12345/1234` numbers as having `default_prefix`.
+    /// Only use this for text that holds nothing but licence numbers, where the prefix is
+    /// often left off.
+    pub fn find_all_with_default(text: &str, default_prefix: LicencePrefix) -> Vec<Self> {
+        RE_PRODUCT_LICENCE_OR_NUMBER
+            .captures_iter(text)
+            .filter_map(|captures| from_captures(&captures, Some(default_prefix)))
+            .collect()
+    }
+
+    /// Rewrites every licence number in `text` in its canonical form.
+    pub fn normalize_all(text: &str) -> String {
+        RE_PRODUCT_LICENCE
+            .replace_all(text, |captures: &Captures| {
+                from_captures(captures, None)
+                    .map(|licence| licence.canonical())
+                    .unwrap_or_else(|| captures[0].to_string())
+            })
+            .to_string()
+    }
+}
+
+fn from_captures(
+    captures: &Captures,
+    default_prefix: Option<LicencePrefix>,
+) -> Option<ProductLicence> {
+    let prefix = match captures.name("prefix") {
+        Some(prefix) => prefix.as_str().parse().ok()?,
+        None => default_prefix?,
+    };
+    Some(ProductLicence::new(
+        prefix,
+        captures["company"].parse().ok()?,
+        captures["product"].parse().ok()?,
+    ))
+}
+
+impl FromStr for ProductLicence {
+    type Err = ProductLicenceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RE_WHOLE_PRODUCT_LICENCE
+            .captures(s.trim())
+            .and_then(|captures| from_captures(&captures, None))
+            .ok_or_else(|| ProductLicenceParseError {
+                source: s.to_string(),
+            })
+    }
+}
+
+impl Display for ProductLicence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:05}/{:04}",
+            self.prefix, self.company_number, self.product_number
+        )
+    }
+}
+
+impl Serialize for ProductLicence {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.canonical())
+    }
+}
+
+impl<'de> Deserialize<'de> for ProductLicence {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProductLicenceParseError {
+    source: String,
+}
+
+impl Display for ProductLicenceParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Could not parse ProductLicence from string: {}",
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for ProductLicenceParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("PL 12345/1234", "PL123451234")]
+    #[test_case("PL12345/1234", "PL123451234")]
+    #[test_case("pl/23456/1234", "PL234561234")]
+    #[test_case("PL-34567-1234", "PL345671234")]
+    #[test_case("PL_45678_1234", "PL456781234")]
+    #[test_case(" PL123451234 ", "PL123451234")]
+    #[test_case("PLGB 12345/1234", "PLGB123451234")]
+    #[test_case("PLNI 12345/1234", "PLNI123451234")]
+    #[test_case("PLPI 12345/1234", "PLPI123451234")]
+    #[test_case("THR 12345/1234", "THR123451234")]
+    #[test_case("thrgb 12345/1234", "THRGB123451234")]
+    #[test_case("NRNI 00012/0001", "NRNI000120001")]
+    fn parses_licences_written_any_way(input: &str, canonical: &str) {
+        let licence: ProductLicence = input.parse().unwrap();
+        assert_eq!(licence.canonical(), canonical);
+    }
+
+    #[test_case("NEW 12345/1234")]
+    #[test_case("12345/1234")]
+    #[test_case("PL 1234/1234")]
+    #[test_case("PL 12345/1234 and more")]
+    fn rejects_anything_else(input: &str) {
+        assert!(input.parse::<ProductLicence>().is_err());
+    }
+
+    #[test_case("PL123451234", Some(TerritoryType::UK))]
+    #[test_case("PLPI123451234", Some(TerritoryType::UK))]
+    #[test_case("PLGB123451234", Some(TerritoryType::GB))]
+    #[test_case("PLNI123451234", Some(TerritoryType::NI))]
+    #[test_case("THRGB123451234", Some(TerritoryType::GB))]
+    #[test_case("THRNI123451234", Some(TerritoryType::NI))]
+    #[test_case("NRGB123451234", Some(TerritoryType::GB))]
+    #[test_case("NRNI123451234", Some(TerritoryType::NI))]
+    #[test_case("THR123451234", None)]
+    #[test_case("NR123451234", None)]
+    fn derives_territory_from_prefix(input: &str, territory: Option<TerritoryType>) {
+        assert_eq!(
+            input.parse::<ProductLicence>().unwrap().territory(),
+            territory
+        );
+    }
+
+    #[test]
+    fn finds_every_licence_in_text() {
+        let licences = ProductLicence::find_all(
+            "Paracetamol PL 12345/0010-0001, plgb_23456_0002 and NEW 34567/0003 or 45678/0004",
+        );
+
+        assert_eq!(
+            licences,
+            vec![
+                ProductLicence::new(LicencePrefix::Pl, 12345, 10),
+                ProductLicence::new(LicencePrefix::Plgb, 23456, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_bare_numbers_with_a_default_prefix() {
+        let licences = ProductLicence::find_all_with_default(
+            "12345/1234, PLGB 23456/2345, CON123456789",
+            LicencePrefix::Pl,
+        );
+
+        assert_eq!(
+            licences,
+            vec![
+                ProductLicence::new(LicencePrefix::Pl, 12345, 1234),
+                ProductLicence::new(LicencePrefix::Plgb, 23456, 2345),
+            ]
+        );
+    }
+
+    #[test_case("PRETEXT pl 12345/1234 POSTTEXT", "PRETEXT PL123451234 POSTTEXT")]
+    #[test_case("PLGB 12345/1234 PL-23456-1234", "PLGB123451234 PL234561234")]
+    #[test_case("NEW 12345/1234", "NEW 12345/1234")]
+    fn normalizes_licences_in_text(input: &str, expected: &str) {
+        assert_eq!(ProductLicence::normalize_all(input), expected);
+    }
+
+    #[test]
+    fn serialises_in_canonical_form() {
+        let licences: Vec<ProductLicence> =
+            serde_json::from_str(r#"["PL 12345/1234", "THRGB123450001"]"#).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&licences).unwrap(),
+            r#"["PL123451234","THRGB123450001"]"#
+        );
+        assert!(serde_json::from_str::<ProductLicence>(r#""PL 123""#).is_err());
+    }
+}
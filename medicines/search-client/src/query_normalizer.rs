@@ -1,37 +1,5 @@
-use regex::Captures;
-
 use regex::Regex;
 
-pub fn normalize_product_licences(search_term: &str) -> String {
-    lazy_static! {
-        static ref RE_PRODUCT_LICENCE: Regex = Regex::new(r"(?i)(?P<prefix>PL|PLGB|PLNI|PLPI|THR|THRGB|THRNI|NR|NRGB|NRNI)(\s+|/|_|-)*(?P<fivenumbers>\d{5})(\s+|/|_|-)*(?P<fournumbers>\d{4})").unwrap();
-    }
-
-    RE_PRODUCT_LICENCE
-        .replace_all(&search_term, |caps: &Captures| {
-            format!(
-                "{prefix}{five_numbers}{four_numbers}",
-                prefix = caps[1].to_uppercase(),
-                five_numbers = &caps[3],
-                four_numbers = &caps[5]
-            )
-        })
-        .to_string()
-}
-
-pub fn prefer_exact_match_but_support_fuzzy_match(
-    word: &str,
-    search_word_fuzziness: &str,
-    search_exactness_boost: &str,
-) -> String {
-    format!(
-        "({word}~{search_word_fuzziness} || {word}^{search_exactness_boost})",
-        word = word,
-        search_word_fuzziness = search_word_fuzziness,
-        search_exactness_boost = search_exactness_boost
-    )
-}
-
 pub fn escape_special_characters(search_term: &str) -> String {
     lazy_static! {
         static ref RE_SPECIAL_CHARACTERS: Regex =
@@ -42,16 +10,38 @@ pub fn escape_special_characters(search_term: &str) -> String {
         .to_string()
 }
 
-pub fn escape_special_words(search_term: &str) -> String {
+/// Joins amounts to their units and writes the units one way, so that `200 mg`, `200MG` and
+/// `200 milligrams` all become `200mg`, and `250 mg / 5 ml` becomes `250mg/5ml`.
+pub fn normalize_strengths(text: &str) -> String {
     lazy_static! {
-        static ref RE_SPECIAL_WORDS: Regex =
-            Regex::new(r#"([^a-zA-Z])(?P<special_words>AND|OR|NOT)([^a-zA-Z])"#).unwrap();
+        static ref RE_STRENGTH: Regex = Regex::new(
+            r"(?i)\b(?P<amount>\d{1,3}(?:,\d{3})+|\d+(?:\.\d+)?)\s*(?P<unit>micrograms?|mcg|µg|ug|milligrams?|mg|grams?|g|millilit(?:re|er)s?|ml|i\.?u\.?|%)(?P<end>[^\p{L}\p{N}]|$)"
+        )
+        .unwrap();
+        static ref RE_PER: Regex =
+            Regex::new(r"(?i)(?P<strength>\d(?:mcg|mg|g|ml|iu|%))\s*/\s*(?P<per>\d|ml\b|dose\b)")
+                .unwrap();
     }
-    RE_SPECIAL_WORDS
-        .replace_all(search_term, |caps: &Captures| {
-            format!("{}{}{}", &caps[1], &caps[2].to_lowercase(), &caps[3])
-        })
-        .to_string()
+
+    let joined = RE_STRENGTH.replace_all(text, |captures: &regex::Captures| {
+        let unit = captures["unit"].to_lowercase();
+        let unit = match unit.trim_end_matches('s') {
+            "microgram" | "µg" | "ug" => "mcg",
+            "milligram" => "mg",
+            "gram" => "g",
+            "millilitre" | "milliliter" => "ml",
+            "i.u." | "i.u" | "iu." => "iu",
+            unit => unit,
+        };
+        format!(
+            "{}{}{}",
+            captures["amount"].replace(',', ""),
+            unit,
+            &captures["end"]
+        )
+    });
+
+    RE_PER.replace_all(&joined, "$strength/$per").to_string()
 }
 
 #[cfg(test)]
@@ -59,28 +49,32 @@ mod test {
     use super::*;
     use test_case::test_case;
 
-    #[test_case("PL 12345/1234", "PL123451234")]
-    #[test_case("THR 12345/1234", "THR123451234")]
-    #[test_case("PLGB 12345/1234", "PLGB123451234")]
-    #[test_case("PLNI 12345/1234", "PLNI123451234")]
-    #[test_case("PLPI 12345/1234", "PLPI123451234")]
-    #[test_case("NEW 12345/1234", "NEW 12345/1234")]
-    #[test_case("12345/1234", "12345/1234")]
-    #[test_case("PRETEXT PL 12345/1234 POSTTEXT", "PRETEXT PL123451234 POSTTEXT")]
-    #[test_case("PRETEXT pl 12345/1234", "PRETEXT PL123451234")]
-    #[test_case("PRETEXT plgb 12345/1234", "PRETEXT PLGB123451234")]
-    #[test_case("PL/23456/1234", "PL234561234")]
-    #[test_case("PL-34567-1234", "PL345671234")]
-    #[test_case("PL_45678_1234", "PL456781234")]
-    fn test_normalize_product_licences(input: &str, expected: &str) {
-        let result = normalize_product_licences(&input);
-        assert_eq!(result, expected);
-    }
-
-    #[test_case("ibuprofen", "(ibuprofen~1 || ibuprofen^4)")]
-    fn test_prefer_exact_match_but_support_fuzzy_match(input: &str, expected: &str) {
-        let result = prefer_exact_match_but_support_fuzzy_match(&input, "1", "4");
-        assert_eq!(result, expected);
+    #[test_case("IBUPROFEN 200MG TABLETS", "IBUPROFEN 200mg TABLETS")]
+    #[test_case(
+        "Ibuprofen 200 mg Film-coated Tablets",
+        "Ibuprofen 200mg Film-coated Tablets"
+    )]
+    #[test_case(
+        "Amoxicillin 250 mg / 5 ml Powder for Oral Suspension",
+        "Amoxicillin 250mg/5ml Powder for Oral Suspension"
+    )]
+    #[test_case(
+        "AMOXICILLIN 250MG/5ML ORAL SUSPENSION",
+        "AMOXICILLIN 250mg/5ml ORAL SUSPENSION"
+    )]
+    #[test_case("Co-codamol 30 mg/500 mg Tablets", "Co-codamol 30mg/500mg Tablets")]
+    #[test_case(
+        "Salbutamol 100 micrograms/dose Pressurised Inhalation",
+        "Salbutamol 100mcg/dose Pressurised Inhalation"
+    )]
+    #[test_case("Hydrocortisone 1 % w/w Cream", "Hydrocortisone 1% w/w Cream")]
+    #[test_case("Colecalciferol 1,000 IU Capsules", "Colecalciferol 1000iu Capsules")]
+    #[test_case("Adrenaline 1 mg / ml (1 in 1000)", "Adrenaline 1mg/ml (1 in 1000)")]
+    #[test_case("Paracetamol 0.5 g Tablets", "Paracetamol 0.5g Tablets")]
+    #[test_case("PL 12345/0001 Gaviscon", "PL 12345/0001 Gaviscon"; "leaves other numbers alone")]
+    #[test_case("5 grams of 2 mgs", "5g of 2 mgs"; "only joins whole units")]
+    fn normalizes_strengths_in_product_names(input: &str, expected: &str) {
+        assert_eq!(normalize_strengths(input), expected);
     }
 
     //todo: encode for special characters ; / ? : @ = + &
@@ -91,12 +85,4 @@ mod test {
         let result = escape_special_characters(&input);
         assert_eq!(result, expected);
     }
-
-    #[test]
-    fn test_escape_special_words() {
-        let input = "this AND that OR something else NOT the other for grand cannot";
-        let expected = "this and that or something else not the other for grand cannot";
-        let result = escape_special_words(&input);
-        assert_eq!(result, expected);
-    }
 }
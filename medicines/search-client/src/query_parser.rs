@@ -0,0 +1,472 @@
+use crate::{
+    fuzziness::FuzzinessRules,
+    models::ProductLicence,
+    query_normalizer::{escape_special_characters, normalize_strengths},
+    synonyms::SynonymMap,
+};
+use regex::Regex;
+
+/// Whether a clause has to match, mustn't match, or just counts towards the score.
+///
+/// With `searchMode=all` an unmarked clause has to match as well.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Occur {
+    Should,
+    Must,
+    MustNot,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    /// A word, matched fuzzily but preferring exact matches.
+    Term(String),
+    /// Digits only. Fuzzing these would let `10` match `100` or `18`.
+    Number(String),
+    /// A dose or strength such as `500mg`, `250mg/5ml`, `0.5%` or `8/500`, matched exactly,
+    /// with or without spaces between the amounts and their units.
+    Dose(String),
+    /// A normalised product licence number such as `PL123451234`.
+    ProductLicence(String),
+    /// Quoted words, matched exactly and in order.
+    Phrase(String),
+    /// A word ending in `*`, matching any word that starts with it.
+    Prefix(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Clause {
+    pub(crate) occur: Occur,
+    pub(crate) node: Node,
+}
+
+/// What a user typed into the search box, parsed so that each part can be rendered as Lucene
+/// by its own rules. Anything that isn't recognised syntax is escaped and searched for as text.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct SearchQuery {
+    pub(crate) clauses: Vec<Clause>,
+}
+
+impl SearchQuery {
+    pub(crate) fn parse(input: &str) -> Self {
+        let input = normalize_strengths(&ProductLicence::normalize_all(input));
+        let chars = input.chars().collect::<Vec<_>>();
+        let mut clauses = vec![];
+        let mut i = 0;
+
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            // `+` and `-` are only operators when they are attached to what follows them.
+            let occur = match (chars[i], chars.get(i + 1)) {
+                ('+', Some(next)) if !next.is_whitespace() => Occur::Must,
+                ('-', Some(next)) if !next.is_whitespace() => Occur::MustNot,
+                _ => Occur::Should,
+            };
+            if occur != Occur::Should {
+                i += 1;
+            }
+
+            let node = if chars[i] == '"' {
+                // An unterminated quote runs to the end of the input.
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|c| *c == '"')
+                    .map_or(chars.len(), |position| i + 1 + position);
+                let phrase = chars[i + 1..end].iter().collect::<String>();
+                i = end + 1;
+                phrase_node(&phrase)
+            } else {
+                let end = chars[i..]
+                    .iter()
+                    .position(|c| c.is_whitespace())
+                    .map_or(chars.len(), |position| i + position);
+                let word = chars[i..end].iter().collect::<String>();
+                i = end;
+                Some(word_node(&word))
+            };
+
+            if let Some(node) = node {
+                clauses.push(Clause { occur, node });
+            }
+        }
+
+        Self { clauses }
+    }
+
+    pub(crate) fn to_lucene(
+        &self,
+        fuzziness: &FuzzinessRules,
+        search_exactness_boost: &str,
+        synonyms: Option<&SynonymMap>,
+    ) -> String {
+        let last = self.clauses.len().saturating_sub(1);
+        self.clauses
+            .iter()
+            .enumerate()
+            .map(|(i, clause)| {
+                let occur = match clause.occur {
+                    Occur::Should => "",
+                    Occur::Must => "+",
+                    Occur::MustNot => "-",
+                };
+                let expansions = match (&clause.node, synonyms) {
+                    (Node::Term(text), Some(synonyms)) | (Node::Phrase(text), Some(synonyms)) => {
+                        synonyms.expand(text)
+                    }
+                    _ => vec![],
+                };
+                let node = match &clause.node {
+                    Node::Term(word) => {
+                        // Only a word still being typed can be a prefix, not one being excluded.
+                        let is_prefix = i == last
+                            && clause.occur != Occur::MustNot
+                            && fuzziness.is_prefix(word);
+                        term_query(
+                            word,
+                            fuzziness.edit_distance(word),
+                            is_prefix,
+                            &expansions,
+                            search_exactness_boost,
+                        )
+                    }
+                    Node::Number(word) | Node::ProductLicence(word) => {
+                        escape_special_characters(word)
+                    }
+                    Node::Dose(dose) => dose_query(dose),
+                    Node::Phrase(phrase) if expansions.is_empty() => phrase_query(phrase),
+                    Node::Phrase(phrase) => format!(
+                        "({} || {})",
+                        phrase_query(phrase),
+                        alternatives(&expansions, search_exactness_boost)
+                    ),
+                    Node::Prefix(stem) => format!("{}*", escape_special_characters(stem)),
+                };
+                format!("{}{}", occur, node)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The text of the clauses that don't exclude anything.
+    pub(crate) fn included_text(&self) -> String {
+        self.text(|occur| occur != Occur::MustNot)
+    }
+
+    /// The text of the clauses that exclude documents.
+    pub(crate) fn excluded_text(&self) -> String {
+        self.text(|occur| occur == Occur::MustNot)
+    }
+
+    /// The query in a canonical form, with words in lower case and licence numbers and
+    /// strengths normalised, so that searches for the same thing read the same. With `redact`
+    /// any free text is replaced by `*`, keeping only numbers, strengths and licence numbers.
+    pub(crate) fn normalized_text(&self, redact: bool) -> String {
+        self.clauses
+            .iter()
+            .map(|clause| {
+                let text = match &clause.node {
+                    Node::Term(_) | Node::Phrase(_) | Node::Prefix(_) if redact => "*".to_string(),
+                    Node::Term(word) => word.to_lowercase(),
+                    Node::Phrase(phrase) => format!("\"{}\"", phrase.to_lowercase()),
+                    Node::Prefix(stem) => format!("{}*", stem.to_lowercase()),
+                    Node::Dose(dose) => dose.to_lowercase(),
+                    Node::Number(text) | Node::ProductLicence(text) => text.clone(),
+                };
+                match clause.occur {
+                    Occur::Should => text,
+                    Occur::Must => format!("+{}", text),
+                    Occur::MustNot => format!("-{}", text),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn text(&self, include: impl Fn(Occur) -> bool) -> String {
+        self.clauses
+            .iter()
+            .filter(|clause| include(clause.occur))
+            .map(|clause| match &clause.node {
+                Node::Term(text)
+                | Node::Number(text)
+                | Node::Dose(text)
+                | Node::ProductLicence(text)
+                | Node::Phrase(text)
+                | Node::Prefix(text) => text.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// An exact match is preferred to a fuzzy one, a prefix or a synonym.
+fn term_query(
+    word: &str,
+    edit_distance: usize,
+    is_prefix: bool,
+    expansions: &[&str],
+    search_exactness_boost: &str,
+) -> String {
+    let word = escape_special_characters(word);
+    let mut parts = if edit_distance > 0 {
+        vec![
+            format!("{}~{}", word, edit_distance),
+            format!("{}^{}", word, search_exactness_boost),
+        ]
+    } else {
+        vec![word.clone()]
+    };
+    if is_prefix {
+        parts.push(format!("{}*", word));
+    }
+    if !expansions.is_empty() {
+        parts.push(alternatives(expansions, search_exactness_boost));
+    }
+
+    if parts.len() == 1 {
+        parts.remove(0)
+    } else {
+        format!("({})", parts.join(" || "))
+    }
+}
+
+// Product names that haven't been through the strength-aware analyzer may still have spaces
+// between amounts and units, so match those too.
+fn dose_query(dose: &str) -> String {
+    lazy_static! {
+        static ref RE_AMOUNT_UNIT: Regex =
+            Regex::new(r"(?P<amount>\d)(?P<unit>[^\d/:.,])").unwrap();
+    }
+
+    let spaced = RE_AMOUNT_UNIT.replace_all(dose, "$amount $unit");
+    if spaced == dose {
+        escape_special_characters(dose)
+    } else {
+        format!(
+            "({} || {})",
+            escape_special_characters(dose),
+            phrase_query(&spaced)
+        )
+    }
+}
+
+fn phrase_query(phrase: &str) -> String {
+    format!(r#""{}""#, phrase.replace('\\', r"\\"))
+}
+
+// Synonyms are matched exactly, as they are already a different spelling.
+fn alternatives(expansions: &[&str], search_exactness_boost: &str) -> String {
+    expansions
+        .iter()
+        .map(|expansion| {
+            let expansion = if expansion.contains(' ') {
+                phrase_query(expansion)
+            } else {
+                escape_special_characters(expansion)
+            };
+            format!("{}^{}", expansion, search_exactness_boost)
+        })
+        .collect::<Vec<_>>()
+        .join(" || ")
+}
+
+fn phrase_node(phrase: &str) -> Option<Node> {
+    let words = phrase.split_whitespace().collect::<Vec<_>>();
+    if words.is_empty() {
+        None
+    } else {
+        Some(Node::Phrase(words.join(" ")))
+    }
+}
+
+fn word_node(word: &str) -> Node {
+    lazy_static! {
+        static ref RE_NUMBER: Regex = Regex::new(r"^\d+([.,]\d+)*$").unwrap();
+        // An amount, then optionally per some other amount, e.g. `250mg/5ml` or `1:1000`.
+        static ref RE_DOSE: Regex = Regex::new(&format!(
+            r"(?i)^\d+([.,]\d+)?({units})?([/:](\d+([.,]\d+)?)?({units}|dose|h|hours?)?)*$",
+            units = "mg|g|mcg|µg|ug|ng|kg|ml|l|iu|units?|mmol|micrograms?|milligrams?|grams?|%"
+        ))
+        .unwrap();
+    }
+
+    let stem = word.trim_end_matches('*');
+    if let Ok(licence) = word.parse::<ProductLicence>() {
+        Node::ProductLicence(licence.canonical())
+    } else if RE_NUMBER.is_match(word) {
+        Node::Number(word.to_string())
+    } else if RE_DOSE.is_match(word) {
+        Node::Dose(word.to_string())
+    } else if !stem.is_empty() && stem.len() < word.len() {
+        Node::Prefix(stem.to_string())
+    } else {
+        match word {
+            // Lucene operators are searched for as plain words.
+            "AND" | "OR" | "NOT" => Node::Term(word.to_lowercase()),
+            _ => Node::Term(word.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn clause(occur: Occur, node: Node) -> Clause {
+        Clause { occur, node }
+    }
+
+    #[test]
+    fn parses_each_kind_of_clause() {
+        let query = SearchQuery::parse(
+            r#"ibuprofen "film-coated  tablets" +400 -child* PL 12345/1234 -"oral suspension" 5 mg/ml"#,
+        );
+
+        assert_eq!(
+            query.clauses,
+            vec![
+                clause(Occur::Should, Node::Term("ibuprofen".to_string())),
+                clause(
+                    Occur::Should,
+                    Node::Phrase("film-coated tablets".to_string())
+                ),
+                clause(Occur::Must, Node::Number("400".to_string())),
+                clause(Occur::MustNot, Node::Prefix("child".to_string())),
+                clause(
+                    Occur::Should,
+                    Node::ProductLicence("PL123451234".to_string())
+                ),
+                clause(Occur::MustNot, Node::Phrase("oral suspension".to_string())),
+                clause(Occur::Should, Node::Dose("5mg/ml".to_string())),
+            ]
+        );
+    }
+
+    #[test_case("ibuprofen", "(ibuprofen~1 || ibuprofen^4)")]
+    #[test_case("ibuprofen 400", "(ibuprofen~1 || ibuprofen^4) 400")]
+    #[test_case("plpi 12345-1234", "PLPI123451234")]
+    #[test_case(r#""film-coated tablets""#, r#""film-coated tablets""#)]
+    #[test_case(r#""unterminated phrase"#, r#""unterminated phrase""#)]
+    #[test_case(r#""" empty"#, "(empty~1 || empty^4)")]
+    #[test_case(
+        "+ibuprofen -paracetamol",
+        "+(ibuprofen~1 || ibuprofen^4) -(paracetamol~1 || paracetamol^4)"
+    )]
+    #[test_case("ibupro*", "ibupro*")]
+    #[test_case("- + *", r"\- \+ \*")]
+    #[test_case("co-codamol 8/500", r"(co\-codamol~1 || co\-codamol^4) 8\/500")]
+    #[test_case(
+        r#"back\slash "back\slash""#,
+        r#"(back\\slash~1 || back\\slash^4) "back\\slash""#
+    )]
+    fn renders_safe_lucene(input: &str, expected: &str) {
+        assert_eq!(
+            SearchQuery::parse(input).to_lucene(&FuzzinessRules::default(), "4", None),
+            expected
+        );
+    }
+
+    #[test_case(
+        "acetaminophen tablets",
+        "(acetaminophen~1 || acetaminophen^4 || paracetamol^4) (tablets~1 || tablets^4)"
+    )]
+    #[test_case("-Epinephrine", "-(Epinephrine~1 || Epinephrine^4 || adrenaline^4)")]
+    #[test_case(
+        "nitroglycerin",
+        r#"(nitroglycerin~1 || nitroglycerin^4 || "glyceryl trinitrate"^4)"#
+    )]
+    #[test_case(
+        r#""glyceryl trinitrate""#,
+        r#"("glyceryl trinitrate" || nitroglycerin^4)"#
+    )]
+    #[test_case("acetamin*", "acetamin*")]
+    fn renders_synonyms_as_exact_alternatives(input: &str, expected: &str) {
+        let synonyms = SynonymMap::from_solr(
+            "paracetamol, acetaminophen\nadrenaline, epinephrine\nglyceryl trinitrate, nitroglycerin",
+        );
+
+        assert_eq!(
+            SearchQuery::parse(input).to_lucene(&FuzzinessRules::default(), "4", Some(&synonyms)),
+            expected
+        );
+    }
+
+    // Searches people have made, with the rules we'd like to run them with.
+    #[test_case(
+        "paracetamol 500mg tablets",
+        r#"(paracetamol~2 || paracetamol^4) (500mg || "500 mg") (tablets~1 || tablets^4 || tablets*)"#
+    )]
+    #[test_case(
+        "ibuprofen 200 mg",
+        r#"(ibuprofen~2 || ibuprofen^4) (200mg || "200 mg")"#
+    )]
+    #[test_case(
+        "amoxicillin 250mg/5ml",
+        r#"(amoxicillin~2 || amoxicillin^4) (250mg\/5ml || "250 mg/5 ml")"#
+    )]
+    #[test_case(
+        "hydrocortisone 0.5% cream",
+        r#"(hydrocortisone~2 || hydrocortisone^4) (0.5% || "0.5 %") (cream~1 || cream^4 || cream*)"#
+    )]
+    #[test_case("co-codamol 30/500", r"(co\-codamol~2 || co\-codamol^4) 30\/500")]
+    #[test_case("adrenaline 1:1000", r"(adrenaline~2 || adrenaline^4) 1\:1000")]
+    #[test_case(
+        "vitamin d3 1000iu",
+        r#"(vitamin~1 || vitamin^4) d3 (1000iu || "1000 iu")"#
+    )]
+    #[test_case("salbutamol 100 micrograms inhaler", r#"(salbutamol~2 || salbutamol^4) (100mcg || "100 mcg") (inhaler~1 || inhaler^4 || inhaler*)"#)]
+    #[test_case("PL 12345/1234", "PL123451234")]
+    #[test_case("hrt patch", "hrt (patch~1 || patch^4 || patch*)")]
+    #[test_case("flu", "(flu || flu*)")]
+    #[test_case("flu jab", "flu (jab || jab*)")]
+    #[test_case("metfor", "(metfor~1 || metfor^4 || metfor*)")]
+    #[test_case("iron -tablets", "(iron~1 || iron^4) -(tablets~1 || tablets^4)")]
+    #[test_case("5mg", r#"(5mg || "5 mg")"#)]
+    #[test_case(
+        "co-codamol 30 mg/500 mg",
+        r#"(co\-codamol~2 || co\-codamol^4) (30mg\/500mg || "30 mg/500 mg")"#
+    )]
+    fn renders_real_queries_by_the_fuzziness_rules(input: &str, expected: &str) {
+        let rules = FuzzinessRules::default()
+            .max_edits(2)
+            .prefix_last_term(true);
+
+        assert_eq!(
+            SearchQuery::parse(input).to_lucene(&rules, "4", None),
+            expected
+        );
+    }
+
+    #[test]
+    fn operators_are_searched_for_as_words() {
+        let input = "this AND that OR something else NOT the other for grand cannot";
+        let expected = "this and that or something else not the other for grand cannot";
+
+        assert_eq!(SearchQuery::parse(input).included_text(), expected);
+    }
+
+    #[test]
+    fn separates_included_and_excluded_text() {
+        let query = SearchQuery::parse(r#"ibuprofen -"oral suspension" -child"#);
+
+        assert_eq!(query.included_text(), "ibuprofen");
+        assert_eq!(query.excluded_text(), "oral suspension child");
+    }
+
+    #[test_case("Ibuprofen  200 MG", false, "ibuprofen 200mg")]
+    #[test_case(
+        r#"+Nurofen -"Oral Suspension" ibu*"#,
+        false,
+        r#"+nurofen -"oral suspension" ibu*"#
+    )]
+    #[test_case("pl 12345/1234", false, "PL123451234")]
+    #[test_case(r#"jane smith -"my gp" ibu* 2 200mg"#, true, "* * -* * 2 200mg")]
+    #[test_case("PL 12345/1234 paracetamol", true, "PL123451234 *")]
+    fn renders_normalized_text(input: &str, redact: bool, expected: &str) {
+        assert_eq!(SearchQuery::parse(input).normalized_text(redact), expected);
+    }
+}
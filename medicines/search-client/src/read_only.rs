@@ -0,0 +1,154 @@
+use crate::{
+    credentials,
+    models::{AutocompleteResults, FacetQueryResults, FacetResults, SuggestResults},
+    AzureSearchClient, Backend, InMemorySearchClient, Search, SearchClient, SearchClientError,
+    SearchOptions,
+};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+
+/// A client that can only query an index, for services that have no business changing one.
+///
+/// With the `azure` backend it authenticates with a query key from `AZURE_API_QUERY_KEY`
+/// rather than the admin key, so the service rejects writes too. With a token
+/// `AZURE_AUTH_MODE`, the identity should only have the Search Index Data Reader role.
+#[derive(Clone)]
+pub struct ReadOnlySearchClient(SearchClient);
+
+impl Default for ReadOnlySearchClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReadOnlySearchClient {
+    pub fn new() -> Self {
+        let default_index = crate::get_env("AZURE_SEARCH_INDEX");
+        ReadOnlySearchClient::new_with_index(default_index)
+    }
+
+    pub fn new_with_index(index: String) -> Self {
+        match Backend::from_env() {
+            Backend::InMemory => InMemorySearchClient::new_with_index(index).into(),
+            Backend::Azure => {
+                let credential =
+                    credentials::from_env("AZURE_API_QUERY_KEY", credentials::SEARCH_SCOPE);
+                Self(SearchClient::Azure(AzureSearchClient::new_with_credential(
+                    index, credential,
+                )))
+            }
+        }
+    }
+}
+
+impl From<InMemorySearchClient> for ReadOnlySearchClient {
+    fn from(client: InMemorySearchClient) -> Self {
+        Self(SearchClient::InMemory(client))
+    }
+}
+
+#[async_trait]
+impl Search for ReadOnlySearchClient {
+    async fn search<T>(&self, options: SearchOptions) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.0.search(options).await
+    }
+
+    async fn search_by_facet_field(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<FacetResults, SearchClientError> {
+        self.0.search_by_facet_field(field_name, field_value).await
+    }
+
+    async fn search_facets(
+        &self,
+        options: SearchOptions,
+    ) -> Result<FacetQueryResults, SearchClientError> {
+        self.0.search_facets(options).await
+    }
+
+    async fn suggest(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<SuggestResults, SearchClientError> {
+        self.0.suggest(search_term, top).await
+    }
+
+    async fn autocomplete(
+        &self,
+        search_term: &str,
+        top: i32,
+    ) -> Result<AutocompleteResults, SearchClientError> {
+        self.0.autocomplete(search_term, top).await
+    }
+
+    async fn filter_by_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.0
+            .filter_by_collection_field(field_name, field_value)
+            .await
+    }
+
+    async fn filter_by_non_collection_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<T, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.0
+            .filter_by_non_collection_field(field_name, field_value)
+            .await
+    }
+
+    async fn get_document<T>(&self, key: &str) -> Result<Option<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.0.get_document(key).await
+    }
+
+    async fn find_by_field<T>(
+        &self,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<T>, SearchClientError>
+    where
+        T: DeserializeOwned,
+    {
+        self.0.find_by_field(field_name, field_value).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+    use tokio_test::block_on;
+
+    #[test]
+    fn read_only_client_searches_the_index_it_wraps() {
+        let client: ReadOnlySearchClient = InMemorySearchClient::from_documents(
+            "read-only-index",
+            vec![json!({ "metadata_storage_name": "abc", "title": "Ibuprofen" })],
+        )
+        .into();
+
+        let document: Option<Value> = block_on(client.get_document("abc")).unwrap();
+
+        assert_eq!(document.unwrap()["title"], "Ibuprofen");
+    }
+}
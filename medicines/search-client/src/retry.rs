@@ -0,0 +1,386 @@
+use crate::{get_env_or_default, SearchClientError};
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How often, and how patiently, failed requests to the search service are retried.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub(crate) fn from_env() -> Self {
+        Self {
+            max_retries: parse_env("AZURE_SEARCH_MAX_RETRIES", "3"),
+            initial_backoff: Duration::from_millis(parse_env(
+                "AZURE_SEARCH_RETRY_INITIAL_BACKOFF_MS",
+                "200",
+            )),
+            max_backoff: Duration::from_millis(parse_env(
+                "AZURE_SEARCH_RETRY_MAX_BACKOFF_MS",
+                "10000",
+            )),
+        }
+    }
+
+    // Honours `Retry-After` when Azure sends it, otherwise backs off exponentially with
+    // full jitter so that throttled clients don't all retry at the same moment.
+    fn backoff(&self, attempt: u32, error: &SearchClientError) -> Duration {
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after.min(self.max_backoff);
+        }
+
+        let exponential = self
+            .initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        exponential.mul_f64(jitter())
+    }
+}
+
+/// Stops sending requests for a while after several consecutive failures, so that callers
+/// fail fast rather than queueing up behind a service that is down. Clones share state.
+#[derive(Clone, Debug)]
+pub(crate) struct CircuitBreaker {
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    state: Arc<Mutex<CircuitState>>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_started_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn from_env() -> Self {
+        Self::new(
+            parse_env("AZURE_SEARCH_CIRCUIT_BREAKER_THRESHOLD", "5"),
+            Duration::from_secs(parse_env("AZURE_SEARCH_CIRCUIT_BREAKER_RESET_SECS", "30")),
+        )
+    }
+
+    fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            failure_threshold,
+            reset_timeout,
+            state: Arc::new(Mutex::new(CircuitState::default())),
+        }
+    }
+
+    // Once the reset timeout has passed one request is let through to probe the service,
+    // while the rest still fail fast: success closes the circuit again, failure re-opens it.
+    // A probe that never reports back, because its caller gave up on it, is replaced after
+    // another reset timeout.
+    fn check(&self) -> Result<(), SearchClientError> {
+        let mut state = self
+            .state
+            .lock()
+            .expect("Circuit breaker state was poisoned");
+        let waited = match state.opened_at {
+            Some(opened_at) => opened_at.elapsed(),
+            None => return Ok(()),
+        };
+        let waited = match state.probe_started_at {
+            Some(probe_started_at) => waited.min(probe_started_at.elapsed()),
+            None => waited,
+        };
+
+        if waited < self.reset_timeout {
+            Err(SearchClientError::CircuitOpen {
+                retry_after: self.reset_timeout - waited,
+            })
+        } else {
+            state.probe_started_at = Some(Instant::now());
+            Ok(())
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("Circuit breaker state was poisoned");
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+        state.probe_started_at = None;
+    }
+
+    fn record_failure(&self) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("Circuit breaker state was poisoned");
+        state.consecutive_failures += 1;
+        state.probe_started_at = None;
+        if self.failure_threshold > 0 && state.consecutive_failures >= self.failure_threshold {
+            if state.opened_at.is_none() {
+                tracing::warn!(
+                    "Opening circuit to Azure search service after {} consecutive failures",
+                    state.consecutive_failures
+                );
+            }
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Retries and circuit breaking for requests to the Azure search service.
+#[derive(Clone, Debug)]
+pub(crate) struct Resilience {
+    policy: RetryPolicy,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl Resilience {
+    pub(crate) fn from_env() -> Self {
+        Self {
+            policy: RetryPolicy::from_env(),
+            circuit_breaker: CircuitBreaker::from_env(),
+        }
+    }
+
+    /// Runs `operation`, retrying it while it fails with a retryable error.
+    pub(crate) async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, SearchClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SearchClientError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            self.circuit_breaker.check()?;
+
+            let error = match operation().await {
+                Ok(result) => {
+                    self.circuit_breaker.record_success();
+                    return Ok(result);
+                }
+                Err(e) if !e.is_retryable() => {
+                    // The service answered, so it is up even if it didn't like the request.
+                    self.circuit_breaker.record_success();
+                    return Err(e);
+                }
+                Err(e) => e,
+            };
+
+            self.circuit_breaker.record_failure();
+            if attempt >= self.policy.max_retries {
+                return Err(error);
+            }
+
+            let backoff = self.policy.backoff(attempt, &error);
+            tracing::warn!(
+                "Retrying Azure search request in {:?} (attempt {} of {}): {}",
+                backoff,
+                attempt + 1,
+                self.policy.max_retries,
+                error
+            );
+            tokio::time::delay_for(backoff).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn parse_env<T>(key: &str, default: &str) -> T
+where
+    T: std::str::FromStr,
+{
+    get_env_or_default(key, default)
+        .parse()
+        .or_else(|_| default.parse())
+        .unwrap_or_else(|_| panic!("Invalid default for {}", key))
+}
+
+// A random factor between 0 and 1. This only spreads retries out, so it needn't be strong.
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+    use std::cell::Cell;
+    use tokio_test::block_on;
+
+    fn given_a_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    fn given_resilience(max_retries: u32, failure_threshold: u32) -> Resilience {
+        Resilience {
+            policy: given_a_policy(max_retries),
+            circuit_breaker: CircuitBreaker::new(failure_threshold, Duration::from_secs(60)),
+        }
+    }
+
+    fn throttled(retry_after: Option<Duration>) -> SearchClientError {
+        SearchClientError::Throttled {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn backoff_honours_retry_after_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        };
+
+        assert_eq!(
+            policy.backoff(0, &throttled(Some(Duration::from_secs(2)))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.backoff(0, &throttled(Some(Duration::from_secs(60)))),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_with_jitter() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+
+        assert!(policy.backoff(0, &throttled(None)) <= Duration::from_millis(100));
+        assert!(policy.backoff(2, &throttled(None)) <= Duration::from_millis(400));
+        assert!(policy.backoff(9, &throttled(None)) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn retries_retryable_errors_until_success() {
+        let resilience = given_resilience(3, 10);
+        let attempts = Cell::new(0);
+
+        let result = block_on(resilience.run(|| {
+            attempts.set(attempts.get() + 1);
+            let attempt = attempts.get();
+            async move {
+                if attempt < 3 {
+                    Err(throttled(None))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        }));
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_the_maximum_retries() {
+        let resilience = given_resilience(2, 10);
+        let attempts = Cell::new(0);
+
+        let result: Result<(), _> = block_on(resilience.run(|| {
+            attempts.set(attempts.get() + 1);
+            async { Err(throttled(None)) }
+        }));
+
+        assert!(matches!(result, Err(SearchClientError::Throttled { .. })));
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_invalid_requests() {
+        let resilience = given_resilience(3, 10);
+        let attempts = Cell::new(0);
+
+        let result: Result<(), _> = block_on(resilience.run(|| {
+            attempts.set(attempts.get() + 1);
+            async { Err(SearchClientError::InvalidRequest("Bad filter".to_string())) }
+        }));
+
+        assert!(matches!(result, Err(SearchClientError::InvalidRequest(_))));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn open_circuit_fails_fast() {
+        let resilience = given_resilience(0, 2);
+        let attempts = Cell::new(0);
+        let call = || {
+            block_on(resilience.run(|| {
+                attempts.set(attempts.get() + 1);
+                async { Err::<(), _>(throttled(None)) }
+            }))
+        };
+
+        call().unwrap_err();
+        call().unwrap_err();
+        let result = call();
+
+        assert!(matches!(result, Err(SearchClientError::CircuitOpen { .. })));
+        assert_eq!(attempts.get(), 2);
+    }
+
+    #[test]
+    fn circuit_closes_again_after_a_success() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(0));
+
+        breaker.record_failure();
+        assert!(breaker.check().is_ok(), "Probe should be let through");
+        breaker.record_success();
+
+        assert_eq!(
+            breaker.state.lock().unwrap().consecutive_failures,
+            0,
+            "Failures should be reset"
+        );
+    }
+
+    #[test]
+    fn only_one_probe_is_let_through_at_a_time() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.state.lock().unwrap().opened_at = Some(Instant::now() - Duration::from_secs(61));
+
+        assert!(breaker.check().is_ok(), "Probe should be let through");
+        assert!(matches!(
+            breaker.check(),
+            Err(SearchClientError::CircuitOpen { .. })
+        ));
+
+        breaker.record_success();
+        assert!(breaker.check().is_ok());
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn a_failed_probe_reopens_the_circuit() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.state.lock().unwrap().opened_at = Some(Instant::now() - Duration::from_secs(61));
+
+        assert!(breaker.check().is_ok(), "Probe should be let through");
+        breaker.record_failure();
+
+        assert!(matches!(
+            breaker.check(),
+            Err(SearchClientError::CircuitOpen { .. })
+        ));
+    }
+}
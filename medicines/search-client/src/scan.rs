@@ -0,0 +1,297 @@
+use crate::{
+    error::from_value,
+    filter::{field, Filter},
+    models::Documents,
+    Search, SearchClientError, SearchOptions, SortOrder,
+};
+use futures::stream::{self, Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+// The most documents Azure will return in one response.
+const MAX_PAGE_SIZE: i32 = 1000;
+
+/// Walks every document in an index in order of a key, a page at a time.
+///
+/// Each page asks for the documents after the last key of the one before, rather than
+/// skipping over them with `$skip`, which Azure refuses past 100,000 documents. The key
+/// field must be filterable and sortable, and unique, as the index key is.
+///
+/// ```
+/// use search_client::{filter::field, ScanOptions};
+///
+/// let options = ScanOptions::new("metadata_storage_name")
+///     .filter(field("doc_type").eq("Spc"))
+///     .select(&["metadata_storage_name", "file_name"])
+///     .page_size(500);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanOptions {
+    key_field: String,
+    filter: Option<Filter>,
+    select: Vec<String>,
+    page_size: i32,
+    after: Option<String>,
+}
+
+impl ScanOptions {
+    pub fn new(key_field: &str) -> Self {
+        Self {
+            key_field: key_field.to_string(),
+            filter: None,
+            select: vec![],
+            page_size: MAX_PAGE_SIZE,
+            after: None,
+        }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// The fields to return. The key field is always returned, as it is needed to find the next page.
+    pub fn select(mut self, fields: &[&str]) -> Self {
+        self.select = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    /// Between 1 and 1000 documents per request.
+    pub fn page_size(mut self, page_size: i32) -> Self {
+        self.page_size = if page_size > MAX_PAGE_SIZE {
+            MAX_PAGE_SIZE
+        } else {
+            page_size.max(1)
+        };
+        self
+    }
+
+    /// Carries on from the `continuation` of a page from an earlier scan.
+    pub fn after(mut self, continuation: &str) -> Self {
+        self.after = Some(continuation.to_string());
+        self
+    }
+
+    fn search_options(&self, after: Option<&str>) -> SearchOptions {
+        let range = after.map(|key| field(&self.key_field).gt(key));
+        let mut options = SearchOptions::default()
+            .highlight(&[])
+            .scoring_profile(None)
+            .order_by(&self.key_field, SortOrder::Asc)
+            .top(self.page_size);
+
+        if let Some(filter) = Filter::all_of(self.filter.clone().into_iter().chain(range)) {
+            options = options.filter(filter);
+        }
+        if !self.select.is_empty() {
+            let mut select = self.select.iter().map(String::as_str).collect::<Vec<_>>();
+            if !select.contains(&self.key_field.as_str()) {
+                select.push(&self.key_field);
+            }
+            options = options.select(&select);
+        }
+        options
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanPage<T> {
+    pub documents: Vec<T>,
+    /// Where to carry on from with `ScanOptions::after`, or `None` if this is the last page.
+    pub continuation: Option<String>,
+}
+
+/// The pages of documents matching `options`. The stream ends after the last page, or
+/// after the first error; scanning can be resumed from the last page's `continuation`.
+pub fn scan_pages<'a, S, T>(
+    client: &'a S,
+    options: ScanOptions,
+) -> impl Stream<Item = Result<ScanPage<T>, SearchClientError>> + 'a
+where
+    S: Search + Sync,
+    T: DeserializeOwned + 'a,
+{
+    let after = Some(options.after.clone());
+    stream::unfold((options, after), move |(options, after)| async move {
+        // `None` once there are no pages left, `Some(None)` for the first page.
+        let after = after?;
+        let page = get_page(client, &options, after.as_deref()).await;
+        let next = match &page {
+            Ok(page) if page.documents.is_empty() => return None,
+            Ok(page) => page.continuation.clone().map(Some),
+            Err(_) => None,
+        };
+        Some((page, (options, next)))
+    })
+}
+
+/// Every document matching `options`, one at a time, holding no more than a page in memory.
+pub fn scan<'a, S, T>(
+    client: &'a S,
+    options: ScanOptions,
+) -> impl Stream<Item = Result<T, SearchClientError>> + 'a
+where
+    S: Search + Sync,
+    T: DeserializeOwned + 'a,
+{
+    scan_pages(client, options)
+        .map_ok(|page| stream::iter(page.documents.into_iter().map(Ok)))
+        .try_flatten()
+}
+
+async fn get_page<S, T>(
+    client: &S,
+    options: &ScanOptions,
+    after: Option<&str>,
+) -> Result<ScanPage<T>, SearchClientError>
+where
+    S: Search + Sync,
+    T: DeserializeOwned,
+{
+    let documents: Documents<Value> = client.search(options.search_options(after)).await?;
+
+    // A short page is the last one, which saves asking for an empty page after it.
+    let continuation = if documents.value.len() < options.page_size as usize {
+        None
+    } else {
+        documents
+            .value
+            .last()
+            .and_then(|document| document.get(&options.key_field))
+            .and_then(Value::as_str)
+            .map(String::from)
+    };
+    let documents = documents
+        .value
+        .into_iter()
+        .map(from_value)
+        .collect::<Result<Vec<T>, _>>()?;
+
+    Ok(ScanPage {
+        documents,
+        continuation,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::InMemorySearchClient;
+    use futures::stream::StreamExt;
+    use pretty_assertions::assert_eq;
+    use serde_derive::Deserialize;
+    use serde_json::json;
+    use tokio_test::block_on;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Key {
+        metadata_storage_name: String,
+    }
+
+    fn given_a_client_with_documents(names: &[&str]) -> InMemorySearchClient {
+        InMemorySearchClient::from_documents(
+            "products-index",
+            names
+                .iter()
+                .map(|name| {
+                    json!({
+                        "metadata_storage_name": name,
+                        "doc_type": if name.starts_with('p') { "Pil" } else { "Spc" },
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn keys<T>(pages: &[ScanPage<T>], key: impl Fn(&T) -> &str) -> Vec<Vec<&str>> {
+        pages
+            .iter()
+            .map(|page| page.documents.iter().map(&key).collect())
+            .collect()
+    }
+
+    #[test]
+    fn scan_options_page_by_the_key_after_the_last_one() {
+        let options = ScanOptions::new("id")
+            .filter(field("doc_type").eq("Spc"))
+            .select(&["title"])
+            .page_size(5000);
+
+        let first = options.search_options(None);
+        let next = options.search_options(Some("b'c"));
+
+        assert_eq!(first.top, Some(1000));
+        assert_eq!(first.order_by, vec![("id".to_string(), SortOrder::Asc)]);
+        assert_eq!(first.select, vec!["title", "id"]);
+        assert_eq!(first.filter.unwrap().to_string(), "doc_type eq 'Spc'");
+        assert_eq!(
+            next.filter.unwrap().to_string(),
+            "(doc_type eq 'Spc' and id gt 'b''c')"
+        );
+    }
+
+    #[test]
+    fn scan_pages_walks_the_whole_index_in_key_order() {
+        let client = given_a_client_with_documents(&["e", "b", "d", "a", "c"]);
+
+        let pages: Vec<ScanPage<Key>> = block_on(
+            scan_pages(
+                &client,
+                ScanOptions::new("metadata_storage_name").page_size(2),
+            )
+            .map(Result::unwrap)
+            .collect(),
+        );
+
+        assert_eq!(
+            keys(&pages, |key| key.metadata_storage_name.as_str()),
+            vec![vec!["a", "b"], vec!["c", "d"], vec!["e"]]
+        );
+        assert_eq!(pages[1].continuation, Some("d".to_string()));
+        assert_eq!(pages[2].continuation, None);
+    }
+
+    #[test]
+    fn scan_pages_ends_without_an_empty_page_when_the_last_page_is_full() {
+        let client = given_a_client_with_documents(&["a", "b", "c", "d"]);
+
+        let pages: Vec<ScanPage<Key>> = block_on(
+            scan_pages(
+                &client,
+                ScanOptions::new("metadata_storage_name").page_size(2),
+            )
+            .map(Result::unwrap)
+            .collect(),
+        );
+
+        assert_eq!(pages.len(), 2);
+    }
+
+    #[test]
+    fn scan_can_resume_from_a_continuation_and_respects_the_filter() {
+        let client = given_a_client_with_documents(&["a", "b", "pa", "pb", "pc"]);
+        let options = ScanOptions::new("metadata_storage_name")
+            .filter(field("doc_type").eq("Pil"))
+            .after("pa");
+
+        let documents: Vec<Key> = block_on(scan(&client, options).map(Result::unwrap).collect());
+
+        assert_eq!(
+            documents
+                .iter()
+                .map(|key| key.metadata_storage_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["pb", "pc"]
+        );
+    }
+
+    #[test]
+    fn scan_of_an_empty_index_yields_nothing() {
+        let client = given_a_client_with_documents(&[]);
+
+        let documents: Vec<Result<Key, _>> =
+            block_on(scan(&client, ScanOptions::new("metadata_storage_name")).collect());
+
+        assert!(documents.is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    facet::FacetSpec, filter::Filter, query_parser::SearchQuery, synonyms::SynonymMap,
+    AzurePagination, FuzzinessRules,
+};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+const DEFAULT_HIGHLIGHT_FIELD: &str = "content";
+const DEFAULT_SCORING_PROFILE: &str = scoring_profiles::PREFER_KEYWORDS;
+
+/// The scoring profiles declared in `search/definitions/indexes`. Azure rejects a search
+/// with a profile its index doesn't declare.
+pub mod scoring_profiles {
+    /// Weights licence numbers, keywords and product names (report names, substances and
+    /// products in the BMGF index). Declared in both indexes.
+    pub const PREFER_KEYWORDS: &str = "preferKeywords";
+    /// As `preferKeywords`, but boosts documents created in the last year. Products index only.
+    pub const PREFER_RECENT: &str = "preferRecent";
+    /// Strongly prefers matches in the report name. BMGF index only.
+    pub const PREFER_REPORT_NAME: &str = "preferReportName";
+}
+
+/// Whether any or all of the search terms have to match for a document to be returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SearchMode {
+    Any,
+    All,
+}
+
+/// How the search term is parsed: the simple query syntax or full Lucene syntax.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QueryType {
+    Simple,
+    Full,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl Display for SearchMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchMode::Any => write!(f, "any"),
+            SearchMode::All => write!(f, "all"),
+        }
+    }
+}
+
+impl Display for QueryType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryType::Simple => write!(f, "simple"),
+            QueryType::Full => write!(f, "full"),
+        }
+    }
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortOrder::Asc => write!(f, "asc"),
+            SortOrder::Desc => write!(f, "desc"),
+        }
+    }
+}
+
+/// The parameters of a search request.
+///
+/// The defaults match how we have always queried the indexes: a full Lucene query
+/// matching all terms, highlighting `content` and scored with `preferKeywords`.
+///
+/// ```
+/// use search_client::{filter::field, SearchOptions, SortOrder};
+///
+/// let options = SearchOptions::new("ibuprofen")
+///     .filter(field("doc_type").eq("Spc"))
+///     .order_by("created", SortOrder::Desc)
+///     .top(10)
+///     .include_count(true);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchOptions {
+    pub(crate) search_term: String,
+    pub(crate) filter: Option<Filter>,
+    pub(crate) order_by: Vec<(String, SortOrder)>,
+    pub(crate) select: Vec<String>,
+    pub(crate) facets: Vec<FacetSpec>,
+    pub(crate) highlight_fields: Vec<String>,
+    pub(crate) highlight_tags: Option<(String, String)>,
+    pub(crate) scoring_profile: Option<String>,
+    pub(crate) search_mode: SearchMode,
+    pub(crate) query_type: QueryType,
+    pub(crate) top: Option<i32>,
+    pub(crate) skip: Option<i32>,
+    pub(crate) include_count: bool,
+    pub(crate) synonyms: Option<Arc<SynonymMap>>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl SearchOptions {
+    pub fn new(search_term: &str) -> Self {
+        Self {
+            search_term: search_term.to_string(),
+            filter: None,
+            order_by: vec![],
+            select: vec![],
+            facets: vec![],
+            highlight_fields: vec![DEFAULT_HIGHLIGHT_FIELD.to_string()],
+            highlight_tags: None,
+            scoring_profile: Some(DEFAULT_SCORING_PROFILE.to_string()),
+            search_mode: SearchMode::All,
+            query_type: QueryType::Full,
+            top: None,
+            skip: None,
+            include_count: false,
+            synonyms: None,
+        }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Sorts by `field`, after any fields already given. Results are sorted by score otherwise.
+    pub fn order_by(mut self, field: &str, order: SortOrder) -> Self {
+        self.order_by.push((field.to_string(), order));
+        self
+    }
+
+    /// Restricts the fields returned for each result. All retrievable fields are returned otherwise.
+    pub fn select(mut self, fields: &[&str]) -> Self {
+        self.select = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    /// Requests facet counts for a field, after any facets already given.
+    pub fn facet(mut self, facet: impl Into<FacetSpec>) -> Self {
+        self.facets.push(facet.into());
+        self
+    }
+
+    /// Replaces the highlighted fields; an empty list turns highlighting off.
+    pub fn highlight(mut self, fields: &[&str]) -> Self {
+        self.highlight_fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    pub fn highlight_tags(mut self, pre_tag: &str, post_tag: &str) -> Self {
+        self.highlight_tags = Some((pre_tag.to_string(), post_tag.to_string()));
+        self
+    }
+
+    /// Scores with a profile from `scoring_profiles`, or by text relevance alone with `None`.
+    pub fn scoring_profile(mut self, scoring_profile: Option<&str>) -> Self {
+        self.scoring_profile = scoring_profile.map(String::from);
+        self
+    }
+
+    pub fn search_mode(mut self, search_mode: SearchMode) -> Self {
+        self.search_mode = search_mode;
+        self
+    }
+
+    pub fn query_type(mut self, query_type: QueryType) -> Self {
+        self.query_type = query_type;
+        self
+    }
+
+    pub fn top(mut self, top: i32) -> Self {
+        self.top = Some(top);
+        self
+    }
+
+    pub fn skip(mut self, skip: i32) -> Self {
+        self.skip = Some(skip);
+        self
+    }
+
+    pub fn pagination(self, pagination: AzurePagination) -> Self {
+        self.top(pagination.result_count).skip(pagination.offset)
+    }
+
+    pub fn include_count(mut self, include_count: bool) -> Self {
+        self.include_count = include_count;
+        self
+    }
+
+    /// Also searches for the other names of each word or quoted phrase. Only applies to
+    /// full Lucene queries, which are the ones built from what users type.
+    pub fn synonyms(mut self, synonyms: Arc<SynonymMap>) -> Self {
+        self.synonyms = Some(synonyms);
+        self
+    }
+
+    /// The query parameters Azure is sent for these options, other than the API version.
+    pub(crate) fn query(
+        &self,
+        fuzziness: &FuzzinessRules,
+        search_exactness_boost: &str,
+    ) -> Vec<(&'static str, String)> {
+        let search_term = match self.query_type {
+            QueryType::Full => SearchQuery::parse(&self.search_term).to_lucene(
+                fuzziness,
+                search_exactness_boost,
+                self.synonyms.as_deref(),
+            ),
+            QueryType::Simple => self.search_term.clone(),
+        };
+
+        let mut query = vec![];
+        if !self.highlight_fields.is_empty() {
+            query.push(("highlight", self.highlight_fields.join(",")));
+        }
+        query.push(("queryType", self.query_type.to_string()));
+        query.push(("search", search_term));
+        if let Some(scoring_profile) = &self.scoring_profile {
+            query.push(("scoringProfile", scoring_profile.clone()));
+        }
+        query.push(("searchMode", self.search_mode.to_string()));
+        query.push(("$count", self.include_count.to_string()));
+        if let Some(filter) = &self.filter {
+            query.push(("$filter", filter.to_string()));
+        }
+        for facet in &self.facets {
+            query.push(("facet", facet.to_string()));
+        }
+        if !self.order_by.is_empty() {
+            let order_by = self
+                .order_by
+                .iter()
+                .map(|(field, order)| format!("{} {}", field, order))
+                .collect::<Vec<_>>()
+                .join(",");
+            query.push(("$orderby", order_by));
+        }
+        if !self.select.is_empty() {
+            query.push(("$select", self.select.join(",")));
+        }
+        if let Some((pre_tag, post_tag)) = &self.highlight_tags {
+            query.push(("highlightPreTag", pre_tag.clone()));
+            query.push(("highlightPostTag", post_tag.clone()));
+        }
+        if let Some(top) = self.top {
+            query.push(("$top", top.to_string()));
+        }
+        if let Some(skip) = self.skip {
+            query.push(("$skip", skip.to_string()));
+        }
+        query
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn defaults_match_the_existing_queries() {
+        let options = SearchOptions::new("ibuprofen");
+        assert_eq!(options.highlight_fields, vec!["content"]);
+        assert_eq!(options.scoring_profile, Some("preferKeywords".to_string()));
+        assert_eq!(options.search_mode, SearchMode::All);
+        assert_eq!(options.query_type, QueryType::Full);
+        assert_eq!(options.top, None);
+        assert!(!options.include_count);
+    }
+
+    #[test]
+    fn pagination_sets_top_and_skip() {
+        let options = SearchOptions::new("ibuprofen").pagination(AzurePagination {
+            result_count: 10,
+            offset: 20,
+        });
+        assert_eq!(options.top, Some(10));
+        assert_eq!(options.skip, Some(20));
+    }
+}
@@ -0,0 +1,155 @@
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+
+/// Other names to search for alongside a word or phrase, e.g. `acetaminophen` for `paracetamol`.
+///
+/// Rules use the Solr format of Azure synonym maps, one per line: `a, b, c` makes the names
+/// interchangeable, while `a => b, c` only expands searches for `a`.
+///
+/// ```
+/// use search_client::SynonymMap;
+///
+/// let synonyms = SynonymMap::from_solr("paracetamol, acetaminophen\nadvil => ibuprofen");
+///
+/// assert_eq!(synonyms.expand("Acetaminophen"), vec!["paracetamol"]);
+/// assert_eq!(synonyms.expand("advil"), vec!["ibuprofen"]);
+/// assert!(synonyms.expand("ibuprofen").is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SynonymMap {
+    expansions: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct SynonymMapDefinition {
+    synonyms: String,
+}
+
+impl SynonymMap {
+    pub fn from_solr(rules: &str) -> Self {
+        let mut synonyms = Self::default();
+        for rule in rules.lines().map(str::trim) {
+            if rule.is_empty() || rule.starts_with('#') {
+                continue;
+            }
+            match rule.splitn(2, "=>").collect::<Vec<_>>()[..] {
+                [names, expansions] => {
+                    let expansions = names_in(expansions);
+                    for name in names_in(names) {
+                        synonyms.add(&name, &expansions);
+                    }
+                }
+                _ => {
+                    let names = names_in(rule);
+                    for name in &names {
+                        synonyms.add(name, &names);
+                    }
+                }
+            }
+        }
+        synonyms
+    }
+
+    /// Reads a synonym map definition as published to Azure (see `search/definitions/synonymmaps`),
+    /// so that the same rules can be applied by the client.
+    pub fn from_definition(definition: &str) -> Result<Self, serde_json::Error> {
+        let definition: SynonymMapDefinition = serde_json::from_str(definition)?;
+        Ok(Self::from_solr(&definition.synonyms))
+    }
+
+    /// The other names for `name`, ignoring case.
+    pub fn expand(&self, name: &str) -> Vec<&str> {
+        let name = normalize(name);
+        self.expansions
+            .get(&name)
+            .map(|expansions| {
+                expansions
+                    .iter()
+                    .filter(|expansion| **expansion != name)
+                    .map(String::as_str)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn add(&mut self, name: &str, expansions: &[String]) {
+        let existing = self.expansions.entry(name.to_string()).or_default();
+        for expansion in expansions {
+            if !existing.contains(expansion) {
+                existing.push(expansion.clone());
+            }
+        }
+    }
+}
+
+fn names_in(names: &str) -> Vec<String> {
+    names
+        .split(',')
+        .map(normalize)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn equivalent_names_expand_to_each_other() {
+        let synonyms =
+            SynonymMap::from_solr("ciclosporin, cyclosporine, cyclosporin\n\n# comment\n");
+
+        assert_eq!(
+            synonyms.expand("ciclosporin"),
+            vec!["cyclosporine", "cyclosporin"]
+        );
+        assert_eq!(
+            synonyms.expand("CYCLOSPORIN"),
+            vec!["ciclosporin", "cyclosporine"]
+        );
+    }
+
+    #[test]
+    fn explicit_mappings_only_expand_the_left_hand_side() {
+        let synonyms = SynonymMap::from_solr("epipen => epipen, adrenaline");
+
+        assert_eq!(synonyms.expand("epipen"), vec!["adrenaline"]);
+        assert!(synonyms.expand("adrenaline").is_empty());
+    }
+
+    #[test]
+    fn phrases_are_matched_as_a_whole() {
+        let synonyms = SynonymMap::from_solr("glyceryl trinitrate, nitroglycerin");
+
+        assert_eq!(
+            synonyms.expand("Glyceryl  Trinitrate"),
+            vec!["nitroglycerin"]
+        );
+        assert_eq!(
+            synonyms.expand("nitroglycerin"),
+            vec!["glyceryl trinitrate"]
+        );
+        assert!(synonyms.expand("glyceryl").is_empty());
+    }
+
+    #[test]
+    fn reads_azure_synonym_map_definitions() {
+        let definition = r#"{
+            "name": "medicine-names",
+            "format": "solr",
+            "synonyms": "paracetamol, acetaminophen\nadrenaline, epinephrine"
+        }"#;
+
+        let synonyms = SynonymMap::from_definition(definition).unwrap();
+
+        assert_eq!(synonyms.expand("epinephrine"), vec!["adrenaline"]);
+    }
+}
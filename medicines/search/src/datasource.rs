@@ -1,19 +1,19 @@
 use crate::{
     azure_rest,
     env::{
-        get_from_env, DATASOURCE_NAME, SEARCH_API_ADMIN_KEY, SEARCH_SERVICE, STORAGE_ACCOUNT,
-        STORAGE_CONTAINER, STORAGE_MASTER_KEY,
+        get_from_env, get_search_service_url, DATASOURCE_NAME, SEARCH_API_ADMIN_KEY,
+        STORAGE_ACCOUNT, STORAGE_CONTAINER, STORAGE_MASTER_KEY,
     },
 };
 
 pub async fn create_datasource() -> Result<(), reqwest::Error> {
-    let search_service = get_from_env(SEARCH_SERVICE);
+    let search_service_url = get_search_service_url();
     let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
     let datasource_name = get_from_env(DATASOURCE_NAME);
     let storage_account = get_from_env(STORAGE_ACCOUNT);
     let storage_container = get_from_env(STORAGE_CONTAINER);
     let storage_master_key = get_from_env(STORAGE_MASTER_KEY);
-    let url = get_base_url(&search_service);
+    let url = get_base_url(&search_service_url);
 
     let datasource_definition = get_datasource_definition(
         get_raw_datasource_definition(),
@@ -29,20 +29,20 @@ pub async fn create_datasource() -> Result<(), reqwest::Error> {
 pub async fn delete_datasource() -> Result<(), reqwest::Error> {
     let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
     let datasource_name = get_from_env(DATASOURCE_NAME);
-    let search_service = get_from_env(SEARCH_SERVICE);
-    let url = get_resource_url(&search_service, &datasource_name);
+    let search_service_url = get_search_service_url();
+    let url = get_resource_url(&search_service_url, &datasource_name);
 
     azure_rest::make_delete_request(&url, &api_key).await
 }
 
-fn get_base_url(search_service: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/datasources?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", &search_service)
+fn get_base_url(search_service_url: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/datasources?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
 }
 
-fn get_resource_url(search_service: &str, datasource_name: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/datasources/DATASOURCE_NAME_PLACEHOLDER?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", search_service)
+fn get_resource_url(search_service_url: &str, datasource_name: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/datasources/DATASOURCE_NAME_PLACEHOLDER?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
         .replace("DATASOURCE_NAME_PLACEHOLDER", datasource_name)
 }
 
@@ -70,7 +70,7 @@ mod test {
     #[test]
     fn test_get_base_url() {
         assert_eq!(
-            get_base_url("service_name"),
+            get_base_url("https://service_name.search.windows.net"),
             "https://service_name.search.windows.net/datasources?api-version=2019-05-06"
                 .to_string()
         );
@@ -79,7 +79,7 @@ mod test {
     #[test]
     fn test_get_resource_url() {
         assert_eq!(
-        get_resource_url("service_name", "datasource_name"),
+        get_resource_url("https://service_name.search.windows.net", "datasource_name"),
         "https://service_name.search.windows.net/datasources/datasource_name?api-version=2019-05-06".to_string()
     );
     }
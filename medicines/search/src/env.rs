@@ -7,6 +7,7 @@ pub const DATASOURCE_NAME: &str = "DATASOURCE_NAME";
 pub const INDEX_NAME: &str = "INDEX_NAME";
 pub const INDEXER_NAME: &str = "INDEXER_NAME";
 pub const SEARCH_SERVICE: &str = "SEARCH_SERVICE";
+pub const SEARCH_SERVICE_URL: &str = "SEARCH_SERVICE_URL";
 pub const STORAGE_ACCOUNT: &str = "STORAGE_ACCOUNT";
 pub const STORAGE_CONTAINER: &str = "STORAGE_CONTAINER";
 pub const STORAGE_MASTER_KEY: &str = "STORAGE_MASTER_KEY";
@@ -16,6 +17,17 @@ pub fn get_from_env(environment_variable: &str) -> String {
         .unwrap_or_else(|e| format!("Set env variable {} first!, {:?}", environment_variable, e))
 }
 
+// Set `SEARCH_SERVICE_URL` to use something other than Azure, e.g. a local mock over http.
+pub fn get_search_service_url() -> String {
+    let url = env::var(SEARCH_SERVICE_URL).unwrap_or_else(|_| {
+        format!(
+            "https://{}.search.windows.net",
+            get_from_env(SEARCH_SERVICE)
+        )
+    });
+    url.trim_end_matches('/').to_string()
+}
+
 #[cfg(test)]
 mod test {
     use self::rand::Rng;
@@ -37,4 +49,10 @@ mod test {
         env::set_var(&random_env_var, "found");
         assert_eq!(get_from_env(&random_env_var), "found");
     }
+
+    #[test]
+    fn test_get_search_service_url_from_env() {
+        env::set_var(SEARCH_SERVICE_URL, "http://localhost:8080/");
+        assert_eq!(get_search_service_url(), "http://localhost:8080");
+    }
 }
@@ -1,11 +1,11 @@
 use crate::{
     azure_rest,
-    env::{get_from_env, INDEX_NAME, SEARCH_API_ADMIN_KEY, SEARCH_SERVICE},
+    env::{get_from_env, get_search_service_url, INDEX_NAME, SEARCH_API_ADMIN_KEY},
 };
 use reqwest::Url;
 
 pub async fn create_or_update_index(index_definition: &str) -> Result<(), reqwest::Error> {
-    let search_service = get_from_env(SEARCH_SERVICE);
+    let search_service_url = get_search_service_url();
     let index_name = get_from_env(INDEX_NAME);
     let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
     let raw_index_definition;
@@ -14,7 +14,7 @@ pub async fn create_or_update_index(index_definition: &str) -> Result<(), reqwes
         _ => raw_index_definition = get_default_raw_index_definition(),
     }
     let index_definition = get_index_definition(raw_index_definition, &index_name);
-    let mut url = Url::parse(&get_base_url(&search_service)).unwrap();
+    let mut url = Url::parse(&get_base_url(&search_service_url)).unwrap();
     url.set_path(&format!("{}/{}", url.path(), index_name));
 
     azure_rest::make_put_request_with_body(index_definition, url, &api_key).await
@@ -23,20 +23,20 @@ pub async fn create_or_update_index(index_definition: &str) -> Result<(), reqwes
 pub async fn delete_index() -> Result<(), reqwest::Error> {
     let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
     let index_name = get_from_env(INDEX_NAME);
-    let search_service = get_from_env(SEARCH_SERVICE);
-    let url = get_resource_url(&search_service, &index_name);
+    let search_service_url = get_search_service_url();
+    let url = get_resource_url(&search_service_url, &index_name);
 
     azure_rest::make_delete_request(&url, &api_key).await
 }
 
-fn get_base_url(search_service: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/indexes/?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", search_service)
+fn get_base_url(search_service_url: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/indexes/?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
 }
 
-fn get_resource_url(search_service: &str, index_name: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/indexes/INDEX_NAME_PLACEHOLDER?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", search_service)
+fn get_resource_url(search_service_url: &str, index_name: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/indexes/INDEX_NAME_PLACEHOLDER?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
         .replace("INDEX_NAME_PLACEHOLDER", index_name)
 }
 
@@ -58,7 +58,7 @@ mod test {
     #[test]
     fn test_get_base_url() {
         assert_eq!(
-            get_base_url("service_name"),
+            get_base_url("https://service_name.search.windows.net"),
             "https://service_name.search.windows.net/indexes/?api-version=2019-05-06".to_string()
         );
     }
@@ -66,7 +66,7 @@ mod test {
     #[test]
     fn test_get_resource_url() {
         assert_eq!(
-            get_resource_url("service_name", "index_name"),
+            get_resource_url("https://service_name.search.windows.net", "index_name"),
             "https://service_name.search.windows.net/indexes/index_name?api-version=2019-05-06"
                 .to_string()
         );
@@ -1,8 +1,8 @@
 use crate::{
     azure_rest,
     env::{
-        get_from_env, DATASOURCE_NAME, INDEXER_NAME, INDEX_NAME, SEARCH_API_ADMIN_KEY,
-        SEARCH_SERVICE,
+        get_from_env, get_search_service_url, DATASOURCE_NAME, INDEXER_NAME, INDEX_NAME,
+        SEARCH_API_ADMIN_KEY,
     },
 };
 
@@ -11,7 +11,7 @@ pub async fn create_indexer(indexer_definition: &str) -> Result<(), reqwest::Err
     let datasource_name = get_from_env(DATASOURCE_NAME);
     let index_name = get_from_env(INDEX_NAME);
     let indexer_name = get_from_env(INDEXER_NAME);
-    let search_service = get_from_env(SEARCH_SERVICE);
+    let search_service_url = get_search_service_url();
     let raw_indexer_definition;
     match indexer_definition {
         "bmgf" => raw_indexer_definition = get_bmgf_raw_indexer_definition(),
@@ -23,7 +23,7 @@ pub async fn create_indexer(indexer_definition: &str) -> Result<(), reqwest::Err
         &index_name,
         &indexer_name,
     );
-    let url = get_base_url(&search_service);
+    let url = get_base_url(&search_service_url);
 
     azure_rest::make_post_request_with_body(indexer_definition, &url, &api_key).await
 }
@@ -31,8 +31,8 @@ pub async fn create_indexer(indexer_definition: &str) -> Result<(), reqwest::Err
 pub async fn delete_indexer() -> Result<(), reqwest::Error> {
     let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
     let indexer_name = get_from_env(INDEXER_NAME);
-    let search_service = get_from_env(SEARCH_SERVICE);
-    let url = get_resource_url(&search_service, &indexer_name);
+    let search_service_url = get_search_service_url();
+    let url = get_resource_url(&search_service_url, &indexer_name);
 
     azure_rest::make_delete_request(&url, &api_key).await
 }
@@ -40,8 +40,8 @@ pub async fn delete_indexer() -> Result<(), reqwest::Error> {
 pub async fn run_indexer() -> Result<(), reqwest::Error> {
     let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
     let indexer_name = get_from_env(INDEXER_NAME);
-    let search_service = get_from_env(SEARCH_SERVICE);
-    let url = get_run_url(&search_service, &indexer_name);
+    let search_service_url = get_search_service_url();
+    let url = get_run_url(&search_service_url, &indexer_name);
 
     azure_rest::make_post_request(&url, &api_key).await
 }
@@ -49,8 +49,8 @@ pub async fn run_indexer() -> Result<(), reqwest::Error> {
 pub async fn reset_indexer() -> Result<(), reqwest::Error> {
     let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
     let indexer_name = get_from_env(INDEXER_NAME);
-    let search_service = get_from_env(SEARCH_SERVICE);
-    let url = get_reset_url(&search_service, &indexer_name);
+    let search_service_url = get_search_service_url();
+    let url = get_reset_url(&search_service_url, &indexer_name);
 
     azure_rest::make_post_request(&url, &api_key).await
 }
@@ -75,26 +75,26 @@ fn get_indexer_definition(
         .replace("INDEXER_NAME_PLACEHOLDER", indexer_name)
 }
 
-fn get_base_url(search_service: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/indexers?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", &search_service)
+fn get_base_url(search_service_url: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/indexers?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
 }
 
-fn get_resource_url(search_service: &str, indexer_name: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/indexers/INDEXER_NAME_PLACEHOLDER?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", &search_service)
+fn get_resource_url(search_service_url: &str, indexer_name: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/indexers/INDEXER_NAME_PLACEHOLDER?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
         .replace("INDEXER_NAME_PLACEHOLDER", &indexer_name)
 }
 
-fn get_run_url(search_service: &str, indexer_name: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/indexers/INDEXER_NAME_PLACEHOLDER/run?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", &search_service)
+fn get_run_url(search_service_url: &str, indexer_name: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/indexers/INDEXER_NAME_PLACEHOLDER/run?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
         .replace("INDEXER_NAME_PLACEHOLDER", &indexer_name)
 }
 
-fn get_reset_url(search_service: &str, indexer_name: &str) -> String {
-    "https://SEARCH_SERVICE_PLACEHOLDER.search.windows.net/indexers/INDEXER_NAME_PLACEHOLDER/reset?api-version=2019-05-06"
-        .replace("SEARCH_SERVICE_PLACEHOLDER", &search_service)
+fn get_reset_url(search_service_url: &str, indexer_name: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/indexers/INDEXER_NAME_PLACEHOLDER/reset?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
         .replace("INDEXER_NAME_PLACEHOLDER", &indexer_name)
 }
 
@@ -104,7 +104,7 @@ mod test {
     #[test]
     fn test_get_base_url() {
         assert_eq!(
-            get_base_url("service_name"),
+            get_base_url("https://service_name.search.windows.net"),
             "https://service_name.search.windows.net/indexers?api-version=2019-05-06".to_string()
         );
     }
@@ -112,7 +112,7 @@ mod test {
     #[test]
     fn test_get_resource_url() {
         assert_eq!(
-            get_resource_url("service_name", "indexer_name"),
+            get_resource_url("https://service_name.search.windows.net", "indexer_name"),
             "https://service_name.search.windows.net/indexers/indexer_name?api-version=2019-05-06"
                 .to_string()
         );
@@ -121,7 +121,7 @@ mod test {
     #[test]
     fn test_get_run_url() {
         assert_eq!(
-        get_run_url("service_name", "indexer_name"),
+        get_run_url("https://service_name.search.windows.net", "indexer_name"),
         "https://service_name.search.windows.net/indexers/indexer_name/run?api-version=2019-05-06".to_string()
     );
     }
@@ -129,7 +129,7 @@ mod test {
     #[test]
     fn test_get_reset_url() {
         assert_eq!(
-        get_reset_url("service_name", "indexer_name"),
+        get_reset_url("https://service_name.search.windows.net", "indexer_name"),
         "https://service_name.search.windows.net/indexers/indexer_name/reset?api-version=2019-05-06".to_string()
     );
     }
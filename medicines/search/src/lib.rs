@@ -3,3 +3,5 @@ pub mod datasource;
 mod env;
 pub mod index;
 pub mod indexer;
+pub mod query_report;
+pub mod synonym_map;
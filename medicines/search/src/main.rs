@@ -2,7 +2,7 @@
 extern crate clap;
 
 use clap::App;
-use search::{datasource, index, indexer};
+use search::{datasource, index, indexer, query_report, synonym_map};
 
 #[tokio::main]
 async fn main() {
@@ -25,6 +25,30 @@ async fn main() {
         ("delete_index", Some(_m)) => index::delete_index()
             .await
             .expect("Failed to delete index."),
+        ("create_synonym_map", Some(m)) => {
+            let synonym_map_name = m
+                .value_of("synonym_map")
+                .unwrap_or(synonym_map::DEFAULT_SYNONYM_MAP);
+            synonym_map::create_synonym_map(synonym_map_name)
+                .await
+                .expect("Failed to create synonym map")
+        }
+        ("update_synonym_map", Some(m)) => {
+            let synonym_map_name = m
+                .value_of("synonym_map")
+                .unwrap_or(synonym_map::DEFAULT_SYNONYM_MAP);
+            synonym_map::update_synonym_map(synonym_map_name)
+                .await
+                .expect("Failed to update synonym map")
+        }
+        ("delete_synonym_map", Some(m)) => {
+            let synonym_map_name = m
+                .value_of("synonym_map")
+                .unwrap_or(synonym_map::DEFAULT_SYNONYM_MAP);
+            synonym_map::delete_synonym_map(synonym_map_name)
+                .await
+                .expect("Failed to delete synonym map.")
+        }
         ("create_indexer", Some(m)) => {
             let indexer_definition = m.value_of("indexer").unwrap_or("default");
             indexer::create_indexer(indexer_definition)
@@ -40,6 +64,18 @@ async fn main() {
         ("run_indexer", Some(_m)) => indexer::run_indexer()
             .await
             .expect("Failed to run indexer."),
+        ("query_report", Some(m)) => {
+            let file = m
+                .value_of("file")
+                .unwrap_or(query_report::DEFAULT_ANALYTICS_FILE);
+            let top = if m.is_present("top") {
+                value_t_or_exit!(m, "top", usize)
+            } else {
+                query_report::DEFAULT_REPORT_SIZE
+            };
+            query_report::print_zero_result_queries(file, top)
+                .expect("Failed to report zero-result queries.")
+        }
         _ => panic!("Subcommand not recognized."),
     }
 }
@@ -0,0 +1,77 @@
+use search_client::SearchEvent;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+pub const DEFAULT_ANALYTICS_FILE: &str = "search-analytics.jsonl";
+pub const DEFAULT_REPORT_SIZE: usize = 20;
+
+/// Prints the searches that most often found nothing, from a file of search events written
+/// by the API with `SEARCH_ANALYTICS=file`. These are the first candidates for new synonyms.
+pub fn print_zero_result_queries(path: &str, top: usize) -> Result<(), io::Error> {
+    let file = File::open(path)?;
+    let queries = zero_result_queries(BufReader::new(file), top)?;
+
+    println!("{:>8}  term", "searches");
+    for (term, searches) in queries {
+        println!("{:>8}  {}", searches, term);
+    }
+    Ok(())
+}
+
+/// The `top` terms with the most searches that found nothing, most first. Failed searches
+/// and lines that aren't search events are ignored.
+fn zero_result_queries(
+    reader: impl BufRead,
+    top: usize,
+) -> Result<Vec<(String, usize)>, io::Error> {
+    let mut counts = HashMap::new();
+    for line in reader.lines() {
+        let event = match serde_json::from_str::<SearchEvent>(&line?) {
+            Ok(event) => event,
+            Err(_) => continue,
+        };
+        if event.result_count == Some(0) && !event.term.is_empty() {
+            *counts.entry(event.term).or_insert(0) += 1;
+        }
+    }
+
+    let mut queries = counts.into_iter().collect::<Vec<_>>();
+    queries.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    queries.truncate(top);
+    Ok(queries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(term: &str, result_count: Option<u64>) -> String {
+        format!(
+            r#"{{"timestamp":"2020-09-01T10:00:00Z","index":"products-index","term":"{}","filter":null,"result_count":{},"latency_ms":20,"top":10,"skip":0}}"#,
+            term,
+            result_count.map_or("null".to_string(), |count| count.to_string())
+        )
+    }
+
+    #[test]
+    fn test_zero_result_queries_are_ranked_by_searches() {
+        let lines = vec![
+            event("nurofen", Some(0)),
+            event("ibuprofen", Some(12)),
+            event("calpol", Some(0)),
+            event("nurofen", Some(0)),
+            event("nurofen", None),
+            "not an event".to_string(),
+            event("anadin", Some(0)),
+        ]
+        .join("\n");
+
+        assert_eq!(
+            zero_result_queries(lines.as_bytes(), 2).unwrap(),
+            vec![("nurofen".to_string(), 2), ("anadin".to_string(), 1)]
+        );
+    }
+}
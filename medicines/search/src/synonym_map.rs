@@ -0,0 +1,86 @@
+use crate::{
+    azure_rest,
+    env::{get_from_env, get_search_service_url, SEARCH_API_ADMIN_KEY},
+};
+use reqwest::Url;
+
+pub const DEFAULT_SYNONYM_MAP: &str = "medicine-names";
+
+pub async fn create_synonym_map(synonym_map_name: &str) -> Result<(), reqwest::Error> {
+    let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
+    let search_service_url = get_search_service_url();
+    let synonym_map_definition = get_synonym_map_definition(synonym_map_name);
+    let url = get_base_url(&search_service_url);
+
+    azure_rest::make_post_request_with_body(synonym_map_definition, &url, &api_key).await
+}
+
+pub async fn update_synonym_map(synonym_map_name: &str) -> Result<(), reqwest::Error> {
+    let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
+    let search_service_url = get_search_service_url();
+    let synonym_map_definition = get_synonym_map_definition(synonym_map_name);
+    let url = Url::parse(&get_resource_url(&search_service_url, synonym_map_name)).unwrap();
+
+    azure_rest::make_put_request_with_body(synonym_map_definition, url, &api_key).await
+}
+
+pub async fn delete_synonym_map(synonym_map_name: &str) -> Result<(), reqwest::Error> {
+    let api_key = get_from_env(SEARCH_API_ADMIN_KEY);
+    let search_service_url = get_search_service_url();
+    let url = get_resource_url(&search_service_url, synonym_map_name);
+
+    azure_rest::make_delete_request(&url, &api_key).await
+}
+
+fn get_base_url(search_service_url: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/synonymmaps?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
+}
+
+fn get_resource_url(search_service_url: &str, synonym_map_name: &str) -> String {
+    "SEARCH_SERVICE_URL_PLACEHOLDER/synonymmaps/SYNONYM_MAP_NAME_PLACEHOLDER?api-version=2019-05-06"
+        .replace("SEARCH_SERVICE_URL_PLACEHOLDER", search_service_url)
+        .replace("SYNONYM_MAP_NAME_PLACEHOLDER", synonym_map_name)
+}
+
+// Index definitions refer to synonym maps by name, so each definition keeps the name it is
+// published under rather than taking it from the environment.
+fn get_synonym_map_definition(synonym_map_name: &str) -> String {
+    match synonym_map_name {
+        DEFAULT_SYNONYM_MAP => {
+            include_str!("../definitions/synonymmaps/medicine-names.json").to_string()
+        }
+        _ => panic!("No synonym map definition named {}", synonym_map_name),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_get_base_url() {
+        assert_eq!(
+            get_base_url("https://service_name.search.windows.net"),
+            "https://service_name.search.windows.net/synonymmaps?api-version=2019-05-06"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_get_resource_url() {
+        assert_eq!(
+            get_resource_url("https://service_name.search.windows.net", "medicine-names"),
+            "https://service_name.search.windows.net/synonymmaps/medicine-names?api-version=2019-05-06"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_synonym_map_definition_is_published_under_its_name() {
+        let definition = get_synonym_map_definition(DEFAULT_SYNONYM_MAP);
+
+        assert!(definition.contains(r#""name": "medicine-names""#));
+        assert!(definition.contains(r#""format": "solr""#));
+        assert!(definition.contains("paracetamol, acetaminophen"));
+    }
+}